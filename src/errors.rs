@@ -0,0 +1,36 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/.
+Copyright 2021 Peter Dunne */
+//! Errors
+//! Error type returned by the field routines
+//!
+
+use std::error::Error;
+use std::fmt;
+
+/// Errors returned by magnet and field routines
+#[derive(Debug)]
+pub enum MagnetError {
+    /// A parameter is outside of its valid range
+    InvalidParameter(String),
+    /// An underlying field calculation failed
+    Calculation(String),
+}
+
+impl fmt::Display for MagnetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MagnetError::InvalidParameter(msg) => write!(f, "invalid parameter: {}", msg),
+            MagnetError::Calculation(msg) => write!(f, "calculation failed: {}", msg),
+        }
+    }
+}
+
+impl Error for MagnetError {}
+
+impl From<Box<dyn Error>> for MagnetError {
+    fn from(error: Box<dyn Error>) -> Self {
+        MagnetError::Calculation(error.to_string())
+    }
+}
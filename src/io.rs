@@ -0,0 +1,22 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/.
+Copyright 2021 Peter Dunne */
+//!
+//! The io module contains routines for exporting calculated fields to
+//! formats used by other tools, and for importing magnet geometry
+//!
+
+#[cfg(feature = "arrow")]
+pub mod arrow;
+#[cfg(feature = "hdf5")]
+pub mod hdf5;
+pub mod mesh;
+pub mod vdb;
+
+/// Returns a path in the temporary directory unique to this test run, so
+/// that concurrent runs do not overwrite each other's files
+#[cfg(test)]
+pub(crate) fn temp_path(name: &str) -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("magnet_rs_{}_{}", std::process::id(), name))
+}
@@ -0,0 +1,117 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/.
+Copyright 2021 Peter Dunne */
+//! Parquet export
+//!
+//! Writes field results as an Apache Parquet table, which can be read
+//! directly by Pandas, Polars, Spark and other dataframe tools. Requires the
+//! `arrow` feature.
+//!
+//! # Schema
+//! | Column | Type      | Content                    |
+//! |--------|-----------|----------------------------|
+//! | `x`    | `Float64` | x coordinate of the point  |
+//! | `y`    | `Float64` | y coordinate of the point  |
+//! | `Bx`   | `Float64` | x component of the field   |
+//! | `By`   | `Float64` | y component of the field   |
+
+use std::error::Error;
+use std::fs::File;
+use std::path::Path;
+use std::sync::Arc;
+
+use arrow_array::{ArrayRef, Float64Array, RecordBatch};
+use arrow_schema::{DataType, Field, Schema};
+use parquet::arrow::ArrowWriter;
+
+use crate::utils::points2::Point2;
+
+/// Returns the schema of the tables written by [`write_parquet`]
+pub fn field_schema() -> Schema {
+    Schema::new(
+        ["x", "y", "Bx", "By"]
+            .iter()
+            .map(|name| Field::new(*name, DataType::Float64, false))
+            .collect::<Vec<Field>>(),
+    )
+}
+
+/// Writes `points` and the `field` at each point to `path` as a Parquet file
+/// with columns `x`, `y`, `Bx` and `By`.
+pub fn write_parquet<P: AsRef<Path>>(
+    path: P,
+    points: &[Point2],
+    field: &[Point2],
+) -> Result<(), Box<dyn Error>> {
+    if points.len() != field.len() {
+        return Err(format!(
+            "{} points do not match {} field values",
+            points.len(),
+            field.len()
+        )
+        .into());
+    }
+
+    let column = |values: Vec<f64>| Arc::new(Float64Array::from(values)) as ArrayRef;
+    let schema = Arc::new(field_schema());
+    let batch = RecordBatch::try_new(
+        schema.clone(),
+        vec![
+            column(points.iter().map(|p| p.x).collect()),
+            column(points.iter().map(|p| p.y).collect()),
+            column(field.iter().map(|b| b.x).collect()),
+            column(field.iter().map(|b| b.y).collect()),
+        ],
+    )?;
+
+    let mut writer = ArrowWriter::try_new(File::create(path)?, schema, None)?;
+    writer.write(&batch)?;
+    writer.close()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::io::arrow::{field_schema, write_parquet};
+    use crate::io::temp_path;
+    use crate::utils::points2::Point2;
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+    use std::fs::File;
+
+    #[test]
+    fn parquet_schema_and_rows() {
+        let points: Vec<Point2> = (0..25).map(|i| Point2::new(i as f64, -1.0)).collect();
+        let field: Vec<Point2> = points.iter().map(|p| Point2::new(0.1 * p.x, 0.5)).collect();
+
+        let path = temp_path("field_test.parquet");
+        write_parquet(&path, &points, &field).unwrap();
+
+        let reader = SerializedFileReader::new(File::open(&path).unwrap()).unwrap();
+        let metadata = reader.metadata().file_metadata();
+        assert_eq!(25, metadata.num_rows());
+
+        let columns: Vec<&str> = metadata
+            .schema_descr()
+            .columns()
+            .iter()
+            .map(|column| column.name())
+            .collect();
+        let expected: Vec<String> = field_schema()
+            .fields()
+            .iter()
+            .map(|f| f.name().clone())
+            .collect();
+        assert_eq!(expected, columns);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn mismatched_lengths() {
+        let path = temp_path("mismatch_test.parquet");
+        let result = write_parquet(&path, &[Point2::new(0.0, 0.0)], &[]);
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,101 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/.
+Copyright 2021 Peter Dunne */
+//! HDF5 export
+//!
+//! Writes field results to an HDF5 file, the usual exchange format for
+//! scientific data in h5py, MATLAB and ParaView. Requires the `hdf5` feature
+//! and the HDF5 C library.
+//!
+//! # Layout
+//! | Object   | Kind              | Content                              |
+//! |----------|-------------------|--------------------------------------|
+//! | `points` | `f64` `[n, 2]`    | `(x, y)` coordinates of each point   |
+//! | `field`  | `f64` `[n, 2]`    | `(Bx, By)` field at each point       |
+//! | metadata | string attributes | one root attribute per metadata key  |
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::Path;
+
+use hdf5::types::VarLenUnicode;
+use hdf5::File;
+
+use crate::utils::points2::Point2;
+
+/// Writes `points` and the `field` at each point to `path` as the `[n, 2]`
+/// datasets `points` and `field`, with each `metadata` entry stored as a
+/// string attribute on the root group.
+pub fn write_field<P: AsRef<Path>>(
+    path: P,
+    points: &[Point2],
+    field: &[Point2],
+    metadata: &HashMap<String, String>,
+) -> Result<(), Box<dyn Error>> {
+    if points.len() != field.len() {
+        return Err(format!(
+            "{} points do not match {} field values",
+            points.len(),
+            field.len()
+        )
+        .into());
+    }
+
+    let file = File::create(path)?;
+    for (name, values) in &[("points", points), ("field", field)] {
+        let flat: Vec<f64> = values.iter().flat_map(|p| [p.x, p.y]).collect();
+        file.new_dataset::<f64>()
+            .shape([values.len(), 2])
+            .create(*name)?
+            .write_raw(&flat)?;
+    }
+
+    for (key, value) in metadata {
+        file.new_attr::<VarLenUnicode>()
+            .create(key.as_str())?
+            .write_scalar(&value.parse::<VarLenUnicode>()?)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::io::hdf5::write_field;
+    use crate::io::temp_path;
+    use crate::utils::points2::Point2;
+    use hdf5::types::VarLenUnicode;
+    use std::collections::HashMap;
+
+    #[test]
+    fn datasets_and_metadata() {
+        let points: Vec<Point2> = (0..25).map(|i| Point2::new(i as f64, -1.0)).collect();
+        let field: Vec<Point2> = points.iter().map(|p| Point2::new(0.1 * p.x, 0.5)).collect();
+        let mut metadata = HashMap::new();
+        metadata.insert("magnet".to_string(), "Rectangle".to_string());
+
+        let path = temp_path("field_test.h5");
+        write_field(&path, &points, &field, &metadata).unwrap();
+
+        let file = hdf5::File::open(&path).unwrap();
+        for name in &["points", "field"] {
+            assert_eq!(vec![25, 2], file.dataset(name).unwrap().shape());
+        }
+        let values = file.dataset("field").unwrap().read_raw::<f64>().unwrap();
+        assert_eq!((field[24].x, field[24].y), (values[48], values[49]));
+
+        let magnet = file.attr("magnet").unwrap();
+        let magnet = magnet.read_scalar::<VarLenUnicode>().unwrap();
+        assert_eq!("Rectangle", magnet.as_str());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn mismatched_lengths() {
+        let path = temp_path("mismatch_test.h5");
+        let result = write_field(&path, &[Point2::new(0.0, 0.0)], &[], &HashMap::new());
+        assert!(result.is_err());
+    }
+}
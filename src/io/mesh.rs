@@ -0,0 +1,135 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/.
+Copyright 2021 Peter Dunne */
+//! Polygon outline import
+//!
+//! Reads 2D cross-sections, e.g. exported from CAD, as uniformly magnetised
+//! polygons.
+//!
+//! # Format
+//! A plain text list of vertices, one `x y` pair per line, separated by
+//! whitespace or a comma. A blank line ends a polygon, so a file can hold
+//! several. Text after a `#` is a comment. The vertices of a polygon may run
+//! either way round.
+//!
+//! ```text
+//! # a 1 x 1 square and a triangle
+//! -0.5 -0.5
+//! 0.5 -0.5
+//! 0.5 0.5
+//! -0.5 0.5
+//!
+//! 1.0, 0.0
+//! 2.0, 0.0
+//! 1.5, 1.0
+//! ```
+
+use crate::errors::MagnetError;
+use crate::magnets::magnet2d::collection::MagnetCollection2D;
+use crate::magnets::magnet2d::polygon::PolygonMagnet;
+use crate::utils::points2::Point2;
+use std::fs;
+use std::path::Path;
+
+/// Returns a collection of the polygons in the outline file at `path`, each
+/// uniformly magnetised with `magnetisation`, in T, in the global frame.
+///
+/// Returns an error if the file cannot be read, a line is not a pair of
+/// numbers, or a polygon has fewer than 3 vertices or no area.
+pub fn load_polygon_scene(
+    path: &Path,
+    magnetisation: Point2,
+) -> Result<MagnetCollection2D, MagnetError> {
+    let text = fs::read_to_string(path).map_err(|error| {
+        MagnetError::InvalidParameter(format!("cannot read {}: {}", path.display(), error))
+    })?;
+
+    let mut polygons = vec![Vec::new()];
+    for (number, line) in text.lines().enumerate() {
+        let content = line.split('#').next().unwrap_or("").trim();
+        if content.is_empty() {
+            // Comment lines do not end a polygon, blank lines do
+            if line.trim().is_empty()
+                && matches!(polygons.last(), Some(polygon) if !polygon.is_empty())
+            {
+                polygons.push(Vec::new());
+            }
+            continue;
+        }
+
+        let values = content
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|s| !s.is_empty())
+            .map(str::parse::<f64>)
+            .collect::<Result<Vec<f64>, _>>();
+        match values.as_deref() {
+            Ok([x, y]) => polygons.last_mut().unwrap().push(Point2::new(*x, *y)),
+            _ => {
+                return Err(MagnetError::InvalidParameter(format!(
+                    "line {} of {} is not an `x y` vertex: {:?}",
+                    number + 1,
+                    path.display(),
+                    line
+                )))
+            }
+        }
+    }
+
+    let mut scene = MagnetCollection2D::new();
+    for vertices in polygons.into_iter().filter(|v| !v.is_empty()) {
+        scene.push(PolygonMagnet::new(vertices, magnetisation)?);
+    }
+    Ok(scene)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::io::mesh::load_polygon_scene;
+    use crate::io::temp_path;
+    use crate::magnets::magnet2d::rectangle_field::get_field_rectangle;
+    use crate::magnets::magnet2d::Rectangle;
+    use crate::utils::points2::{Point2, Points2};
+
+    #[test]
+    fn square_outline_matches_rectangle() {
+        let path = temp_path("square_outline.txt");
+        std::fs::write(
+            &path,
+            "# unit square\n-0.5 -0.5\n0.5, -0.5\n 0.5 0.5 # corner\n-0.5 0.5\n\n",
+        )
+        .unwrap();
+        let scene = load_polygon_scene(&path, Point2::new(0.0, 1.0)).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(1, scene.len());
+
+        let rectangle = Rectangle::new(1.0, 1.0, (0.0, 0.0), 0.0, 1.0, 90.0);
+        for point in &[Point2::new(0.3, 1.2), Point2::new(-0.2, 0.1)] {
+            let expected = get_field_rectangle(&rectangle, point).unwrap();
+            let field = scene.get_field(point).unwrap();
+            assert!((field - expected).magnitude() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn blank_lines_separate_polygons() {
+        let path = temp_path("two_outlines.txt");
+        std::fs::write(&path, "0 0\n1 0\n0 1\n\n\n2 0\n3 0\n2 1\n").unwrap();
+        let scene = load_polygon_scene(&path, Point2::new(1.0, 0.0)).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(2, scene.len());
+    }
+
+    #[test]
+    fn malformed_outline() {
+        let path = temp_path("bad_outline.txt");
+        std::fs::write(&path, "0 0\n1 0 2\n0 1\n").unwrap();
+        assert!(load_polygon_scene(&path, Point2::new(1.0, 0.0)).is_err());
+        std::fs::write(&path, "0 0\n1 0\n").unwrap();
+        assert!(load_polygon_scene(&path, Point2::new(1.0, 0.0)).is_err());
+        std::fs::remove_file(&path).unwrap();
+
+        let missing = temp_path("missing_outline.txt");
+        assert!(load_polygon_scene(&missing, Point2::new(1.0, 0.0)).is_err());
+    }
+}
@@ -17,6 +17,8 @@
 
 use core::f64;
 
+pub mod errors;
+pub mod io;
 pub mod magnets;
 pub mod utils;
 
@@ -41,6 +43,8 @@ pub const PI_6: f64 = PI / 6.0;
 pub const I_2PI: f64 = 1.0 / M2_PI;
 /// 1/(2*PI)
 pub const I_4PI: f64 = 1.0 / M4_PI;
+/// Permeability of free space (H/m)
+pub const MU0: f64 = 4e-7 * PI;
 /// Floating point cutoff for vector alignment
 pub const FP_CUTOFF: f64 = 1e-6;
 
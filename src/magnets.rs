@@ -16,4 +16,57 @@ Copyright 2021 Peter Dunne */
 pub mod magnet2d;
 pub mod magnet3d;
 
+use crate::errors::MagnetError;
+
 pub trait Magnet {}
+
+/// Checks that the inner radius of a hollow magnet is non-negative, where 0
+/// gives the solid shape
+pub(crate) fn check_inner_radius(inner_radius: f64) -> Result<(), MagnetError> {
+    if inner_radius.is_nan() || inner_radius < 0.0 {
+        return Err(MagnetError::InvalidParameter(format!(
+            "inner radius must be non-negative, got {}",
+            inner_radius
+        )));
+    }
+    Ok(())
+}
+
+/// Checks that the dimension `name` of a magnet is positive and finite
+pub(crate) fn check_size(name: &str, value: f64) -> Result<(), MagnetError> {
+    if value.is_finite() && value > 0.0 {
+        Ok(())
+    } else {
+        Err(MagnetError::InvalidParameter(format!(
+            "{} must be positive and finite, got {}",
+            name, value
+        )))
+    }
+}
+
+/// Checks that all `values` of the quantity `name`, e.g. the coordinates of a
+/// center, are finite
+pub(crate) fn check_finite(name: &str, values: &[f64]) -> Result<(), MagnetError> {
+    if values.iter().all(|value| value.is_finite()) {
+        Ok(())
+    } else {
+        Err(MagnetError::InvalidParameter(format!(
+            "{} must be finite, got {:?}",
+            name, values
+        )))
+    }
+}
+
+/// Checks that a magnetisation of magnitude `jr` with the given `components`
+/// is finite and non-zero
+pub(crate) fn check_magnetisation(jr: f64, components: &[f64]) -> Result<(), MagnetError> {
+    check_finite("magnetisation", components)?;
+    if jr.is_finite() && jr != 0.0 {
+        Ok(())
+    } else {
+        Err(MagnetError::InvalidParameter(format!(
+            "magnetisation must be non-zero and finite, got {}",
+            jr
+        )))
+    }
+}
@@ -6,20 +6,44 @@ Copyright 2021 Peter Dunne */
 //! This consists of modules for calculating magnetic fields due to magnetic
 //! objects in 2D, including:
 //!
+pub mod analysis;
+pub mod bonded;
 pub mod circle_field;
+pub mod circuit;
+pub mod collection;
+pub mod current;
+pub mod custom;
+pub mod design;
+pub mod dipole_field;
+pub mod expansion;
+pub mod extrusion;
+pub mod fitting;
+pub mod force;
+pub mod gradient;
+pub mod grid;
 pub mod line_field;
+pub mod periodic;
+pub mod polygon;
 pub mod rectangle_field;
+pub mod response;
+pub mod rounded;
+pub mod shield;
+pub mod thermal;
+pub mod wedge_field;
 
 use std::error::Error;
 use std::fmt;
 use std::ops::{Add, Mul};
 
+use crate::errors::MagnetError;
 use crate::magnets::magnet2d::circle_field::get_field_circle;
 use crate::magnets::magnet2d::rectangle_field::get_field_rectangle;
-use crate::magnets::Magnet;
-use crate::utils::points2::Point2;
+use crate::magnets::magnet2d::wedge_field::{get_field_annulus, get_field_wedge};
+use crate::magnets::{check_finite, check_inner_radius, check_magnetisation, check_size, Magnet};
+use crate::utils::points::Points;
+use crate::utils::points2::{Point2, Points2};
 // use crate::utils::points2::PolarPoint;
-use crate::PI;
+use crate::{MU0, PI};
 
 /// A 2D magnet
 ///
@@ -116,6 +140,175 @@ impl Rectangle {
             b: height.into() / 2.0,
         }
     }
+
+    /// Returns the magnetometric demagnetising factors `(N_x, N_y)` of the
+    /// rectangle, treated as the cross-section of an infinitely long bar.
+    ///
+    /// In 2D the two factors sum to one, $`N_x + N_y = 1`$, with
+    ///
+    /// ```math
+    /// \pi N_y = 2 \tan^{-1}\left(\frac{a}{b}\right)
+    /// + \frac{b}{2a} \ln \left( \frac{b^2}{a^2 + b^2} \right)
+    /// + \frac{a}{2b} \ln \left( \frac{a^2 + b^2}{a^2} \right)
+    /// ```
+    pub fn demag_factors(&self) -> (f64, f64) {
+        let a = self.a;
+        let b = self.b;
+        let a_sq_plus_b_sq = a.powi(2) + b.powi(2);
+
+        let n_y = (2.0 * (a / b).atan()
+            + b / (2.0 * a) * (b.powi(2) / a_sq_plus_b_sq).ln()
+            + a / (2.0 * b) * (a_sq_plus_b_sq / a.powi(2)).ln())
+            / PI;
+
+        (1.0 - n_y, n_y)
+    }
+
+    /// Returns the demagnetising factor along the magnetisation direction,
+    /// or zero for an unmagnetised magnet
+    fn magnetisation_demag_factor(&self) -> f64 {
+        if self.jr == 0.0 {
+            return 0.0;
+        }
+        let (n_x, n_y) = self.demag_factors();
+        (n_x * self.jx.powi(2) + n_y * self.jy.powi(2)) / self.jr.powi(2)
+    }
+
+    /// Returns the `(H, B)` working point of the magnet, in A/m and T.
+    ///
+    /// The working point is the intersection of the load line,
+    /// $`B = -\mu_0 H (1 - N) / N`$, with the linear recoil line
+    /// $`B = J_r + \mu_0 \mu_r H`$, where `mu_r` is the recoil permeability and
+    /// $`N`$ the demagnetising factor along the magnetisation direction.
+    /// A strongly negative `H` warns that the magnet is close to the knee of
+    /// its demagnetisation curve.
+    pub fn working_point(&self, mu_r: f64) -> (f64, f64) {
+        let n = self.magnetisation_demag_factor();
+
+        let denominator = mu_r * n + 1.0 - n;
+        let h = -self.jr * n / (MU0 * denominator);
+        let b = self.jr * (1.0 - n) / denominator;
+        (h, b)
+    }
+
+    /// Returns the `(H, B)` working point of the magnet, in A/m and T, after
+    /// a demagnetising field `applied_h`, in A/m and antiparallel to the
+    /// magnetisation, has been applied and removed.
+    ///
+    /// The intrinsic curve is the recoil line
+    /// $`J = J_r + \mu_0 (\mu_r - 1) H`$ down to a square knee at
+    /// $`H = -H_{cj}`$, the `intrinsic_coercivity`. While the field is
+    /// applied the internal field is
+    /// $`H = -(N J_r/\mu_0 + H_a)/(1 + N (\mu_r - 1))`$, and if it stays
+    /// above the knee the magnet returns to its [`Rectangle::working_point`].
+    /// Otherwise the magnetisation falls along the knee to the load line,
+    /// $`J = \mu_0 (H_{cj} - H_a)/N`$, and on removal recoils parallel to the
+    /// original line, to the reduced remanence
+    /// $`J_r' = J + \mu_0 (\mu_r - 1) H_{cj}`$, no lower than full reversal
+    /// $`-J_r`$.
+    pub fn recoil_working_point(
+        &self,
+        applied_h: f64,
+        mu_recoil: f64,
+        intrinsic_coercivity: f64,
+    ) -> (f64, f64) {
+        let n = self.magnetisation_demag_factor();
+        let chi = mu_recoil - 1.0;
+
+        let exposed_h = -(n * self.jr / MU0 + applied_h) / (1.0 + n * chi);
+        if exposed_h >= -intrinsic_coercivity {
+            return self.working_point(mu_recoil);
+        }
+
+        let knee_j = MU0 * (intrinsic_coercivity - applied_h) / n;
+        let remanence = (knee_j + MU0 * chi * intrinsic_coercivity).max(-self.jr);
+        if remanence == 0.0 {
+            return (0.0, 0.0);
+        }
+
+        let mut demagnetised = *self;
+        demagnetised.jx *= remanence / self.jr;
+        demagnetised.jy *= remanence / self.jr;
+        demagnetised.jr = remanence;
+        demagnetised.working_point(mu_recoil)
+    }
+
+    /// Returns the magnetisation vector $`\mathbf{J}`$, in T, in the global
+    /// frame.
+    ///
+    /// The angle `theta` is measured anticlockwise from the magnet's local
+    /// +x axis, so that with `alpha = 0` a positive `jr` at `theta = 0` gives
+    /// $`(J_r, 0)`$ and at `theta = 90` gives $`(0, J_r)`$. A negative `jr`
+    /// reverses the direction. The rotation `alpha` of the magnet rotates the
+    /// vector with it.
+    pub fn magnetisation_vector(&self) -> Point2 {
+        Point2::new(self.jx, self.jy).rotate(self.alpha * PI / 180.)
+    }
+
+    /// Returns a copy of the magnet with the global magnetisation vector
+    /// `j`, in T, the inverse of `magnetisation_vector`.
+    pub fn with_magnetisation(&self, j: Point2) -> Rectangle {
+        let local = j.rotate(-self.alpha * PI / 180.);
+        let mut magnet = *self;
+        magnet.jr = local.magnitude();
+        magnet.theta = local.y.atan2(local.x) * 180. / PI;
+        magnet.jx = local.x;
+        magnet.jy = local.y;
+        magnet
+    }
+
+    /// Returns the demagnetising field $`\mathbf{H} = (\mathbf{B} - \mathbf{J})/\mu_0`$,
+    /// in A/m, at a `point` inside the magnet.
+    ///
+    /// Unlike the B field returned by `get_field`, this is the field acting
+    /// on the magnetisation, which broadly opposes it. Returns an error for
+    /// points outside the magnet, where there is no magnetisation to
+    /// demagnetise.
+    pub fn internal_demag_field(&self, point: &Point2) -> Result<Point2, MagnetError> {
+        if !self.contains(point) {
+            return Err(MagnetError::InvalidParameter(format!(
+                "point {} is outside the magnet",
+                point
+            )));
+        }
+
+        let field = self.get_field(point)? - self.magnetisation_vector();
+        Ok(field.scale(1.0 / MU0))
+    }
+
+    /// Returns the mass and the moment of inertia about the z axis through
+    /// the center, per unit depth, for a `density` in kg/m^3,
+    ///
+    /// ```math
+    /// m = \rho w h, \qquad I_z = \frac{m}{12} (w^2 + h^2)
+    /// ```
+    ///
+    /// in kg/m and kg m.
+    pub fn mass_and_inertia(&self, density: f64) -> (f64, f64) {
+        let mass = density * self.width * self.height;
+        let inertia = mass * (self.width.powi(2) + self.height.powi(2)) / 12.0;
+        (mass, inertia)
+    }
+
+    /// Returns the magnet divided into `nx` x `ny` equal cells, with x the
+    /// fastest varying index of the cells along the magnet's local axes.
+    ///
+    /// Each cell has the magnet's rotation and magnetisation, so together
+    /// they give the magnet's field; callers set their own magnetisation to
+    /// model non-uniform magnets. Zero divisions give no cells.
+    pub fn cells(&self, nx: usize, ny: usize) -> Vec<Rectangle> {
+        let alpha = self.alpha * PI / 180.;
+        let (dx, dy) = (self.width / nx as f64, self.height / ny as f64);
+
+        (0..nx * ny)
+            .map(|k| {
+                let (i, j) = ((k % nx) as f64, (k / nx) as f64);
+                let local = Point2::new(-self.a + (i + 0.5) * dx, -self.b + (j + 0.5) * dy);
+                let center = self.center + local.rotate(alpha);
+                Rectangle::new(dx, dy, center, self.alpha, self.jr, self.theta)
+            })
+            .collect()
+    }
 }
 
 /// Implements Display for Rectangle magnets.
@@ -137,13 +330,85 @@ pub trait Magnet2D<T>: Magnet {
     fn get_center(&self) -> Point2;
     fn get_size(&self) -> Point2;
     fn get_j(self) -> Point2;
+    /// Returns true if `point` lies inside or on the boundary of the magnet.
+    /// The default, for sources without a body, is always false.
+    fn contains(&self, _point: &T) -> bool {
+        false
+    }
+
+    /// Returns an error if the magnet is malformed, e.g. with a NaN or
+    /// negative dimension or no magnetisation, so that its field would be
+    /// meaningless. Sources without such parameters are always valid.
+    fn validate(&self) -> Result<(), MagnetError> {
+        Ok(())
+    }
+}
+
+/// A rectangular magnet made up of uniformly magnetised cells, e.g. from
+/// [`Rectangle::cells`], with the field the sum of the fields of the cells
+///
+/// The cells tile the `outline` and share its rotation, so each
+/// implementation only provides the two and gets `Magnet2D` from them.
+pub trait CellMagnet2D {
+    /// Returns the rectangle filled by the cells
+    fn outline(&self) -> &Rectangle;
+    /// Returns the cells of the magnet
+    fn cells(&self) -> &[Rectangle];
+}
+
+impl<M: CellMagnet2D + Magnet> Magnet2D<Point2> for M {
+    fn get_field(&self, point: &Point2) -> Result<Point2, Box<dyn Error>> {
+        let mut field = Point2::zero();
+        for cell in self.cells() {
+            field += cell.get_field(point)?;
+        }
+        Ok(field)
+    }
+
+    fn get_center(&self) -> Point2 {
+        self.outline().center
+    }
+
+    fn get_size(&self) -> Point2 {
+        Point2::new(self.outline().width, self.outline().height)
+    }
+
+    /// Returns the mean magnetisation of the cells, in the frame of the
+    /// outline as for [`Rectangle`]
+    fn get_j(self) -> Point2 {
+        let cells = self.cells();
+        cells
+            .iter()
+            .fold(Point2::zero(), |sum, cell| sum + cell.get_j())
+            .scale(1.0 / cells.len() as f64)
+    }
+
+    fn contains(&self, point: &Point2) -> bool {
+        self.outline().contains(point)
+    }
+
+    fn validate(&self) -> Result<(), MagnetError> {
+        self.outline().validate()?;
+        if self.cells().is_empty() {
+            return Err(MagnetError::InvalidParameter(
+                "magnet has no cells".to_string(),
+            ));
+        }
+        Ok(())
+    }
 }
 
 impl Magnet for Rectangle {}
 
 impl Magnet2D<Point2> for Rectangle {
+    /// Returns the field at `point` in the global frame, accounting for the
+    /// magnet's center and its rotation `alpha` (in degrees). The
+    /// magnetisation angle `theta` is measured in the magnet's own frame.
     fn get_field(&self, point: &Point2) -> Result<Point2, Box<dyn Error>> {
-        get_field_rectangle(&self, point)
+        let alpha = self.alpha * PI / 180.;
+        let local_point = (*point - self.center).rotate(-alpha);
+        let local_field = get_field_rectangle(self, &local_point)?;
+        Ok(local_field.rotate(alpha))
     }
 
     fn get_center(&self) -> Point2 {
@@ -157,6 +422,18 @@ impl Magnet2D<Point2> for Rectangle {
     fn get_j(self) -> Point2 {
         Point2::new(self.jx, self.jy)
     }
+
+    fn contains(&self, point: &Point2) -> bool {
+        let local_point = (*point - self.center).rotate(-self.alpha * PI / 180.);
+        local_point.x.abs() <= self.a && local_point.y.abs() <= self.b
+    }
+
+    fn validate(&self) -> Result<(), MagnetError> {
+        check_size("width", self.width)?;
+        check_size("height", self.height)?;
+        check_finite("center", &[self.center.x, self.center.y, self.alpha])?;
+        check_magnetisation(self.jr, &[self.jx, self.jy])
+    }
 }
 
 #[derive(Copy, Clone)]
@@ -222,8 +499,14 @@ impl fmt::Display for Circle {
 impl Magnet for Circle {}
 
 impl Magnet2D<Point2> for Circle {
+    /// Returns the field at `point` in the global frame. As for rectangles,
+    /// the magnetisation angle `theta` is measured in the magnet's frame,
+    /// which is rotated by `alpha` (in degrees).
     fn get_field(&self, point: &Point2) -> Result<Point2, Box<dyn Error>> {
-        get_field_circle(&self, point)
+        let alpha = self.alpha * PI / 180.;
+        let local_point = (*point - self.center).rotate(-alpha);
+        let local_field = get_field_circle(self, &local_point)?;
+        Ok(local_field.rotate(alpha))
     }
 
     fn get_center(&self) -> Point2 {
@@ -237,4 +520,507 @@ impl Magnet2D<Point2> for Circle {
     fn get_j(self) -> Point2 {
         Point2::new(self.jx, self.jy)
     }
+
+    fn contains(&self, point: &Point2) -> bool {
+        (*point - self.center).magnitude() <= self.radius
+    }
+
+    fn validate(&self) -> Result<(), MagnetError> {
+        check_size("radius", self.radius)?;
+        check_finite("center", &[self.center.x, self.center.y, self.alpha])?;
+        check_magnetisation(self.jr, &[self.jx, self.jy])
+    }
+}
+
+/// A uniformly magnetised annular sector (wedge), the building block of
+/// segmented ring magnets
+///
+/// The sector spans the radii `inner_radius` to `outer_radius` about `center`,
+/// anticlockwise from `start_angle` to `end_angle` (in degrees, measured from
+/// +x), with `end_angle > start_angle`. The magnetisation angle `theta` is in
+/// the global frame.
+///
+/// # Example
+/// ```rust
+/// use magnet_rs::magnets::magnet2d::Wedge;
+/// let magnet = Wedge::new((0.0, 0.0), 0.5, 1.0, 0.0, 45.0, 1.0, 22.5);
+/// println!("Wedge:{}", magnet);
+/// ```
+#[derive(Copy, Clone)]
+pub struct Wedge {
+    pub center: Point2,
+    pub inner_radius: f64,
+    pub outer_radius: f64,
+    pub start_angle: f64,
+    pub end_angle: f64,
+    pub jr: f64,
+    pub theta: f64,
+    pub jx: f64,
+    pub jy: f64,
+}
+
+impl Wedge {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new<C, R, S, P, Q, J, T>(
+        center: C,
+        inner_radius: R,
+        outer_radius: S,
+        start_angle: P,
+        end_angle: Q,
+        jr: J,
+        theta: T,
+    ) -> Wedge
+    where
+        C: GetCenter2D,
+        R: Into<f64> + Mul<Output = R> + Add<Output = R> + Copy,
+        S: Into<f64> + Mul<Output = S> + Add<Output = S> + Copy,
+        P: Into<f64> + Mul<Output = P> + Add<Output = P> + Copy,
+        Q: Into<f64> + Mul<Output = Q> + Add<Output = Q> + Copy,
+        J: Into<f64> + Mul<Output = J> + Add<Output = J> + Copy,
+        T: Into<f64> + Mul<Output = T> + Add<Output = T> + Copy,
+    {
+        Wedge {
+            center: center.get_center(),
+            inner_radius: inner_radius.into(),
+            outer_radius: outer_radius.into(),
+            start_angle: start_angle.into(),
+            end_angle: end_angle.into(),
+            jr: jr.into(),
+            theta: theta.into(),
+            jx: jr.into() * (theta.into() * PI / 180.).cos(),
+            jy: jr.into() * (theta.into() * PI / 180.).sin(),
+        }
+    }
+
+    /// Returns true if `point`, relative to the wedge's center, lies in the
+    /// wedge
+    pub fn contains_local(&self, point: &Point2) -> bool {
+        let rho = point.magnitude();
+        let start = self.start_angle * PI / 180.;
+        let span = (self.end_angle - self.start_angle) * PI / 180.;
+        let phi = (point.y.atan2(point.x) - start).rem_euclid(2.0 * PI);
+        rho >= self.inner_radius && rho <= self.outer_radius && phi <= span
+    }
+}
+
+/// Implements Display for Wedge magnets.
+impl fmt::Display for Wedge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[r: {} - {},\tphi: {} - {},\tc: {},\tJ ({:.3}, {:.3})]",
+            self.inner_radius,
+            self.outer_radius,
+            self.start_angle,
+            self.end_angle,
+            self.center,
+            self.jx,
+            self.jy
+        )
+    }
+}
+
+impl Magnet for Wedge {}
+
+impl Magnet2D<Point2> for Wedge {
+    fn get_field(&self, point: &Point2) -> Result<Point2, Box<dyn Error>> {
+        get_field_wedge(self, &(*point - self.center))
+    }
+
+    fn get_center(&self) -> Point2 {
+        self.center
+    }
+
+    fn get_size(&self) -> Point2 {
+        Point2::new(self.outer_radius, self.outer_radius)
+    }
+
+    fn get_j(self) -> Point2 {
+        Point2::new(self.jx, self.jy)
+    }
+
+    fn contains(&self, point: &Point2) -> bool {
+        self.contains_local(&(*point - self.center))
+    }
+
+    fn validate(&self) -> Result<(), MagnetError> {
+        check_inner_radius(self.inner_radius)?;
+        check_size("radial thickness", self.outer_radius - self.inner_radius)?;
+        check_size("angular span", self.end_angle - self.start_angle)?;
+        check_finite("center", &[self.center.x, self.center.y, self.start_angle])?;
+        check_magnetisation(self.jr, &[self.jx, self.jy])
+    }
+}
+
+/// A magnetised annulus (hollow circle)
+///
+/// The annulus is either diametrically magnetised, with a uniform
+/// magnetisation at angle `theta`, or radially magnetised if `radial` is set,
+/// with a magnetisation of magnitude `jr` pointing outward (inward for
+/// negative `jr`) at every point.
+#[derive(Copy, Clone)]
+pub struct Annulus {
+    pub inner_radius: f64,
+    pub outer_radius: f64,
+    pub center: Point2,
+    pub jr: f64,
+    pub theta: f64,
+    pub jx: f64,
+    pub jy: f64,
+    pub radial: bool,
+}
+
+impl Annulus {
+    pub fn new<R, S, C, J, T>(
+        inner_radius: R,
+        outer_radius: S,
+        center: C,
+        jr: J,
+        theta: T,
+    ) -> Annulus
+    where
+        R: Into<f64> + Mul<Output = R> + Add<Output = R> + Copy,
+        S: Into<f64> + Mul<Output = S> + Add<Output = S> + Copy,
+        C: GetCenter2D,
+        J: Into<f64> + Mul<Output = J> + Add<Output = J> + Copy,
+        T: Into<f64> + Mul<Output = T> + Add<Output = T> + Copy,
+    {
+        Annulus {
+            inner_radius: inner_radius.into(),
+            outer_radius: outer_radius.into(),
+            center: center.get_center(),
+            jr: jr.into(),
+            theta: theta.into(),
+            jx: jr.into() * (theta.into() * PI / 180.).cos(),
+            jy: jr.into() * (theta.into() * PI / 180.).sin(),
+            radial: false,
+        }
+    }
+
+    /// Returns a radially magnetised annulus, with a magnetisation of
+    /// magnitude `jr` pointing away from the center, or towards it for
+    /// negative `jr`
+    ///
+    /// ```rust
+    /// use magnet_rs::magnets::magnet2d::Annulus;
+    /// let magnet = Annulus::new_radial(0.5, 1.0, (0.0, 0.0), 1.0);
+    /// println!("Annulus:{}", magnet);
+    /// ```
+    pub fn new_radial<R, S, C, J>(inner_radius: R, outer_radius: S, center: C, jr: J) -> Annulus
+    where
+        R: Into<f64> + Mul<Output = R> + Add<Output = R> + Copy,
+        S: Into<f64> + Mul<Output = S> + Add<Output = S> + Copy,
+        C: GetCenter2D,
+        J: Into<f64> + Mul<Output = J> + Add<Output = J> + Copy,
+    {
+        Annulus {
+            inner_radius: inner_radius.into(),
+            outer_radius: outer_radius.into(),
+            center: center.get_center(),
+            jr: jr.into(),
+            theta: 0.0,
+            jx: 0.0,
+            jy: 0.0,
+            radial: true,
+        }
+    }
+
+    /// Returns the circle bounding the annulus, with the annulus's
+    /// magnetisation
+    pub fn outer_circle(&self) -> Circle {
+        Circle::new(self.outer_radius, self.center, 0.0, self.jr, self.theta)
+    }
+
+    /// Returns the circle filling the hole of the annulus, with the annulus's
+    /// magnetisation
+    pub fn inner_circle(&self) -> Circle {
+        Circle::new(self.inner_radius, self.center, 0.0, self.jr, self.theta)
+    }
+}
+
+/// Implements Display for Annulus magnets.
+impl fmt::Display for Annulus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[r_in: {}\tr_out: {}\tc: {},\tJ ({:.3}, {:.3})]",
+            self.inner_radius, self.outer_radius, self.center, self.jx, self.jy
+        )?;
+        if self.radial {
+            write!(f, " radial, J_r {:.3}", self.jr)?;
+        }
+        Ok(())
+    }
+}
+
+impl Magnet for Annulus {}
+
+impl Magnet2D<Point2> for Annulus {
+    fn get_field(&self, point: &Point2) -> Result<Point2, Box<dyn Error>> {
+        get_field_annulus(self, &(*point - self.center))
+    }
+
+    fn get_center(&self) -> Point2 {
+        self.center
+    }
+
+    fn get_size(&self) -> Point2 {
+        Point2::new(self.outer_radius, self.outer_radius)
+    }
+
+    fn get_j(self) -> Point2 {
+        Point2::new(self.jx, self.jy)
+    }
+
+    fn contains(&self, point: &Point2) -> bool {
+        let rho = (*point - self.center).magnitude();
+        rho >= self.inner_radius && rho <= self.outer_radius
+    }
+
+    fn validate(&self) -> Result<(), MagnetError> {
+        check_inner_radius(self.inner_radius)?;
+        check_size("radial thickness", self.outer_radius - self.inner_radius)?;
+        check_finite("center", &[self.center.x, self.center.y])?;
+        check_magnetisation(self.jr, &[self.jx, self.jy])
+    }
+}
+
+/// A uniform background field, e.g. the bias field of a pair of Helmholtz
+/// coils
+///
+/// The source has no extent or magnetisation, and can be added to a
+/// collection of magnets like any other `Magnet2D`.
+#[derive(Copy, Clone)]
+pub struct UniformField2D {
+    pub field: Point2,
+}
+
+impl UniformField2D {
+    pub fn new(field: Point2) -> UniformField2D {
+        UniformField2D { field }
+    }
+}
+
+/// Implements Display for UniformField2D sources.
+impl fmt::Display for UniformField2D {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[B: {}]", self.field)
+    }
+}
+
+impl Magnet for UniformField2D {}
+
+impl Magnet2D<Point2> for UniformField2D {
+    fn get_field(&self, _point: &Point2) -> Result<Point2, Box<dyn Error>> {
+        Ok(self.field)
+    }
+
+    fn get_center(&self) -> Point2 {
+        Point2::zero()
+    }
+
+    fn get_size(&self) -> Point2 {
+        Point2::zero()
+    }
+
+    fn get_j(self) -> Point2 {
+        Point2::zero()
+    }
+
+    fn validate(&self) -> Result<(), MagnetError> {
+        check_finite("field", &[self.field.x, self.field.y])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::magnets::magnet2d::circle_field::get_field_circle;
+    use crate::magnets::magnet2d::collection::MagnetCollection2D;
+    use crate::magnets::magnet2d::rectangle_field::get_field_rectangle;
+    use crate::magnets::magnet2d::{Annulus, Circle, Magnet2D, Rectangle, UniformField2D};
+    use crate::utils::comparison::nearly_equal;
+    use crate::utils::points2::{Point2, Points2};
+    use crate::{MU0, PI};
+
+    #[test]
+    fn demag_factors_square() {
+        let magnet = Rectangle::default();
+        let (n_x, n_y) = magnet.demag_factors();
+        assert!(nearly_equal(n_x, 0.5) && nearly_equal(n_y, 0.5));
+    }
+
+    #[test]
+    fn rectangle_field_follows_center_and_alpha() {
+        let magnet = Rectangle::new(1.0, 0.5, (1.0, -0.5), 30.0, 1.0, 60.0);
+        let point = Point2::new(0.3, 0.8);
+        let alpha = 30.0 * PI / 180.;
+        let local_point = (point - magnet.center).rotate(-alpha);
+        let expected = get_field_rectangle(&magnet, &local_point)
+            .unwrap()
+            .rotate(alpha);
+        assert!((magnet.get_field(&point).unwrap() - expected).magnitude() < 1e-15);
+
+        // A quarter turn swaps the sides and the magnetisation direction
+        let turned = Rectangle::new(1.0, 0.5, (0.0, 0.0), 90.0, 1.0, 0.0);
+        let upright = Rectangle::new(0.5, 1.0, (0.0, 0.0), 0.0, 1.0, 90.0);
+        let point = Point2::new(0.4, 0.9);
+        let difference = turned.get_field(&point).unwrap() - upright.get_field(&point).unwrap();
+        assert!(difference.magnitude() < 1e-12);
+    }
+
+    #[test]
+    fn circle_field_follows_center() {
+        let magnet = Circle::new(0.5, (2.0, -1.0), 0.0, 1.0, 0.0);
+        let offset = Point2::new(0.3, 0.7);
+        let expected = get_field_circle(&magnet, &offset).unwrap();
+        let field = magnet.get_field(&(magnet.center + offset)).unwrap();
+        assert!((field - expected).magnitude() < 1e-15);
+    }
+
+    #[test]
+    fn with_magnetisation_round_trip() {
+        let magnet = Rectangle::new(1.0, 0.5, (0.2, -0.1), 30.0, 1.0, 90.0);
+        let j = Point2::new(-0.3, 0.9);
+        let remagnetised = magnet.with_magnetisation(j);
+        assert!((remagnetised.magnetisation_vector() - j).magnitude() < 1e-15);
+        assert!(nearly_equal(remagnetised.jr, j.magnitude()));
+    }
+
+    #[test]
+    fn mass_and_inertia_of_rectangle() {
+        let magnet = Rectangle::new(0.02, 0.01, (0.0, 0.0), 30.0, 1.0, 0.0);
+        let (mass, inertia) = magnet.mass_and_inertia(7500.0);
+        assert!(nearly_equal(mass, 1.5));
+        assert!(nearly_equal(inertia, 1.5 * (4e-4 + 1e-4) / 12.0));
+    }
+
+    #[test]
+    fn magnetisation_vector_convention() {
+        let magnet = Rectangle::new(1.0, 1.0, (0.0, 0.0), 0.0, 1.2, 0.0);
+        let j = magnet.magnetisation_vector();
+        assert!(nearly_equal(j.x, 1.2) && nearly_equal(j.y, 0.0));
+
+        let magnet = Rectangle::new(1.0, 1.0, (0.0, 0.0), 0.0, 1.2, 90.0);
+        let j = magnet.magnetisation_vector();
+        assert!(nearly_equal(j.x, 0.0) && nearly_equal(j.y, 1.2));
+
+        let magnet = Rectangle::new(1.0, 1.0, (0.0, 0.0), 90.0, 1.2, 0.0);
+        let j = magnet.magnetisation_vector();
+        assert!(nearly_equal(j.x, 0.0) && nearly_equal(j.y, 1.2));
+    }
+
+    #[test]
+    fn uniform_field_shifts_total_field() {
+        let bias = Point2::new(0.01, -0.02);
+        let mut magnets = MagnetCollection2D::new();
+        magnets.push(Rectangle::new(1.0, 2.0, (0.5, 0.0), 10.0, 1.0, 90.0));
+        let mut biased = MagnetCollection2D::new();
+        biased.push(Rectangle::new(1.0, 2.0, (0.5, 0.0), 10.0, 1.0, 90.0));
+        biased.push(UniformField2D::new(bias));
+
+        for point in &[
+            Point2::new(0.0, 3.0),
+            Point2::new(-2.0, 0.1),
+            Point2::new(0.6, 0.2),
+        ] {
+            let shift = biased.get_field(point).unwrap() - magnets.get_field(point).unwrap();
+            assert!((shift - bias).magnitude() < 1e-15);
+        }
+    }
+
+    #[test]
+    fn demag_field_opposes_magnetisation() {
+        // In a square N_x = N_y = 1/2, so at the centre H = -J / (2 mu_0)
+        let magnet = Rectangle::new(1.0, 1.0, (1.0, 1.0), 20.0, 1.0, 30.0);
+        let h = magnet.internal_demag_field(&Point2::new(1.0, 1.0)).unwrap();
+        let j = magnet.magnetisation_vector();
+        assert!(nearly_equal(
+            h.dot(&j) / (h.magnitude() * j.magnitude()),
+            -1.0
+        ));
+        assert!(nearly_equal(h.magnitude() * MU0, 0.5));
+
+        assert!(magnet.internal_demag_field(&Point2::new(2.0, 0.0)).is_err());
+    }
+
+    #[test]
+    fn working_point_square() {
+        let magnet = Rectangle::new(1.0, 1.0, (0.0, 0.0), 0.0, 1.2, 90.0);
+        let (h, b) = magnet.working_point(1.05);
+        let result = nearly_equal(h, -1.2 * 0.5 / (MU0 * 1.025)) && nearly_equal(b, 0.6 / 1.025);
+        assert!(result);
+    }
+
+    #[test]
+    fn cells_tile_the_rectangle() {
+        let magnet = Rectangle::new(1.0, 0.5, (0.2, -0.1), 30.0, 1.0, 60.0);
+        let cells = magnet.cells(4, 3);
+        assert_eq!(12, cells.len());
+        assert!(magnet.cells(0, 3).is_empty());
+
+        let area: f64 = cells.iter().map(|cell| cell.width * cell.height).sum();
+        assert!(nearly_equal(area, 0.5));
+        for cell in &cells {
+            assert!(magnet.contains(&cell.center));
+            assert_eq!(magnet.alpha, cell.alpha);
+        }
+
+        let point = Point2::new(1.5, 0.8);
+        let expected = magnet.get_field(&point).unwrap();
+        let mut field = Point2::zero();
+        for cell in &cells {
+            field += cell.get_field(&point).unwrap();
+        }
+        assert!((field - expected).magnitude() < 1e-12 * expected.magnitude());
+    }
+
+    #[test]
+    fn working_point_unmagnetised() {
+        let magnet = Rectangle::new(1.0, 0.5, (0.0, 0.0), 0.0, 0.0, 90.0);
+        assert_eq!((0.0, 0.0), magnet.working_point(1.05));
+        assert_eq!((0.0, 0.0), magnet.recoil_working_point(2e6, 1.05, 1e6));
+    }
+
+    #[test]
+    fn recoil_working_point_below_and_above_knee() {
+        // N = 0.5, so the applied field at the knee is H_cj - J_r / (2 mu_0)
+        // for unit recoil permeability
+        let magnet = Rectangle::new(1.0, 1.0, (0.0, 0.0), 0.0, 1.2, 90.0);
+        let coercivity = 1.0e6;
+        let knee = coercivity - 1.2 / (2.0 * MU0);
+
+        let reversible = magnet.recoil_working_point(0.9 * knee, 1.0, coercivity);
+        assert_eq!(magnet.working_point(1.0), reversible);
+
+        let (h, b) = magnet.recoil_working_point(1.2 * knee, 1.0, coercivity);
+        let expected_jr = 2.0 * MU0 * (coercivity - 1.2 * knee);
+        assert!(nearly_equal(b, expected_jr / 2.0) && nearly_equal(h, -expected_jr / (2.0 * MU0)));
+        assert!(b < reversible.1);
+
+        let (_, b_worse) = magnet.recoil_working_point(1.5 * knee, 1.0, coercivity);
+        assert!(b_worse < b);
+        let (_, b_reversed) = magnet.recoil_working_point(100.0 * knee, 1.0, coercivity);
+        assert!(nearly_equal(b_reversed, -0.6));
+    }
+
+    #[test]
+    fn validate_rejects_malformed_magnets() {
+        assert!(Rectangle::default().validate().is_ok());
+        assert!(Rectangle::new(f64::NAN, 1.0, (0.0, 0.0), 0.0, 1.0, 90.0)
+            .validate()
+            .is_err());
+        assert!(Rectangle::new(1.0, -1.0, (0.0, 0.0), 0.0, 1.0, 90.0)
+            .validate()
+            .is_err());
+        assert!(Rectangle::new(1.0, 1.0, (0.0, 0.0), 0.0, 0.0, 90.0)
+            .validate()
+            .is_err());
+
+        assert!(Annulus::new(0.0, 1.0, (0.0, 0.0), 1.0, 90.0)
+            .validate()
+            .is_ok());
+        assert!(Annulus::new(1.0, 0.5, (0.0, 0.0), 1.0, 90.0)
+            .validate()
+            .is_err());
+    }
 }
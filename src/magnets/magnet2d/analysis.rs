@@ -0,0 +1,1522 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/.
+Copyright 2021 Peter Dunne */
+
+//! Analysis of 2D field data
+//!
+//! Routines characterising a field, either sampled by the routine itself
+//! or already sampled at a set of points, e.g. the output of a grid
+//! calculation.
+
+use crate::errors::MagnetError;
+use crate::magnets::magnet2d::collection::MagnetCollection2D;
+use crate::magnets::magnet2d::gradient::field_gradient;
+use crate::magnets::magnet2d::grid::Component;
+use crate::magnets::magnet2d::{Magnet2D, Rectangle};
+use crate::utils::complex::{dft, Complex};
+use crate::utils::points::Points;
+use crate::utils::points2::{Point2, Points2};
+use crate::utils::random::Rng;
+use crate::{NAN, PI};
+
+/// Upper limit on the seeds placed in one cell by `adaptive_seed_density`
+pub const MAX_SEEDS_PER_CELL: usize = 16;
+
+/// Number of cells along each side of the box in `optimal_scan_path`
+pub const SCAN_CELLS: usize = 32;
+
+/// Fraction of the samples of `optimal_scan_path` spread uniformly, so that
+/// regions of weak gradient are still covered
+pub const SCAN_UNIFORM_FRACTION: f64 = 0.2;
+
+/// Returns the $`|\mathbf{B}|`$-weighted average of `points`, where `field`
+/// holds the field at each point.
+///
+/// Returns `(NAN, NAN)` if the field vanishes everywhere.
+pub fn field_centroid(points: &[Point2], field: &[Point2]) -> Point2 {
+    let mut weighted_sum = Point2::zero();
+    let mut total_weight = 0.0;
+
+    for (point, b) in points.iter().zip(field) {
+        let weight = b.magnitude();
+        weighted_sum += point.scale(weight);
+        total_weight += weight;
+    }
+
+    if total_weight > 0.0 {
+        weighted_sum.scale(1.0 / total_weight)
+    } else {
+        Point2::new(NAN, NAN)
+    }
+}
+
+/// Returns the $`|\mathbf{B}|`$-weighted spatial moments of `points` about
+/// the origin, up to total order `order`, where `field` holds the field at
+/// each point,
+///
+/// ```math
+/// M_{pq} = \frac{\sum |\mathbf{B}| x^p y^q}{\sum |\mathbf{B}|}
+/// ```
+///
+/// ordered by total order `k = p + q` and then by decreasing `p`, i.e.
+/// `[M00, M10, M01, M20, M11, M02, M30, ...]`, with `M_pq` at index
+/// `k (k + 1)/2 + q`. `M00` is 1, the first order is the
+/// [`field_centroid`], and the central variance and skewness follow from
+/// the second and third order, e.g. $`\sigma_x^2 = M_{20} - M_{10}^2`$.
+/// Returns an error if the field vanishes everywhere.
+pub fn field_spatial_moments(
+    field: &[Point2],
+    points: &[Point2],
+    order: usize,
+) -> Result<Vec<f64>, MagnetError> {
+    if field.len() != points.len() {
+        return Err(MagnetError::InvalidParameter(format!(
+            "{} field samples for {} points",
+            field.len(),
+            points.len()
+        )));
+    }
+
+    let n_moments = (order + 1) * (order + 2) / 2;
+    let mut moments = vec![0.0; n_moments];
+    let mut total_weight = 0.0;
+    for (point, b) in points.iter().zip(field) {
+        let weight = b.magnitude();
+        total_weight += weight;
+        for k in 0..=order {
+            for q in 0..=k {
+                moments[k * (k + 1) / 2 + q] +=
+                    weight * point.x.powi((k - q) as i32) * point.y.powi(q as i32);
+            }
+        }
+    }
+
+    if total_weight > 0.0 {
+        Ok(moments.iter().map(|m| m / total_weight).collect())
+    } else {
+        Err(MagnetError::Calculation(
+            "field vanishes at every point".to_string(),
+        ))
+    }
+}
+
+/// Returns the peak-to-peak non-uniformity of $`|\mathbf{B}|`$ over a set
+/// of field samples, relative to its mean,
+///
+/// ```math
+/// U = \frac{\max |\mathbf{B}| - \min |\mathbf{B}|}{\langle |\mathbf{B}| \rangle}
+/// ```
+///
+/// which is 0 for a perfectly uniform field magnitude, and `NAN` if the field
+/// vanishes everywhere or there are no samples.
+pub fn field_uniformity(field: &[Point2]) -> f64 {
+    let mut min = f64::INFINITY;
+    let mut max = 0.0_f64;
+    let mut sum = 0.0;
+    for b in field {
+        let magnitude = b.magnitude();
+        min = min.min(magnitude);
+        max = max.max(magnitude);
+        sum += magnitude;
+    }
+
+    if sum > 0.0 {
+        (max - min) * field.len() as f64 / sum
+    } else {
+        NAN
+    }
+}
+
+/// Returns the `n_bins + 1` bin edges and the `n_bins` counts of a histogram
+/// of $`|\mathbf{B}|`$ over a set of field samples.
+///
+/// The bins evenly span the smallest to the largest magnitude, with the
+/// largest counted in the last bin. If all magnitudes are equal they are all
+/// counted in the first bin. Returns empty vectors if there are no samples or
+/// no bins.
+pub fn field_histogram(field: &[Point2], n_bins: usize) -> (Vec<f64>, Vec<usize>) {
+    if field.is_empty() || n_bins == 0 {
+        return (Vec::new(), Vec::new());
+    }
+
+    let magnitudes: Vec<f64> = field.iter().map(|b| b.magnitude()).collect();
+    let min = magnitudes.iter().cloned().fold(f64::INFINITY, f64::min);
+    let max = magnitudes.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    let width = (max - min) / n_bins as f64;
+
+    let edges = (0..=n_bins).map(|i| min + width * i as f64).collect();
+    let mut counts = vec![0; n_bins];
+    for magnitude in &magnitudes {
+        let bin = if width > 0.0 {
+            (((magnitude - min) / width) as usize).min(n_bins - 1)
+        } else {
+            0
+        };
+        counts[bin] += 1;
+    }
+
+    (edges, counts)
+}
+
+/// Returns the radial profile of $`|\mathbf{B}|`$ about `center`, as
+/// `(radius, magnitude)` pairs averaged over the samples in each of `n_bins`
+/// rings.
+///
+/// The rings evenly span the radii from 0 to the furthest point, with that
+/// point counted in the last ring. Each pair holds the mean radius and the
+/// mean magnitude of the samples in a ring, so the profile of a magnitude
+/// linear in the radius is exact, and rings without samples are left out.
+/// For an axisymmetric assembly the average over angle removes the
+/// azimuthal variation of a sampled map.
+pub fn azimuthal_average(
+    field: &[Point2],
+    points: &[Point2],
+    center: Point2,
+    n_bins: usize,
+) -> Result<Vec<(f64, f64)>, MagnetError> {
+    if field.len() != points.len() {
+        return Err(MagnetError::InvalidParameter(format!(
+            "{} field values but {} points",
+            field.len(),
+            points.len()
+        )));
+    }
+    if field.is_empty() || n_bins == 0 {
+        return Err(MagnetError::InvalidParameter(
+            "an azimuthal average needs samples and at least one bin".to_string(),
+        ));
+    }
+
+    let radii: Vec<f64> = points.iter().map(|p| (*p - center).magnitude()).collect();
+    let max = radii.iter().cloned().fold(0.0, f64::max);
+    let width = max / n_bins as f64;
+
+    let mut sums = vec![(0.0, 0.0, 0_usize); n_bins];
+    for (radius, b) in radii.iter().zip(field) {
+        let bin = if width > 0.0 {
+            ((radius / width) as usize).min(n_bins - 1)
+        } else {
+            0
+        };
+        sums[bin].0 += radius;
+        sums[bin].1 += b.magnitude();
+        sums[bin].2 += 1;
+    }
+
+    Ok(sums
+        .iter()
+        .filter(|(_, _, count)| *count > 0)
+        .map(|(radius, magnitude, count)| (radius / *count as f64, magnitude / *count as f64))
+        .collect())
+}
+
+/// Splits a sampled field into its parts symmetric and antisymmetric under
+/// reflection about `axis`, a direction through the origin.
+///
+/// With $`M`$ the reflection, the parts at a point $`\mathbf{r}`$ are
+/// $`\frac{1}{2}(\mathbf{B}(\mathbf{r}) \pm M\mathbf{B}(M\mathbf{r}))`$,
+/// so a configuration whose magnets and magnetisations are mirror images
+/// about the axis has no antisymmetric part. The field at the mirror image
+/// of each point is looked up in `points`, so the samples should form a
+/// mirror symmetric set; both parts are `NAN` at points without a partner.
+pub fn symmetry_decompose(
+    field: &[Point2],
+    points: &[Point2],
+    axis: Point2,
+) -> (Vec<Point2>, Vec<Point2>) {
+    let axis = axis.unit();
+    let reflect = |v: &Point2| axis.scale(2.0 * v.dot(&axis)) - *v;
+    let extent = points.iter().fold(0.0_f64, |max, p| max.max(p.magnitude()));
+    let tolerance = 1e-9 * extent.max(1.0);
+
+    points
+        .iter()
+        .zip(field)
+        .map(|(point, b)| {
+            let mirror = reflect(point);
+            match points
+                .iter()
+                .position(|p| (*p - mirror).magnitude() <= tolerance)
+            {
+                Some(i) => {
+                    let mirrored = reflect(&field[i]);
+                    ((*b + mirrored).scale(0.5), (*b - mirrored).scale(0.5))
+                }
+                None => (Point2::new(NAN, NAN), Point2::new(NAN, NAN)),
+            }
+        })
+        .unzip()
+}
+
+/// Returns `4 * n_per_edge` points a distance `offset` outside the boundary
+/// of `magnet`, with the field at each.
+///
+/// The points sit at the midpoints of `n_per_edge` equal segments of each
+/// edge, moved out along the edge normal, which keeps them away from the
+/// singular corners and from the jump of the field across the surface. The
+/// edges are visited anticlockwise, starting with the bottom edge in the
+/// magnet's local frame.
+pub fn field_on_boundary(
+    magnet: &Rectangle,
+    n_per_edge: usize,
+    offset: f64,
+) -> Result<(Vec<Point2>, Vec<Point2>), MagnetError> {
+    if n_per_edge == 0 {
+        return Err(MagnetError::InvalidParameter(
+            "at least one point per edge is needed".to_string(),
+        ));
+    }
+    if !(offset.is_finite() && offset > 0.0) {
+        return Err(MagnetError::InvalidParameter(format!(
+            "offset must be positive, got {}",
+            offset
+        )));
+    }
+
+    let (a, b) = (magnet.a, magnet.b);
+    // Start corner, edge direction and outward normal of each edge
+    let edges = [
+        (
+            Point2::new(-a, -b),
+            Point2::new(2.0 * a, 0.0),
+            Point2::new(0.0, -1.0),
+        ),
+        (
+            Point2::new(a, -b),
+            Point2::new(0.0, 2.0 * b),
+            Point2::new(1.0, 0.0),
+        ),
+        (
+            Point2::new(a, b),
+            Point2::new(-2.0 * a, 0.0),
+            Point2::new(0.0, 1.0),
+        ),
+        (
+            Point2::new(-a, b),
+            Point2::new(0.0, -2.0 * b),
+            Point2::new(-1.0, 0.0),
+        ),
+    ];
+
+    let alpha = magnet.alpha * PI / 180.;
+    let points: Vec<Point2> = edges
+        .iter()
+        .flat_map(|(corner, along, normal)| {
+            (0..n_per_edge).map(move |k| {
+                let fraction = (k as f64 + 0.5) / n_per_edge as f64;
+                *corner + along.scale(fraction) + normal.scale(offset)
+            })
+        })
+        .map(|local| magnet.center + local.rotate(alpha))
+        .collect();
+
+    let field = points
+        .iter()
+        .map(|point| Ok(magnet.get_field(point)?))
+        .collect::<Result<Vec<Point2>, MagnetError>>()?;
+
+    Ok((points, field))
+}
+
+/// Returns field-line seed points in the box from `bbox.0` to `bbox.1`,
+/// placed more densely where the field is stronger, so that the density of
+/// the rendered lines encodes $`|\mathbf{B}|`$.
+///
+/// The box is split into `base_density` x `base_density` cells, and each cell
+/// receives seeds in proportion to $`|\mathbf{B}|`$ at its center relative to
+/// the mean over all cells, giving about `base_density`² seeds in total. The
+/// seeds of a cell are spread on a regular sub-grid. The count per cell is
+/// capped at `MAX_SEEDS_PER_CELL`, as the field diverges at magnet corners.
+pub fn adaptive_seed_density(
+    collection: &MagnetCollection2D,
+    bbox: (Point2, Point2),
+    base_density: usize,
+) -> Result<Vec<Point2>, MagnetError> {
+    if base_density == 0 {
+        return Err(MagnetError::InvalidParameter(
+            "base density must be at least 1".to_string(),
+        ));
+    }
+
+    let (start, end) = bbox;
+    let cell = Point2::new(
+        (end.x - start.x) / base_density as f64,
+        (end.y - start.y) / base_density as f64,
+    );
+    let corners: Vec<Point2> = (0..base_density * base_density)
+        .map(|k| {
+            let (i, j) = (k % base_density, k / base_density);
+            Point2::new(start.x + i as f64 * cell.x, start.y + j as f64 * cell.y)
+        })
+        .collect();
+    let magnitudes = corners
+        .iter()
+        .map(|corner| {
+            let center = *corner + cell.scale(0.5);
+            Ok(collection.get_field(&center)?.magnitude())
+        })
+        .collect::<Result<Vec<f64>, MagnetError>>()?;
+
+    let mean = magnitudes.iter().sum::<f64>() / magnitudes.len() as f64;
+    if mean == 0.0 {
+        return Ok(Vec::new());
+    }
+
+    let mut seeds = Vec::new();
+    for (corner, magnitude) in corners.iter().zip(magnitudes) {
+        let n = ((magnitude / mean).round() as usize).min(MAX_SEEDS_PER_CELL);
+        seeds.extend(spread_in_cell(*corner, cell, n));
+    }
+
+    Ok(seeds)
+}
+
+/// Returns `n` points spread on a regular sub-grid of the cell of size `cell`
+/// with its lower left corner at `corner`, filled row by row
+fn spread_in_cell(corner: Point2, cell: Point2, n: usize) -> impl Iterator<Item = Point2> {
+    let m = (n as f64).sqrt().ceil() as usize;
+    (0..n).map(move |k| {
+        let (u, v) = ((k % m) as f64 + 0.5, (k / m) as f64 + 0.5);
+        corner + Point2::new(u * cell.x / m as f64, v * cell.y / m as f64)
+    })
+}
+
+/// Returns a scan path of `n_points` samples for a sensor moving over the box
+/// from `bbox.0` to `bbox.1`, with the samples concentrated where the field
+/// gradient of `magnet` is largest.
+///
+/// The box is split into `SCAN_CELLS` x `SCAN_CELLS` cells, and each cell
+/// receives a share of the samples in proportion to the Frobenius norm of the
+/// gradient at its center, with `SCAN_UNIFORM_FRACTION` of them shared
+/// equally. The shares are rounded by largest remainder so that they sum to
+/// `n_points`, and spread on a regular sub-grid of each cell. The path visits
+/// the rows of cells in turn, alternating direction, so that consecutive
+/// samples are close together.
+pub fn optimal_scan_path(
+    magnet: &dyn Magnet2D<Point2>,
+    bbox: (Point2, Point2),
+    n_points: usize,
+) -> Result<Vec<Point2>, MagnetError> {
+    let (start, end) = bbox;
+    let cell = Point2::new(
+        (end.x - start.x) / SCAN_CELLS as f64,
+        (end.y - start.y) / SCAN_CELLS as f64,
+    );
+    if !(cell.x.is_finite() && cell.y.is_finite() && cell.x > 0.0 && cell.y > 0.0) {
+        return Err(MagnetError::InvalidParameter(format!(
+            "box from {} to {} is empty",
+            start, end
+        )));
+    }
+
+    let h = 1e-3 * cell.x.min(cell.y);
+    let gradients = (0..SCAN_CELLS * SCAN_CELLS)
+        .map(|k| {
+            let (i, j) = (k % SCAN_CELLS, k / SCAN_CELLS);
+            let center = start + Point2::new((i as f64 + 0.5) * cell.x, (j as f64 + 0.5) * cell.y);
+            let (d_dx, d_dy) = field_gradient(magnet, &center, h)?;
+            Ok((d_dx.magnitude_squared() + d_dy.magnitude_squared()).sqrt())
+        })
+        .collect::<Result<Vec<f64>, MagnetError>>()?;
+
+    let total: f64 = gradients.iter().sum();
+    let n_cells = gradients.len() as f64;
+    let shares: Vec<f64> = gradients
+        .iter()
+        .map(|g| {
+            let weighted = if total > 0.0 {
+                g / total
+            } else {
+                1.0 / n_cells
+            };
+            n_points as f64
+                * (SCAN_UNIFORM_FRACTION / n_cells + (1.0 - SCAN_UNIFORM_FRACTION) * weighted)
+        })
+        .collect();
+
+    let mut counts: Vec<usize> = shares.iter().map(|s| s.floor() as usize).collect();
+    let mut order: Vec<usize> = (0..counts.len()).collect();
+    order.sort_by(|a, b| {
+        (shares[*b] - shares[*b].floor()).total_cmp(&(shares[*a] - shares[*a].floor()))
+    });
+    let assigned: usize = counts.iter().sum();
+    for k in order.iter().take(n_points.saturating_sub(assigned)) {
+        counts[*k] += 1;
+    }
+
+    let mut path = Vec::with_capacity(n_points);
+    for j in 0..SCAN_CELLS {
+        for column in 0..SCAN_CELLS {
+            let i = if j % 2 == 0 {
+                column
+            } else {
+                SCAN_CELLS - 1 - column
+            };
+            let corner = start + Point2::new(i as f64 * cell.x, j as f64 * cell.y);
+            path.extend(spread_in_cell(corner, cell, counts[j * SCAN_CELLS + i]));
+        }
+    }
+
+    Ok(path)
+}
+
+/// Returns the overlap (cosine similarity) of two field maps sampled on the
+/// same grid,
+///
+/// ```math
+/// O = \frac{\sum_i \mathbf{a}_i \cdot \mathbf{b}_i}
+/// {\sqrt{\sum_i |\mathbf{a}_i|^2 \sum_i |\mathbf{b}_i|^2}}
+/// ```
+///
+/// which is 1 for proportional fields, -1 for opposite fields, and `NAN` if
+/// either field vanishes everywhere.
+pub fn field_overlap(a: &[Point2], b: &[Point2]) -> f64 {
+    let mut dot = 0.0;
+    let mut norm_a = 0.0;
+    let mut norm_b = 0.0;
+
+    for (a_i, b_i) in a.iter().zip(b) {
+        dot += a_i.dot(b_i);
+        norm_a += a_i.magnitude_squared();
+        norm_b += b_i.magnitude_squared();
+    }
+
+    if norm_a > 0.0 && norm_b > 0.0 {
+        dot / (norm_a * norm_b).sqrt()
+    } else {
+        NAN
+    }
+}
+
+/// Returns how closely a computed field profile matches a target profile
+/// sampled at the same points,
+///
+/// ```math
+/// S = 1 - \sqrt{\frac{\sum_i |\mathbf{B}_i - \mathbf{T}_i|^2}
+/// {\sum_i |\mathbf{T}_i|^2}}
+/// ```
+///
+/// i.e. one minus the RMS difference relative to the RMS target. The score is
+/// 1 for identical profiles, 0 for a vanishing computed field, and -1 for the
+/// reversed profile, so it can be maximised directly by shape and placement
+/// optimisers.
+pub fn profile_match_score(computed: &[Point2], target: &[Point2]) -> Result<f64, MagnetError> {
+    if computed.len() != target.len() || target.is_empty() {
+        return Err(MagnetError::InvalidParameter(format!(
+            "profiles must have the same, non-zero length, got {} and {}",
+            computed.len(),
+            target.len()
+        )));
+    }
+
+    let mut difference = 0.0;
+    let mut norm = 0.0;
+    for (b, t) in computed.iter().zip(target) {
+        difference += (*b - *t).magnitude_squared();
+        norm += t.magnitude_squared();
+    }
+
+    if norm == 0.0 {
+        return Err(MagnetError::InvalidParameter(
+            "target profile vanishes everywhere".to_string(),
+        ));
+    }
+
+    Ok(1.0 - (difference / norm).sqrt())
+}
+
+/// Samples `component` of the field at `n` points along the scan line from
+/// `start` towards `end` and returns the samples with their one-sided
+/// amplitude spectrum.
+///
+/// The samples are spaced by `(end - start) / n`, excluding `end`, so that a
+/// field periodic over the scan length has no leakage. Bin `k` of the
+/// spectrum, for `k = 0..=n/2`, corresponds to a spatial frequency of
+/// `k / L`, with `L` the scan length. The amplitudes are normalised so that
+/// a sinusoid of amplitude `A` gives a peak of height `A`.
+pub fn field_ripple(
+    magnet_array: &MagnetCollection2D,
+    start: Point2,
+    end: Point2,
+    n: usize,
+    component: Component,
+) -> Result<(Vec<f64>, Vec<f64>), MagnetError> {
+    if n < 2 {
+        return Err(MagnetError::InvalidParameter(format!(
+            "at least 2 samples are needed, got {}",
+            n
+        )));
+    }
+
+    let step = (end - start).scale(1.0 / n as f64);
+    let samples = (0..n)
+        .map(|i| Ok(component.of(magnet_array.get_field(&(start + step.scale(i as f64)))?)))
+        .collect::<Result<Vec<f64>, MagnetError>>()?;
+
+    let signal: Vec<Complex> = samples.iter().map(|b| Complex::new(*b, 0.0)).collect();
+    let spectrum = dft(&signal)
+        .iter()
+        .take(n / 2 + 1)
+        .enumerate()
+        .map(|(k, x_k)| {
+            let scale = if k == 0 || 2 * k == n { 1.0 } else { 2.0 };
+            scale * x_k.norm() / n as f64
+        })
+        .collect();
+
+    Ok((samples, spectrum))
+}
+
+/// Returns the field at a fixed `probe` point as the whole `rotor` assembly
+/// is rotated anticlockwise about `pivot` through each of `angles_deg`.
+///
+/// Rather than moving the magnets, the probe is rotated by $`-\theta`$ about
+/// the pivot and the resulting field rotated back by $`\theta`$, which is
+/// exact as the magnetisation rotates with the rotor.
+pub fn field_vs_rotation(
+    rotor: &MagnetCollection2D,
+    pivot: Point2,
+    angles_deg: &[f64],
+    probe: Point2,
+) -> Result<Vec<Point2>, MagnetError> {
+    angles_deg
+        .iter()
+        .map(|angle| {
+            let angle = angle * PI / 180.;
+            let rotor_point = pivot + (probe - pivot).rotate(-angle);
+            Ok(rotor.get_field(&rotor_point)?.rotate(angle))
+        })
+        .collect()
+}
+
+/// Returns the fraction of a sampled field that is not the gradient of a
+/// harmonic scalar potential, over a regular grid of `nx` x `ny` points
+/// with x the fastest varying index.
+///
+/// As in the Helmholtz-Hodge decomposition, the field is split into
+///
+/// ```math
+/// \mathbf{B} = \nabla \phi + \nabla \times (\psi \hat{\mathbf{z}}) - \nabla \Phi,
+/// \qquad \nabla^2 \phi = \nabla \cdot \mathbf{B},
+/// \qquad \nabla^2 \psi = -(\nabla \times \mathbf{B})_z
+/// ```
+///
+/// with $`\phi`$ and $`\psi`$ vanishing on the edge of the grid, so that
+/// projecting out the first two terms leaves the field of the harmonic
+/// potential $`\Phi`$. The residual is the size of the part projected out,
+///
+/// ```math
+/// R = \sqrt{\frac{\sum |\nabla \phi + \nabla \times (\psi \hat{\mathbf{z}})|^2}
+/// {\sum |\mathbf{B}|^2}}
+/// ```
+///
+/// over the interior nodes. The derivatives are central differences and the
+/// Poisson equations are solved with the five point Laplacian, so for a
+/// magnetostatic field outside its sources $`R`$ is the truncation error,
+/// falling as the square of the grid spacing. Grids should not straddle a
+/// magnet's surface.
+///
+/// Returns an error if the grid does not match `field` and `points`, is not
+/// regular, has no interior nodes, or the field vanishes.
+pub fn helmholtz_residual(
+    field: &[Point2],
+    points: &[Point2],
+    nx: usize,
+    ny: usize,
+) -> Result<f64, MagnetError> {
+    check_grid(field.len(), nx, ny)?;
+    if points.len() != field.len() {
+        return Err(MagnetError::InvalidParameter(format!(
+            "{} points do not match a field of {} points",
+            points.len(),
+            field.len()
+        )));
+    }
+    if nx < 3 || ny < 3 {
+        return Err(MagnetError::InvalidParameter(format!(
+            "a {} x {} grid has no interior nodes",
+            nx, ny
+        )));
+    }
+
+    let origin = points[0];
+    let (dx, dy) = (points[1].x - origin.x, points[nx].y - origin.y);
+    let tolerance = 1e-6 * dx.abs().min(dy.abs());
+    let regular = tolerance > 0.0
+        && points.iter().enumerate().all(|(k, p)| {
+            let expected = origin + Point2::new((k % nx) as f64 * dx, (k / nx) as f64 * dy);
+            (*p - expected).magnitude() <= tolerance
+        });
+    if !regular {
+        return Err(MagnetError::InvalidParameter(
+            "points do not form a regular grid".to_string(),
+        ));
+    }
+
+    let interior = |k: usize| {
+        let (i, j) = (k % nx, k / nx);
+        i > 0 && i < nx - 1 && j > 0 && j < ny - 1
+    };
+    // Central differences of the field at the interior nodes
+    let mut divergence = vec![0.0; nx * ny];
+    let mut curl = vec![0.0; nx * ny];
+    for k in (0..nx * ny).filter(|k| interior(*k)) {
+        let d_dx = (field[k + 1] - field[k - 1]).scale(0.5 / dx);
+        let d_dy = (field[k + nx] - field[k - nx]).scale(0.5 / dy);
+        divergence[k] = d_dx.x + d_dy.y;
+        curl[k] = -(d_dx.y - d_dy.x);
+    }
+    let phi = solve_poisson(&divergence, nx, ny, dx, dy);
+    let psi = solve_poisson(&curl, nx, ny, dx, dy);
+
+    let mut residual = 0.0;
+    let mut norm = 0.0;
+    for k in (0..nx * ny).filter(|k| interior(*k)) {
+        let (phi_x, phi_y) = (
+            (phi[k + 1] - phi[k - 1]) * 0.5 / dx,
+            (phi[k + nx] - phi[k - nx]) * 0.5 / dy,
+        );
+        let (psi_x, psi_y) = (
+            (psi[k + 1] - psi[k - 1]) * 0.5 / dx,
+            (psi[k + nx] - psi[k - nx]) * 0.5 / dy,
+        );
+        residual += (phi_x + psi_y).powi(2) + (phi_y - psi_x).powi(2);
+        norm += field[k].magnitude_squared();
+    }
+
+    if norm > 0.0 {
+        Ok((residual / norm).sqrt())
+    } else {
+        Err(MagnetError::InvalidParameter(
+            "field vanishes over the grid".to_string(),
+        ))
+    }
+}
+
+/// Returns the solution of $`\nabla^2 u = f`$ with the five point Laplacian
+/// on a regular `nx` x `ny` grid, with `u` zero on the edge of the grid and
+/// `source` read at the interior nodes, by conjugate gradients
+fn solve_poisson(source: &[f64], nx: usize, ny: usize, dx: f64, dy: f64) -> Vec<f64> {
+    let (cx, cy) = (1.0 / (dx * dx), 1.0 / (dy * dy));
+    let interior: Vec<usize> = (0..nx * ny)
+        .filter(|k| {
+            let (i, j) = (k % nx, k / nx);
+            i > 0 && i < nx - 1 && j > 0 && j < ny - 1
+        })
+        .collect();
+    // The negative Laplacian, which is positive definite
+    let apply = |u: &[f64], out: &mut [f64]| {
+        for &k in &interior {
+            out[k] =
+                cx * (2.0 * u[k] - u[k - 1] - u[k + 1]) + cy * (2.0 * u[k] - u[k - nx] - u[k + nx]);
+        }
+    };
+    let dot = |a: &[f64], b: &[f64]| interior.iter().map(|&k| a[k] * b[k]).sum::<f64>();
+
+    let mut u = vec![0.0; nx * ny];
+    let mut r = vec![0.0; nx * ny];
+    for &k in &interior {
+        r[k] = -source[k];
+    }
+    let mut p = r.clone();
+    let mut q = vec![0.0; nx * ny];
+    let mut rr = dot(&r, &r);
+    let target = 1e-28 * rr;
+
+    for _ in 0..10 * interior.len() {
+        if rr <= target {
+            break;
+        }
+        apply(&p, &mut q);
+        let step = rr / dot(&p, &q);
+        for &k in &interior {
+            u[k] += step * p[k];
+            r[k] -= step * q[k];
+        }
+        let rr_next = dot(&r, &r);
+        for &k in &interior {
+            p[k] = r[k] + rr_next / rr * p[k];
+        }
+        rr = rr_next;
+    }
+    u
+}
+
+/// Returns the normalised, cyclic spatial autocorrelation of
+/// $`|\mathbf{B}|`$ over a grid of `nx` x `ny` points, with x the fastest
+/// varying index of `field`.
+///
+/// The value for a lag of `(dx, dy)` grid points is stored at `dy * nx + dx`,
+/// and is 1 at zero lag. The mean of $`|\mathbf{B}|`$ is subtracted first, so
+/// a periodic field gives peaks at multiples of its period. A uniform field
+/// has no structure and returns all zeros.
+pub fn field_autocorrelation(
+    field: &[Point2],
+    nx: usize,
+    ny: usize,
+) -> Result<Vec<f64>, MagnetError> {
+    check_grid(field.len(), nx, ny)?;
+
+    let magnitudes: Vec<f64> = field.iter().map(|b| b.magnitude()).collect();
+    let mean = magnitudes.iter().sum::<f64>() / magnitudes.len() as f64;
+    let signal: Vec<Complex> = magnitudes
+        .iter()
+        .map(|b| Complex::new(b - mean, 0.0))
+        .collect();
+
+    // Wiener-Khinchin: the autocorrelation is the inverse transform of the
+    // power spectrum, and the inverse of a real spectrum is its forward
+    // transform up to normalisation
+    let power: Vec<Complex> = dft_2d(&signal, nx, ny)
+        .iter()
+        .map(|x| Complex::new(x.norm_squared(), 0.0))
+        .collect();
+    let correlation = dft_2d(&power, nx, ny);
+
+    let zero_lag = correlation[0].re;
+    if zero_lag > 0.0 {
+        Ok(correlation.iter().map(|c| c.re / zero_lag).collect())
+    } else {
+        Ok(vec![0.0; nx * ny])
+    }
+}
+
+/// Returns an error unless `n_points` fills a non-empty `nx` x `ny` grid
+fn check_grid(n_points: usize, nx: usize, ny: usize) -> Result<(), MagnetError> {
+    if nx == 0 || ny == 0 || n_points != nx * ny {
+        return Err(MagnetError::InvalidParameter(format!(
+            "field of {} points does not match a {} x {} grid",
+            n_points, nx, ny
+        )));
+    }
+    Ok(())
+}
+
+/// Returns the magnitude of the 2D discrete Fourier transform of a
+/// `component` of the field over an `nx` x `ny` grid stored with x the
+/// fastest varying index.
+///
+/// The magnitude at spatial frequency `(i / (nx dx), j / (ny dy))` is stored at
+/// `[j][i]`, and is normalised by the number of points: a component
+/// $`A \cos(2 \pi k x / L)`$ gives two peaks of height `A / 2` at `i = k` and
+/// `i = nx - k`, and the mean field is at `[0][0]`.
+pub fn field_spectrum_2d(
+    field: &[Point2],
+    nx: usize,
+    ny: usize,
+    component: Component,
+) -> Result<Vec<Vec<f64>>, MagnetError> {
+    check_grid(field.len(), nx, ny)?;
+
+    let signal: Vec<Complex> = field
+        .iter()
+        .map(|b| Complex::new(component.of(*b), 0.0))
+        .collect();
+    let norm = (nx * ny) as f64;
+    Ok(dft_2d(&signal, nx, ny)
+        .chunks(nx)
+        .map(|row| row.iter().map(|x| x.norm() / norm).collect())
+        .collect())
+}
+
+/// Gaussian kernels of [`sensor_response`] are truncated beyond this many
+/// standard deviations
+pub const SENSOR_KERNEL_CUTOFF: f64 = 4.0;
+
+/// Returns the field over a grid of `nx` x `ny` points, with x the fastest
+/// varying index of `field`, as seen by a sensor that averages over a
+/// Gaussian footprint of standard deviation `sigma_cells` grid spacings.
+///
+/// Each component is convolved with the kernel, one axis at a time, and the
+/// kernel is truncated at [`SENSOR_KERNEL_CUTOFF`] standard deviations. Near
+/// the edges of the grid the weights are renormalised over the available
+/// points, so a uniform field is unchanged. The sum of the field over the
+/// grid is conserved if the field vanishes within twice the kernel's reach,
+/// `2 * SENSOR_KERNEL_CUTOFF * sigma_cells` points, of the edges. A
+/// `sigma_cells` of 0 returns the field unchanged.
+///
+/// Returns an error if `field` does not match the grid, or if `sigma_cells`
+/// is negative or not finite.
+pub fn sensor_response(
+    field: &[Point2],
+    nx: usize,
+    ny: usize,
+    sigma_cells: f64,
+) -> Result<Vec<Point2>, MagnetError> {
+    check_grid(field.len(), nx, ny)?;
+    if !sigma_cells.is_finite() || sigma_cells < 0.0 {
+        return Err(MagnetError::InvalidParameter(format!(
+            "sigma_cells must be non-negative, got {}",
+            sigma_cells
+        )));
+    }
+    if sigma_cells == 0.0 {
+        return Ok(field.to_vec());
+    }
+
+    let reach = (SENSOR_KERNEL_CUTOFF * sigma_cells).floor() as usize;
+    let kernel: Vec<f64> = (0..=reach)
+        .map(|k| (-0.5 * (k as f64 / sigma_cells).powi(2)).exp())
+        .collect();
+
+    // Convolves the line of `n` values starting at `offset` with spacing
+    // `stride`
+    let smooth_line =
+        |values: &[Point2], out: &mut [Point2], offset: usize, stride: usize, n: usize| {
+            for i in 0..n {
+                let (lower, upper) = (i.saturating_sub(reach), (i + reach).min(n - 1));
+                let mut sum = Point2::zero();
+                let mut weight = 0.0;
+                for k in lower..=upper {
+                    let w = kernel[k.abs_diff(i)];
+                    sum += values[offset + k * stride].scale(w);
+                    weight += w;
+                }
+                out[offset + i * stride] = sum.scale(1.0 / weight);
+            }
+        };
+
+    let mut rows = field.to_vec();
+    for j in 0..ny {
+        smooth_line(field, &mut rows, j * nx, 1, nx);
+    }
+    let mut smoothed = rows.clone();
+    for i in 0..nx {
+        smooth_line(&rows, &mut smoothed, i, nx, ny);
+    }
+    Ok(smoothed)
+}
+
+/// Returns the 2D discrete Fourier transform of a `nx` x `ny` grid stored
+/// with x the fastest varying index
+fn dft_2d(grid: &[Complex], nx: usize, ny: usize) -> Vec<Complex> {
+    let mut rows: Vec<Complex> = grid.chunks(nx).flat_map(dft).collect();
+
+    for i in 0..nx {
+        let column: Vec<Complex> = (0..ny).map(|j| rows[j * nx + i]).collect();
+        for (j, x) in dft(&column).into_iter().enumerate() {
+            rows[j * nx + i] = x;
+        }
+    }
+    rows
+}
+
+/// Statistics of the field at a point over a set of magnet configurations,
+/// returned by [`sweep_statistics`].
+///
+/// `std_dev`, `min` and `max` are taken per component.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FieldStats {
+    /// Field of the unperturbed magnet
+    pub nominal: Point2,
+    pub mean: Point2,
+    /// Population standard deviation
+    pub std_dev: Point2,
+    pub min: Point2,
+    pub max: Point2,
+    /// Number of configurations
+    pub count: usize,
+}
+
+/// Returns the statistics of the field at `point` over the perturbed
+/// configurations `parameter_samples` of the magnet `base`, e.g. for a Monte
+/// Carlo study of manufacturing tolerances.
+pub fn sweep_statistics(
+    base: &Rectangle,
+    point: Point2,
+    parameter_samples: &[Rectangle],
+) -> Result<FieldStats, MagnetError> {
+    if parameter_samples.is_empty() {
+        return Err(MagnetError::InvalidParameter(
+            "at least one parameter sample is needed".to_string(),
+        ));
+    }
+
+    let nominal = base.get_field(&point)?;
+    let fields = parameter_samples
+        .iter()
+        .map(|magnet| Ok(magnet.get_field(&point)?))
+        .collect::<Result<Vec<Point2>, MagnetError>>()?;
+
+    let count = fields.len();
+    let mut sum = Point2::zero();
+    let mut min = Point2::new(f64::INFINITY, f64::INFINITY);
+    let mut max = Point2::new(f64::NEG_INFINITY, f64::NEG_INFINITY);
+    for b in &fields {
+        sum += *b;
+        min = Point2::new(min.x.min(b.x), min.y.min(b.y));
+        max = Point2::new(max.x.max(b.x), max.y.max(b.y));
+    }
+    let mean = sum.scale(1.0 / count as f64);
+
+    let mut variance = Point2::zero();
+    for b in &fields {
+        let d = *b - mean;
+        variance += Point2::new(d.x * d.x, d.y * d.y);
+    }
+    let variance = variance.scale(1.0 / count as f64);
+
+    Ok(FieldStats {
+        nominal,
+        mean,
+        std_dev: Point2::new(variance.x.sqrt(), variance.y.sqrt()),
+        min,
+        max,
+        count,
+    })
+}
+
+/// Returns the mean and the per component standard deviation of the field
+/// at `point` when the center of `nominal` is displaced in x and y by
+/// independent normal errors of standard deviation `position_sigma`.
+///
+/// The `n_samples` positions are drawn reproducibly from `seed` and the
+/// statistics taken with [`sweep_statistics`]. The sampling error of the
+/// mean falls as $`1/\sqrt{n}`$.
+pub fn tolerance_averaged_field(
+    nominal: &Rectangle,
+    point: Point2,
+    position_sigma: f64,
+    n_samples: usize,
+    seed: u64,
+) -> Result<(Point2, Point2), MagnetError> {
+    if position_sigma.is_nan() || position_sigma < 0.0 {
+        return Err(MagnetError::InvalidParameter(format!(
+            "position tolerance must be non-negative, got {}",
+            position_sigma
+        )));
+    }
+
+    let mut rng = Rng::new(seed);
+    let samples: Vec<Rectangle> = (0..n_samples)
+        .map(|_| {
+            let mut magnet = *nominal;
+            magnet.center += Point2::new(
+                rng.gaussian(0.0, position_sigma),
+                rng.gaussian(0.0, position_sigma),
+            );
+            magnet
+        })
+        .collect();
+
+    let stats = sweep_statistics(nominal, point, &samples)?;
+    Ok((stats.mean, stats.std_dev))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::magnets::magnet2d::analysis::{
+        adaptive_seed_density, azimuthal_average, field_autocorrelation, field_centroid,
+        field_histogram, field_on_boundary, field_overlap, field_ripple, field_spatial_moments,
+        field_spectrum_2d, field_uniformity, field_vs_rotation, helmholtz_residual,
+        optimal_scan_path, profile_match_score, sensor_response, sweep_statistics,
+        symmetry_decompose, tolerance_averaged_field,
+    };
+    use crate::magnets::magnet2d::collection::MagnetCollection2D;
+    use crate::magnets::magnet2d::gradient::field_gradient;
+    use crate::magnets::magnet2d::grid::Component;
+    use crate::magnets::magnet2d::{Magnet2D, Rectangle};
+    use crate::utils::points::Points;
+    use crate::utils::points2::{Point2, Points2};
+    use crate::PI;
+
+    #[test]
+    fn symmetric_rotor_is_periodic() {
+        // Four radially magnetised magnets with 90 degree symmetry about (1, 0)
+        let pivot = Point2::new(1.0, 0.0);
+        let mut rotor = MagnetCollection2D::new();
+        for k in 0..4 {
+            let alpha = 90.0 * k as f64;
+            let center = pivot + Point2::new(0.0, 1.5).rotate(alpha * PI / 180.);
+            rotor.push(Rectangle::new(1.0, 0.5, center, alpha, 1.0, 90.0));
+        }
+
+        let angles = [10.0, 100.0, 190.0, 280.0, 55.0];
+        let probe = Point2::new(1.3, 3.0);
+        let field = field_vs_rotation(&rotor, pivot, &angles, probe).unwrap();
+        for b in &field[1..4] {
+            assert!((*b - field[0]).magnitude() < 1e-12);
+        }
+        assert!((field[4] - field[0]).magnitude() > 1e-3);
+
+        // Rotating by zero is the plain field of the rotor
+        let at_rest = field_vs_rotation(&rotor, pivot, &[0.0], probe).unwrap();
+        assert!((at_rest[0] - rotor.get_field(&probe).unwrap()).magnitude() < 1e-15);
+    }
+
+    #[test]
+    fn periodic_field_autocorrelation() {
+        // |B| varies with a period of 4 grid points in x and is constant in y
+        let (nx, ny) = (16, 6);
+        let field: Vec<Point2> = (0..ny)
+            .flat_map(|_| {
+                (0..nx).map(|i| Point2::new(0.0, 2.0 + (2.0 * PI * i as f64 / 4.0).cos()))
+            })
+            .collect();
+        let correlation = field_autocorrelation(&field, nx, ny).unwrap();
+
+        assert_eq!(nx * ny, correlation.len());
+        for dx in &[0, 4, 8, 12] {
+            assert!((correlation[*dx] - 1.0).abs() < 1e-12);
+            assert!((correlation[3 * nx + dx] - 1.0).abs() < 1e-12);
+        }
+        assert!((correlation[2] + 1.0).abs() < 1e-12);
+        assert!(correlation[1].abs() < 1e-12);
+
+        assert!(field_autocorrelation(&field, nx + 1, ny).is_err());
+        assert!(field_autocorrelation(&[], 0, 0).is_err());
+    }
+
+    #[test]
+    fn spectrum_peaks_at_grating_frequency() {
+        // B_y has 3 periods across the grid in x and 1 in y, and B_x has 5 in
+        // x alone
+        let (nx, ny) = (32, 8);
+        let field: Vec<Point2> = (0..ny)
+            .flat_map(|j| {
+                (0..nx).map(move |i| {
+                    let phase = 2.0 * PI * (3.0 * i as f64 / nx as f64 + j as f64 / ny as f64);
+                    let b_x = 1.5 * (2.0 * PI * 5.0 * i as f64 / nx as f64).sin();
+                    Point2::new(b_x, 0.5 + 2.0 * phase.cos())
+                })
+            })
+            .collect();
+
+        let spectrum = field_spectrum_2d(&field, nx, ny, Component::Y).unwrap();
+        assert_eq!((ny, nx), (spectrum.len(), spectrum[0].len()));
+        assert!((spectrum[0][0] - 0.5).abs() < 1e-12);
+        assert!((spectrum[1][3] - 1.0).abs() < 1e-12);
+        assert!((spectrum[ny - 1][nx - 3] - 1.0).abs() < 1e-12);
+        let total: f64 = spectrum.iter().flatten().sum();
+        assert!((total - 2.5).abs() < 1e-10);
+
+        let spectrum = field_spectrum_2d(&field, nx, ny, Component::X).unwrap();
+        assert!((spectrum[0][5] - 0.75).abs() < 1e-12);
+        assert!((spectrum[0][nx - 5] - 0.75).abs() < 1e-12);
+        let total: f64 = spectrum.iter().flatten().sum();
+        assert!((total - 1.5).abs() < 1e-10);
+
+        assert!(field_spectrum_2d(&field, nx, ny + 1, Component::Y).is_err());
+    }
+
+    #[test]
+    fn scan_path_is_denser_near_magnet() {
+        let magnet = Rectangle::new(1.0, 1.0, (0.0, 0.0), 0.0, 1.0, 90.0);
+        let bbox = (Point2::new(-4.0, -4.0), Point2::new(4.0, 4.0));
+        let path = optimal_scan_path(&magnet, bbox, 2000).unwrap();
+        assert_eq!(2000, path.len());
+        assert!(path
+            .iter()
+            .all(|p| p.x > -4.0 && p.x < 4.0 && p.y > -4.0 && p.y < 4.0));
+
+        // Samples per unit area within 1.5 of the center and beyond 3
+        let near = path.iter().filter(|p| p.magnitude() < 1.5).count() as f64;
+        let far = path.iter().filter(|p| p.magnitude() > 3.0).count() as f64;
+        let far_area = 64.0 - PI * 9.0;
+        assert!(near / (PI * 2.25) > 5.0 * far / far_area);
+        assert!(far > 0.0);
+
+        // Consecutive samples stay close, apart from the step between rows
+        let steps = path
+            .windows(2)
+            .filter(|w| (w[1] - w[0]).magnitude() > 1.0)
+            .count();
+        assert!(steps < 5);
+    }
+
+    #[test]
+    fn azimuthal_average_of_axisymmetric_field() {
+        // A field of magnitude 0.3 r + 0.1 about a center between the points
+        let center = Point2::new(0.55, -0.25);
+        let mut points = Vec::new();
+        for j in 0..41 {
+            for i in 0..41 {
+                points.push(Point2::new(-1.5 + 0.1 * i as f64, -2.2 + 0.1 * j as f64));
+            }
+        }
+        let field: Vec<Point2> = points
+            .iter()
+            .map(|p| {
+                let d = *p - center;
+                let r = d.magnitude();
+                Point2::new(-d.y, d.x).scale((0.3 * r + 0.1) / r)
+            })
+            .collect();
+
+        let profile = azimuthal_average(&field, &points, center, 12).unwrap();
+        assert_eq!(12, profile.len());
+        for (radius, magnitude) in &profile {
+            assert!((magnitude - (0.3 * radius + 0.1)).abs() < 1e-12);
+        }
+        for pair in profile.windows(2) {
+            assert!(pair[1].0 > pair[0].0);
+        }
+
+        assert!(azimuthal_average(&field, &points[1..], center, 12).is_err());
+        assert!(azimuthal_average(&field, &points, center, 0).is_err());
+    }
+
+    #[test]
+    fn sensor_response_broadens_spike() {
+        let (nx, ny) = (41, 37);
+        let mut field = vec![Point2::zero(); nx * ny];
+        let centre = 18 * nx + 20;
+        field[centre] = Point2::new(1.0, -2.0);
+        let smoothed = sensor_response(&field, nx, ny, 2.0).unwrap();
+
+        let total = smoothed.iter().fold(Point2::zero(), |sum, b| sum + *b);
+        assert!((total - field[centre]).magnitude() < 1e-12);
+
+        // The peak is lowered, spread over its neighbours, and symmetric
+        assert!(smoothed[centre].magnitude() < 0.1 * field[centre].magnitude());
+        assert!(smoothed[centre + 2].magnitude() > 0.0);
+        assert!((smoothed[centre + 3] - smoothed[centre - 3]).magnitude() < 1e-15);
+        assert!((smoothed[centre + 3 * nx] - smoothed[centre + 3]).magnitude() < 1e-15);
+
+        let wider = sensor_response(&field, nx, ny, 2.5).unwrap();
+        assert!(wider[centre].magnitude() < smoothed[centre].magnitude());
+    }
+
+    #[test]
+    fn sensor_response_keeps_uniform_field() {
+        let field = vec![Point2::new(0.3, 0.4); 12 * 7];
+        for b in sensor_response(&field, 12, 7, 1.5).unwrap() {
+            assert!((b - Point2::new(0.3, 0.4)).magnitude() < 1e-15);
+        }
+        assert_eq!(field, sensor_response(&field, 12, 7, 0.0).unwrap());
+
+        assert!(sensor_response(&field, 12, 6, 1.5).is_err());
+        assert!(sensor_response(&field, 12, 7, -1.0).is_err());
+        assert!(sensor_response(&field, 12, 7, f64::NAN).is_err());
+    }
+
+    #[test]
+    fn ripple_peaks_at_array_pitch() {
+        // Alternating up/down magnets, so the field has a period of 2
+        let mut array = MagnetCollection2D::new();
+        for i in 0..16 {
+            let theta = if i % 2 == 0 { 90.0 } else { 270.0 };
+            array.push(Rectangle::new(
+                1.0,
+                1.0,
+                (-7.5 + i as f64, 0.0),
+                0.0,
+                1.0,
+                theta,
+            ));
+        }
+
+        // Scan 4 periods over the middle of the array. Both signed components
+        // have the period of the array, |B| repeats every magnet.
+        for &(component, expected) in &[
+            (Component::Y, 0.5),
+            (Component::X, 0.5),
+            (Component::Magnitude, 1.0),
+        ] {
+            let (samples, spectrum) = field_ripple(
+                &array,
+                Point2::new(-4.0, 0.8),
+                Point2::new(4.0, 0.8),
+                64,
+                component,
+            )
+            .unwrap();
+            assert_eq!(64, samples.len());
+            assert_eq!(33, spectrum.len());
+
+            let (k_max, _) = spectrum
+                .iter()
+                .enumerate()
+                .skip(1)
+                .fold(
+                    (0, 0.0),
+                    |acc, (k, a)| if *a > acc.1 { (k, *a) } else { acc },
+                );
+            let frequency = k_max as f64 / 8.0;
+            assert!((frequency - expected).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn symmetric_pair_centroid() {
+        let mut collection = MagnetCollection2D::new();
+        collection.push(Rectangle::new(1.0, 1.0, (-1.0, 0.5), 0.0, 1.0, 90.0));
+        collection.push(Rectangle::new(1.0, 1.0, (1.0, 0.5), 0.0, 1.0, 90.0));
+
+        // The grid is symmetric about (0, 0.5) and avoids the magnet corners
+        let points: Vec<Point2> = (0..21)
+            .flat_map(|i| {
+                (0..21).map(move |j| Point2::new(-3.1 + 0.31 * i as f64, -2.6 + 0.31 * j as f64))
+            })
+            .collect();
+        let field: Vec<Point2> = points
+            .iter()
+            .map(|point| collection.get_field(point).unwrap())
+            .collect();
+
+        let centroid = field_centroid(&points, &field);
+        assert!((centroid - Point2::new(0.0, 0.5)).magnitude() < 1e-10);
+    }
+
+    #[test]
+    fn overlap_with_itself_and_negation() {
+        let magnet = Rectangle::new(1.0, 2.0, (0.0, 0.0), 15.0, 1.0, 60.0);
+        let points: Vec<Point2> = (0..10)
+            .map(|i| Point2::new(0.4 * i as f64 - 2.0, 1.5))
+            .collect();
+        let field: Vec<Point2> = points
+            .iter()
+            .map(|p| magnet.get_field(p).unwrap())
+            .collect();
+        let negated: Vec<Point2> = field.iter().map(|b| -*b).collect();
+        let scaled: Vec<Point2> = field.iter().map(|b| b.scale(3.0)).collect();
+
+        assert!((field_overlap(&field, &field) - 1.0).abs() < 1e-14);
+        assert!((field_overlap(&field, &negated) + 1.0).abs() < 1e-14);
+        assert!((field_overlap(&field, &scaled) - 1.0).abs() < 1e-14);
+        assert!(field_overlap(&field, &[Point2::zero(); 10]).is_nan());
+    }
+
+    #[test]
+    fn profile_match_of_itself_and_negation() {
+        let magnet = Rectangle::new(1.0, 1.0, (0.0, 0.0), 0.0, 1.0, 60.0);
+        let field: Vec<Point2> = (0..20)
+            .map(|i| magnet.get_field(&Point2::new(-1.0 + 0.1 * i as f64, 0.8)))
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let reversed: Vec<Point2> = field.iter().map(|b| b.scale(-1.0)).collect();
+        let shifted: Vec<Point2> = field.iter().map(|b| b.scale(0.9)).collect();
+
+        assert_eq!(1.0, profile_match_score(&field, &field).unwrap());
+        assert!((profile_match_score(&reversed, &field).unwrap() + 1.0).abs() < 1e-12);
+        assert!((profile_match_score(&shifted, &field).unwrap() - 0.9).abs() < 1e-12);
+        assert!(profile_match_score(&field[1..], &field).is_err());
+    }
+
+    #[test]
+    fn seeds_concentrate_near_magnet() {
+        let mut collection = MagnetCollection2D::new();
+        collection.push(Rectangle::new(1.0, 1.0, (0.0, 0.0), 0.0, 1.0, 90.0));
+        let bbox = (Point2::new(-4.0, -4.0), Point2::new(4.0, 4.0));
+        let seeds = adaptive_seed_density(&collection, bbox, 16).unwrap();
+
+        let count_in = |center: Point2| {
+            seeds
+                .iter()
+                .filter(|s| (s.x - center.x).abs() < 1.0 && (s.y - center.y).abs() < 1.0)
+                .count()
+        };
+        assert!(count_in(Point2::new(0.0, 0.0)) > 4 * count_in(Point2::new(3.0, 3.0)).max(1));
+        for seed in &seeds {
+            assert!(seed.x.abs() <= 4.0 && seed.y.abs() <= 4.0);
+        }
+        assert!(adaptive_seed_density(&collection, bbox, 0).is_err());
+    }
+
+    #[test]
+    fn magnetostatic_field_has_small_helmholtz_residual() {
+        let magnet = Rectangle::new(1.0, 1.0, (0.0, 0.0), 0.0, 1.0, 30.0);
+        let grid = |n: usize| -> Vec<Point2> {
+            let step = 1.0 / (n - 1) as f64;
+            (0..n * n)
+                .map(|k| Point2::new(1.0 + (k % n) as f64 * step, 0.8 + (k / n) as f64 * step))
+                .collect()
+        };
+
+        let residuals: Vec<f64> = [11, 21, 41]
+            .iter()
+            .map(|n| {
+                let points = grid(*n);
+                let field: Vec<Point2> = points
+                    .iter()
+                    .map(|p| magnet.get_field(p).unwrap())
+                    .collect();
+                helmholtz_residual(&field, &points, *n, *n).unwrap()
+            })
+            .collect();
+        // Halving the spacing quarters the residual
+        for pair in residuals.windows(2) {
+            let ratio = pair[0] / pair[1];
+            assert!(ratio > 3.5 && ratio < 4.5);
+        }
+        assert!(residuals[2] < 2e-4);
+
+        // Diverging and rotating fields are not magnetostatic
+        let points = grid(11);
+        let center = Point2::new(1.5, 1.3);
+        let source: Vec<Point2> = points.iter().map(|p| *p - center).collect();
+        assert!(helmholtz_residual(&source, &points, 11, 11).unwrap() > 0.1);
+        let vortex: Vec<Point2> = source.iter().map(|r| Point2::new(-r.y, r.x)).collect();
+        assert!(helmholtz_residual(&vortex, &points, 11, 11).unwrap() > 0.1);
+
+        assert!(helmholtz_residual(&source, &points, 11, 10).is_err());
+        assert!(helmholtz_residual(&source, &points[1..], 11, 11).is_err());
+        assert!(helmholtz_residual(&source[..22], &points[..22], 11, 2).is_err());
+        let mut irregular = points.clone();
+        irregular[60].x += 0.01;
+        assert!(helmholtz_residual(&source, &irregular, 11, 11).is_err());
+        let zero = vec![Point2::zero(); 121];
+        assert!(helmholtz_residual(&zero, &points, 11, 11).is_err());
+    }
+
+    #[test]
+    fn symmetric_field_has_no_odd_moments() {
+        // The field of a rectangle is even about its centre
+        let magnet = Rectangle::new(1.0, 0.5, (0.0, 0.0), 0.0, 1.0, 60.0);
+        let points: Vec<Point2> = (0..21)
+            .flat_map(|j| {
+                (0..21).map(move |i| Point2::new(-2.0 + 0.2 * i as f64, -2.0 + 0.2 * j as f64))
+            })
+            .collect();
+        let field: Vec<Point2> = points
+            .iter()
+            .map(|p| magnet.get_field(p).unwrap())
+            .collect();
+        let moments = field_spatial_moments(&field, &points, 3).unwrap();
+
+        assert_eq!(10, moments.len());
+        assert!((moments[0] - 1.0).abs() < 1e-12);
+        for index in &[1, 2, 6, 7, 8, 9] {
+            assert!(moments[*index].abs() < 1e-12);
+        }
+        assert!(moments[3] > 0.0 && moments[5] > 0.0);
+
+        let centroid = field_centroid(&points, &field);
+        assert!(centroid.magnitude() < 1e-12);
+    }
+
+    #[test]
+    fn spatial_moments_of_two_points() {
+        let points = vec![Point2::new(1.0, 2.0), Point2::new(3.0, 0.0)];
+        let field = vec![Point2::new(0.0, 1.0), Point2::new(3.0, 0.0)];
+        let moments = field_spatial_moments(&field, &points, 2).unwrap();
+        let expected = [1.0, 2.5, 0.5, 7.0, 0.5, 1.0];
+        for (m, e) in moments.iter().zip(&expected) {
+            assert!((m - e).abs() < 1e-12);
+        }
+        assert!(field_spatial_moments(&[Point2::zero()], &points[..1], 2).is_err());
+    }
+
+    #[test]
+    fn zero_field_centroid() {
+        let points = vec![Point2::new(1.0, 2.0)];
+        let centroid = field_centroid(&points, &[Point2::zero()]);
+        assert!(centroid.x.is_nan() && centroid.y.is_nan());
+    }
+
+    #[test]
+    fn symmetric_perturbations_average_to_nominal() {
+        let base = Rectangle::new(1.0, 1.0, (0.0, 0.0), 0.0, 1.0, 90.0);
+        let point = Point2::new(0.3, 1.2);
+
+        let mut samples = Vec::new();
+        for d in &[-0.01, 0.01] {
+            samples.push(Rectangle::new(1.0 + d, 1.0, (0.0, 0.0), 0.0, 1.0, 90.0));
+            samples.push(Rectangle::new(1.0, 1.0 + d, (0.0, 0.0), 0.0, 1.0, 90.0));
+            samples.push(Rectangle::new(1.0, 1.0, (*d, 0.0), 0.0, 1.0, 90.0));
+            samples.push(Rectangle::new(1.0, 1.0, (0.0, *d), 0.0, 1.0, 90.0));
+        }
+
+        let stats = sweep_statistics(&base, point, &samples).unwrap();
+        assert_eq!(stats.count, 8);
+        assert!((stats.mean - stats.nominal).magnitude() < 1e-4 * stats.nominal.magnitude());
+        assert!(stats.std_dev.x > 0.0 && stats.std_dev.y > 0.0);
+        assert!(stats.min.x <= stats.mean.x && stats.mean.x <= stats.max.x);
+        assert!(stats.min.y <= stats.mean.y && stats.mean.y <= stats.max.y);
+        assert!(sweep_statistics(&base, point, &[]).is_err());
+    }
+
+    #[test]
+    fn uniformity_of_samples() {
+        let uniform = vec![Point2::new(0.0, 1.0), Point2::new(1.0, 0.0)];
+        assert_eq!(0.0, field_uniformity(&uniform));
+
+        let field = vec![
+            Point2::new(0.0, 0.9),
+            Point2::new(0.0, 1.0),
+            Point2::new(1.1, 0.0),
+        ];
+        assert!((field_uniformity(&field) - 0.2).abs() < 1e-14);
+        assert!(field_uniformity(&[Point2::zero()]).is_nan());
+    }
+
+    #[test]
+    fn histogram_of_known_magnitudes() {
+        let field: Vec<Point2> = [0.0, 0.1, 0.25, 0.5, 0.5, 0.9, 1.0]
+            .iter()
+            .map(|b| Point2::new(0.6 * b, -0.8 * b))
+            .collect();
+        let (edges, counts) = field_histogram(&field, 4);
+        assert_eq!(5, edges.len());
+        assert!((edges[1] - 0.25).abs() < 1e-15 && (edges[4] - 1.0).abs() < 1e-15);
+        assert_eq!(vec![2, 1, 2, 2], counts);
+
+        let (_, counts) = field_histogram(&[Point2::new(0.0, 1.0); 3], 2);
+        assert_eq!(vec![3, 0], counts);
+    }
+
+    #[test]
+    fn symmetric_pair_has_no_antisymmetric_part() {
+        // Mirror images about the y axis, the magnetisation angle 60 maps to 120
+        let mut pair = MagnetCollection2D::new();
+        pair.push(Rectangle::new(1.0, 0.5, (-1.0, 0.0), 0.0, 1.0, 60.0));
+        pair.push(Rectangle::new(1.0, 0.5, (1.0, 0.0), 0.0, 1.0, 120.0));
+        let points: Vec<Point2> = (0..9)
+            .flat_map(|i| (0..3).map(move |j| Point2::new(-2.0 + 0.5 * i as f64, 0.6 + j as f64)))
+            .collect();
+        let field: Vec<Point2> = points.iter().map(|p| pair.get_field(p).unwrap()).collect();
+
+        let (symmetric, antisymmetric) = symmetry_decompose(&field, &points, Point2::j_hat());
+        for ((b, s), a) in field.iter().zip(&symmetric).zip(&antisymmetric) {
+            assert!(a.magnitude() < 1e-12 * b.magnitude());
+            assert!((*s - *b).magnitude() < 1e-12 * b.magnitude());
+        }
+
+        // Without the mirror partner the part is undefined
+        let (symmetric, _) = symmetry_decompose(&field[..1], &points[..1], Point2::j_hat());
+        assert!(symmetric[0].x.is_nan());
+    }
+
+    #[test]
+    fn normal_field_flips_between_poles() {
+        let magnet = Rectangle::new(1.0, 0.5, (0.3, -0.2), 20.0, 1.0, 90.0);
+        let (points, field) = field_on_boundary(&magnet, 5, 1e-3).unwrap();
+        assert_eq!(20, points.len());
+
+        // Outward normals of the bottom (south) and top (north) faces
+        let up = Point2::new(0.0, 1.0).rotate(20.0 * PI / 180.);
+        for k in 0..5 {
+            assert!(field[k].dot(&up.scale(-1.0)) < 0.0);
+            assert!(field[10 + k].dot(&up) > 0.0);
+            assert!(!magnet.contains(&points[k]) && !magnet.contains(&points[10 + k]));
+        }
+        assert!(field_on_boundary(&magnet, 5, 0.0).is_err());
+    }
+
+    #[test]
+    fn zero_tolerance_is_nominal_field() {
+        let magnet = Rectangle::new(1.0, 0.5, (0.2, -0.1), 30.0, 1.2, 60.0);
+        let point = Point2::new(0.9, 1.1);
+        let (mean, std_dev) = tolerance_averaged_field(&magnet, point, 0.0, 16, 1).unwrap();
+        let nominal = magnet.get_field(&point).unwrap();
+        assert!((mean - nominal).magnitude() < 1e-12);
+        assert!(std_dev.magnitude() < 1e-12);
+    }
+
+    #[test]
+    fn position_tolerance_spreads_field() {
+        let magnet = Rectangle::new(1.0, 1.0, (0.0, 0.0), 0.0, 1.0, 90.0);
+        let point = Point2::new(0.0, 1.0);
+        let (mean, std_dev) = tolerance_averaged_field(&magnet, point, 1e-2, 2000, 9).unwrap();
+        assert!(std_dev.x > 0.0 && std_dev.y > 0.0);
+
+        // To first order the spread is the gradient times the displacement
+        let (d_dx, d_dy) = field_gradient(&magnet, &point, 1e-5).unwrap();
+        let expected_x = 1e-2 * d_dx.x.hypot(d_dy.x);
+        assert!((std_dev.x - expected_x).abs() < 0.1 * expected_x);
+        let nominal = magnet.get_field(&point).unwrap();
+        assert!((mean - nominal).magnitude() < 1e-2 * nominal.magnitude());
+        assert!(tolerance_averaged_field(&magnet, point, -1.0, 10, 0).is_err());
+    }
+}
@@ -0,0 +1,194 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/.
+Copyright 2021 Peter Dunne */
+
+//! Bonded anisotropic magnets
+//!
+//! The easy axes of the powder in a bonded magnet are spread about the
+//! alignment direction with a von Mises distribution. On average this
+//! reduces the magnetisation, and a single part can be sampled by dividing
+//! the magnet into cells, each uniformly magnetised along a drawn easy axis.
+
+use crate::errors::MagnetError;
+use crate::magnets::magnet2d::{CellMagnet2D, Rectangle};
+use crate::magnets::Magnet;
+use crate::utils::points::Points;
+use crate::utils::points2::Points2;
+use crate::utils::random::{von_mises_resultant_length, Rng};
+use std::fmt;
+
+/// Default number of cells along each side of a sampled bonded magnet
+pub const BONDED_RESOLUTION: usize = 16;
+
+/// A rectangular bonded magnet whose local easy axes are spread about the
+/// magnetisation direction of `magnet`
+///
+/// The easy axes are rotated from the nominal direction by angles drawn from
+/// a von Mises distribution of the given `concentration`. A large
+/// concentration is a well aligned magnet, zero an isotropic one.
+///
+/// [`BondedMagnet2D::new`] gives the ensemble averaged field. The field is
+/// linear in the magnetisation, so this is exactly that of `magnet` with the
+/// remanence scaled by the mean resultant length
+/// $`I_1(\kappa)/I_0(\kappa)`$. [`BondedMagnet2D::sampled`] instead gives a
+/// single part, divided into `resolution` x `resolution` cells with sampled
+/// easy axes. The `cells` are summed exactly, and depend only on the seed, so
+/// the field is reproducible.
+///
+/// # Example
+/// ```rust
+/// use magnet_rs::magnets::magnet2d::bonded::BondedMagnet2D;
+/// use magnet_rs::magnets::magnet2d::{Magnet2D, Rectangle};
+/// use magnet_rs::utils::points2::Point2;
+/// let nominal = Rectangle::new(1.0, 1.0, (0.0, 0.0), 0.0, 0.7, 90.0);
+/// let magnet = BondedMagnet2D::new(nominal, 20.0).unwrap();
+/// let field = magnet.get_field(&Point2::new(0.0, 1.0)).unwrap();
+/// ```
+#[derive(Clone)]
+pub struct BondedMagnet2D {
+    pub magnet: Rectangle,
+    pub concentration: f64,
+    pub cells: Vec<Rectangle>,
+}
+
+impl BondedMagnet2D {
+    /// Returns the ensemble average of bonded magnets
+    pub fn new(magnet: Rectangle, concentration: f64) -> Result<BondedMagnet2D, MagnetError> {
+        check_concentration(concentration)?;
+        let j = magnet.magnetisation_vector();
+        let mean = magnet.with_magnetisation(j.scale(von_mises_resultant_length(concentration)));
+
+        Ok(BondedMagnet2D {
+            magnet,
+            concentration,
+            cells: vec![mean],
+        })
+    }
+
+    /// Returns a single bonded magnet, divided into `resolution` x
+    /// `resolution` cells with easy axes drawn from the generator seeded with
+    /// `seed`
+    pub fn sampled(
+        magnet: Rectangle,
+        concentration: f64,
+        seed: u64,
+        resolution: usize,
+    ) -> Result<BondedMagnet2D, MagnetError> {
+        check_concentration(concentration)?;
+        if resolution == 0 {
+            return Err(MagnetError::InvalidParameter(
+                "bonded magnet resolution must be at least 1".to_string(),
+            ));
+        }
+
+        let mut rng = Rng::new(seed);
+        let j = magnet.magnetisation_vector();
+        let cells = magnet
+            .cells(resolution, resolution)
+            .iter()
+            .map(|cell| cell.with_magnetisation(j.rotate(rng.von_mises(0.0, concentration))))
+            .collect();
+
+        Ok(BondedMagnet2D {
+            magnet,
+            concentration,
+            cells,
+        })
+    }
+}
+
+/// Returns an error unless `concentration` is a valid von Mises
+/// concentration
+fn check_concentration(concentration: f64) -> Result<(), MagnetError> {
+    if concentration.is_nan() || concentration < 0.0 {
+        return Err(MagnetError::InvalidParameter(format!(
+            "concentration must be non-negative, got {}",
+            concentration
+        )));
+    }
+    Ok(())
+}
+
+/// Implements Display for bonded magnets.
+impl fmt::Display for BondedMagnet2D {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[bonded {}\tkappa: {}\tcells: {}]",
+            self.magnet,
+            self.concentration,
+            self.cells.len()
+        )
+    }
+}
+
+impl Magnet for BondedMagnet2D {}
+
+impl CellMagnet2D for BondedMagnet2D {
+    fn outline(&self) -> &Rectangle {
+        &self.magnet
+    }
+
+    fn cells(&self) -> &[Rectangle] {
+        &self.cells
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::magnets::magnet2d::bonded::BondedMagnet2D;
+    use crate::magnets::magnet2d::{Magnet2D, Rectangle};
+    use crate::utils::points::Points;
+    use crate::utils::points2::{Point2, Points2};
+
+    #[test]
+    fn aligned_magnet_approaches_uniform() {
+        let nominal = Rectangle::new(1.0, 0.5, (0.5, 0.0), 20.0, 1.0, 90.0);
+        let points = [
+            Point2::new(0.5, 0.8),
+            Point2::new(1.5, -0.6),
+            Point2::new(0.6, 0.1),
+        ];
+
+        let deviation = |magnet: BondedMagnet2D| {
+            points
+                .iter()
+                .map(|p| (magnet.get_field(p).unwrap() - nominal.get_field(p).unwrap()).magnitude())
+                .fold(0.0, f64::max)
+        };
+        let sampled = |kappa| BondedMagnet2D::sampled(nominal, kappa, 3, 16).unwrap();
+        assert!(deviation(BondedMagnet2D::new(nominal, 1e5).unwrap()) < 1e-4 * nominal.jr);
+        // Relative to the remanence, as inside the magnet the field of the
+        // local cell dominates
+        assert!(deviation(sampled(1e5)) < 0.01 * nominal.jr);
+        assert!(deviation(sampled(1e5)) < deviation(sampled(10.0)));
+        assert!(BondedMagnet2D::new(nominal, -1.0).is_err());
+    }
+
+    #[test]
+    fn samples_average_to_ensemble() {
+        let nominal = Rectangle::new(1.0, 0.5, (0.5, 0.0), 20.0, 1.0, 90.0);
+        let kappa = 2.0;
+        let ensemble = BondedMagnet2D::new(nominal, kappa).unwrap();
+        // The mean resultant length for kappa = 2, in the magnet's frame
+        let expected = Point2::new(0.0, 0.697_775);
+        assert!((ensemble.clone().get_j() - expected).magnitude() < 1e-6);
+
+        let n = 40;
+        let mean = (0..n)
+            .map(|seed| {
+                BondedMagnet2D::sampled(nominal, kappa, seed, 8)
+                    .unwrap()
+                    .get_j()
+            })
+            .fold(Point2::zero(), |sum, j| sum + j)
+            .scale(1.0 / n as f64);
+        assert!((mean - expected).magnitude() < 0.02);
+
+        let point = Point2::new(1.5, -0.6);
+        let field = ensemble.get_field(&point).unwrap();
+        let uniform = nominal.get_field(&point).unwrap().scale(0.697_775);
+        assert!((field - uniform).magnitude() < 1e-6 * uniform.magnitude());
+    }
+}
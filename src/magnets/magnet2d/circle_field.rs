@@ -5,40 +5,72 @@ Copyright 2021 Peter Dunne */
 /// Contains magnet field routines for calculating the magnetic field due to an
 /// infinitely long bipolar rod (circle)
 use crate::magnets::magnet2d::Circle;
-use crate::utils::points2::{Point2, Points2, PolarPoint, PolarPoints};
-// use crate::PI;
+use crate::utils::conversions::vector_pol2cart;
+use crate::utils::points2::{Point2, Points2, PolarPoint};
+use crate::PI;
 
 use std::error::Error;
 /// Calculates the 2D magnetic field of an infintely long bipolar rod (circle)
+/// centered at the origin, at a point given in polar coordinates, for a
+/// magnetisation `jr` along `theta` (in degrees).
 ///
-/// The field is returned in a `PolarPoint` struct
+/// Outside the rod the field is that of a line dipole,
+/// $`\mathbf{B} = \frac{R^2}{2 r^2} \left( 2 (\mathbf{J} \cdot \mathbf{\hat{r}}) \mathbf{\hat{r}} - \mathbf{J} \right)`$,
+/// and inside it is uniform, $`\mathbf{B} = \mathbf{J}/2`$.
+///
+/// The field is returned in a `PolarPoint` struct, as the radial and
+/// azimuthal components at the point
 pub fn get_polar_field_circle(
     magnet: &Circle,
     point: &PolarPoint,
 ) -> Result<PolarPoint, Box<dyn Error>> {
-    let prefac = magnet.jr * (magnet.radius / point.rho).powi(2) / 2.0;
-    let b_rho = prefac * point.phi.cos();
-    let b_phi = prefac * point.phi.sin();
-    let field = PolarPoint::new(b_rho, b_phi);
+    let (sin_phi, cos_phi) = (point.phi - magnet.theta * PI / 180.).sin_cos();
+    let j_rho = magnet.jr * cos_phi;
+    let j_phi = -magnet.jr * sin_phi;
+
+    let field = if point.rho < magnet.radius {
+        PolarPoint::new(j_rho / 2.0, j_phi / 2.0)
+    } else {
+        let prefac = (magnet.radius / point.rho).powi(2) / 2.0;
+        PolarPoint::new(prefac * j_rho, -prefac * j_phi)
+    };
 
     Ok(field)
 }
 
+/// Calculates the 2D magnetic field of an infintely long bipolar rod (circle)
+/// centered at the origin
 pub fn get_field_circle(magnet: &Circle, point: &Point2) -> Result<Point2, Box<dyn Error>> {
     let polar_val = point.to_polar();
-    let field = get_polar_field_circle(&magnet, &polar_val).unwrap();
+    let field = get_polar_field_circle(magnet, &polar_val)?;
 
-    Ok(field.to_cartesian())
+    Ok(vector_pol2cart(field, polar_val.phi))
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::magnets::magnet2d::circle_field::get_polar_field_circle;
-    use crate::magnets::magnet2d::Circle;
+    use crate::magnets::magnet2d::circle_field::{get_field_circle, get_polar_field_circle};
+    use crate::magnets::magnet2d::{Circle, Magnet2D};
     use crate::utils::comparison::nearly_equal;
-    use crate::utils::points2::PolarPoint;
+    use crate::utils::points::Points;
+    use crate::utils::points2::{Point2, Points2, PolarPoint};
     use crate::{PI_2, PI_4};
 
+    #[test]
+    fn interior_field_is_half_j() {
+        let magnet = Circle::new(1.0, (0.0, 0.0), 0.0, 1.0, 30.0);
+        let field = get_field_circle(&magnet, &Point2::new(0.2, -0.4)).unwrap();
+        assert!((field - Point2::new(magnet.jx, magnet.jy).scale(0.5)).magnitude() < 1e-15);
+    }
+
+    #[test]
+    fn field_along_magnetisation() {
+        // On the axis of J, B = J R^2 / (2 r^2) parallel to J
+        let magnet = Circle::new(0.5, (1.0, 1.0), 0.0, 1.2, 90.0);
+        let field = magnet.get_field(&Point2::new(1.0, 2.0)).unwrap();
+        assert!(nearly_equal(field.x, 0.0) && nearly_equal(field.y, 0.15));
+    }
+
     #[test]
     fn surface_field_x() {
         let magnet = Circle::default();
@@ -0,0 +1,462 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/.
+Copyright 2021 Peter Dunne */
+
+//! Collections of 2D magnets
+//!
+//! The field of a collection is the superposition of the fields of its
+//! magnets.
+
+use crate::errors::MagnetError;
+use crate::magnets::magnet2d::Magnet2D;
+use crate::utils::points::Points;
+use crate::utils::points2::{Point2, Points2};
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+/// The bound on the magnets of a collection, `Send + Sync` with the `rayon`
+/// feature so that it can be evaluated from several threads
+#[cfg(feature = "rayon")]
+pub trait CollectionMagnet: Magnet2D<Point2> + Send + Sync {}
+#[cfg(feature = "rayon")]
+impl<M: Magnet2D<Point2> + Send + Sync> CollectionMagnet for M {}
+
+/// The bound on the magnets of a collection
+#[cfg(not(feature = "rayon"))]
+pub trait CollectionMagnet: Magnet2D<Point2> {}
+#[cfg(not(feature = "rayon"))]
+impl<M: Magnet2D<Point2>> CollectionMagnet for M {}
+
+#[cfg(feature = "rayon")]
+type BoxedMagnet = Box<dyn Magnet2D<Point2> + Send + Sync>;
+#[cfg(not(feature = "rayon"))]
+type BoxedMagnet = Box<dyn Magnet2D<Point2>>;
+
+/// A collection of 2D magnets of any shape
+///
+/// With the `rayon` feature the magnets must be `Send + Sync`, so that a
+/// collection can be evaluated from several threads.
+///
+/// # Example
+/// ```rust
+/// use magnet_rs::magnets::magnet2d::collection::MagnetCollection2D;
+/// use magnet_rs::magnets::magnet2d::{Circle, Rectangle};
+/// use magnet_rs::utils::points2::Point2;
+/// let mut collection = MagnetCollection2D::new();
+/// collection.push(Rectangle::default());
+/// collection.push(Circle::new(0.5, (2.0, 0.0), 0.0, 1.0, 90.0));
+/// let field = collection.get_field(&Point2::new(1.0, 1.0)).unwrap();
+/// ```
+#[derive(Default)]
+pub struct MagnetCollection2D {
+    pub magnets: Vec<BoxedMagnet>,
+}
+
+impl MagnetCollection2D {
+    pub fn new() -> MagnetCollection2D {
+        MagnetCollection2D {
+            magnets: Vec::new(),
+        }
+    }
+
+    /// Adds a magnet to the collection
+    pub fn push<M: CollectionMagnet + 'static>(&mut self, magnet: M) {
+        self.magnets.push(Box::new(magnet));
+    }
+
+    pub fn len(&self) -> usize {
+        self.magnets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.magnets.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &dyn Magnet2D<Point2>> {
+        self.magnets
+            .iter()
+            .map(|magnet| magnet.as_ref() as &dyn Magnet2D<Point2>)
+    }
+
+    /// Returns the total field of all magnets at `point`
+    pub fn get_field(&self, point: &Point2) -> Result<Point2, MagnetError> {
+        let mut field = Point2::zero();
+        for magnet in self.iter() {
+            field += magnet.get_field(point)?;
+        }
+        Ok(field)
+    }
+
+    /// Returns the total field of all magnets at `point`, accumulating each
+    /// component with compensated (Kahan) summation.
+    ///
+    /// The rounding error of the sum stays of order one ulp of the result,
+    /// independent of the number of magnets, instead of growing with it. This
+    /// matters when many weak contributions are added to a strong one.
+    pub fn total_field_kahan(&self, point: &Point2) -> Result<Point2, MagnetError> {
+        let mut sum = Point2::zero();
+        let mut compensation = Point2::zero();
+        for magnet in self.iter() {
+            let term = magnet.get_field(point)? - compensation;
+            let total = sum + term;
+            compensation = (total - sum) - term;
+            sum = total;
+        }
+        Ok(sum)
+    }
+
+    /// Returns the total field at `point` of the magnets that pass
+    /// [`Magnet2D::validate`], calling `on_invalid` with the index and error
+    /// of each magnet that is skipped instead.
+    ///
+    /// A malformed magnet, e.g. one read from a damaged file, would otherwise
+    /// give a NaN or meaningless field that spoils the whole sum.
+    pub fn total_field<F: FnMut(usize, &MagnetError)>(
+        &self,
+        point: &Point2,
+        mut on_invalid: F,
+    ) -> Result<Point2, MagnetError> {
+        let mut field = Point2::zero();
+        for (k, magnet) in self.iter().enumerate() {
+            match magnet.validate() {
+                Ok(()) => field += magnet.get_field(point)?,
+                Err(error) => on_invalid(k, &error),
+            }
+        }
+        Ok(field)
+    }
+
+    /// Returns true if `point` lies inside any magnet of the collection
+    pub fn contains(&self, point: &Point2) -> bool {
+        self.iter().any(|magnet| magnet.contains(point))
+    }
+}
+
+/// Flags the points where the linear model may be invalid, i.e. points inside
+/// a magnet of the collection where $`|\mathbf{B}|`$ exceeds
+/// `saturation_tesla`.
+///
+/// Points outside all magnets carry no magnetisation and are never flagged.
+pub fn saturation_mask(
+    collection: &MagnetCollection2D,
+    saturation_tesla: f64,
+    points: &[Point2],
+) -> Result<Vec<bool>, MagnetError> {
+    if saturation_tesla.is_nan() || saturation_tesla < 0.0 {
+        return Err(MagnetError::InvalidParameter(format!(
+            "saturation field must be non-negative, got {}",
+            saturation_tesla
+        )));
+    }
+
+    points
+        .iter()
+        .map(|point| {
+            if collection.contains(point) {
+                Ok(collection.get_field(point)?.magnitude() > saturation_tesla)
+            } else {
+                Ok(false)
+            }
+        })
+        .collect()
+}
+
+/// Returns the fraction of the field at `probe` that comes from the magnets
+/// of the collection other than the one at `element_index`,
+///
+/// ```math
+/// c = \frac{|\mathbf{B}_{others}|}{|\mathbf{B}_{element}| + |\mathbf{B}_{others}|}
+/// ```
+///
+/// which is 0 for a perfectly isolated element and 1 for a probe that only
+/// sees the others. Normalising by the sum of the magnitudes, rather than the
+/// magnitude of the total, keeps the fraction below 1 where the contributions
+/// cancel. Returns an error if the index is out of range or the field
+/// vanishes.
+pub fn crosstalk(
+    collection: &MagnetCollection2D,
+    element_index: usize,
+    probe: Point2,
+) -> Result<f64, MagnetError> {
+    if element_index >= collection.len() {
+        return Err(MagnetError::InvalidParameter(format!(
+            "element {} is out of range for a collection of {} magnets",
+            element_index,
+            collection.len()
+        )));
+    }
+
+    let mut own = Point2::zero();
+    let mut others = Point2::zero();
+    for (k, magnet) in collection.iter().enumerate() {
+        if k == element_index {
+            own += magnet.get_field(&probe)?;
+        } else {
+            others += magnet.get_field(&probe)?;
+        }
+    }
+
+    let total = own.magnitude() + others.magnitude();
+    if total > 0.0 {
+        Ok(others.magnitude() / total)
+    } else {
+        Err(MagnetError::Calculation(format!(
+            "field vanishes at the probe {}",
+            probe
+        )))
+    }
+}
+
+/// Returns the sum of the fields at `point` of the magnets of the collection,
+/// each scaled by its entry in `weights`.
+///
+/// Unit weights give the total field, and a single non-zero weight the
+/// field of one magnet. As the field is linear in the magnetisation, a
+/// weight is equivalent to scaling that magnet's remanence. Returns an error
+/// unless there is one weight per magnet.
+pub fn weighted_field(
+    collection: &MagnetCollection2D,
+    weights: &[f64],
+    point: Point2,
+) -> Result<Point2, MagnetError> {
+    if weights.len() != collection.len() {
+        return Err(MagnetError::InvalidParameter(format!(
+            "{} weights for a collection of {} magnets",
+            weights.len(),
+            collection.len()
+        )));
+    }
+
+    let mut field = Point2::zero();
+    for (magnet, weight) in collection.iter().zip(weights) {
+        if *weight != 0.0 {
+            field += magnet.get_field(&point)?.scale(*weight);
+        }
+    }
+    Ok(field)
+}
+
+/// Returns the field of the collection at each point, evaluated in chunks of
+/// `chunk_size` points.
+///
+/// Each chunk is evaluated for all magnets before moving on to the next, which
+/// keeps the working set small for very large point clouds. With the `rayon`
+/// feature the chunks are evaluated in parallel. The result is identical to
+/// evaluating the points one by one.
+pub fn get_field_collection_chunked(
+    collection: &MagnetCollection2D,
+    points: &[Point2],
+    chunk_size: usize,
+) -> Result<Vec<Point2>, MagnetError> {
+    if chunk_size == 0 {
+        return Err(MagnetError::InvalidParameter(
+            "chunk size must be at least 1".to_string(),
+        ));
+    }
+
+    let chunk_field = |chunk: &[Point2]| {
+        chunk
+            .iter()
+            .map(|point| collection.get_field(point))
+            .collect::<Result<Vec<Point2>, MagnetError>>()
+    };
+
+    #[cfg(feature = "rayon")]
+    let chunks: Vec<_> = points.par_chunks(chunk_size).map(chunk_field).collect();
+    #[cfg(not(feature = "rayon"))]
+    let chunks: Vec<_> = points.chunks(chunk_size).map(chunk_field).collect();
+
+    let mut fields = Vec::with_capacity(points.len());
+    for chunk in chunks {
+        fields.extend(chunk?);
+    }
+    Ok(fields)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::magnets::magnet2d::collection::{
+        crosstalk, get_field_collection_chunked, saturation_mask, weighted_field,
+        MagnetCollection2D,
+    };
+    use crate::magnets::magnet2d::{Magnet2D, Rectangle, UniformField2D};
+    use crate::utils::points::Points;
+    use crate::utils::points2::{Point2, Points2};
+
+    #[test]
+    fn collection_field_is_superposition() {
+        let first = Rectangle::new(1.0, 1.0, (-1.0, 0.0), 0.0, 1.0, 90.0);
+        let second = Rectangle::new(1.0, 1.0, (1.0, 0.0), 0.0, 1.0, 45.0);
+        let mut collection = MagnetCollection2D::new();
+        collection.push(first);
+        collection.push(second);
+
+        let point = Point2::new(0.3, 1.2);
+        let expected = first.get_field(&point).unwrap() + second.get_field(&point).unwrap();
+        let field = collection.get_field(&point).unwrap();
+        assert_eq!(2, collection.len());
+        assert!((field - expected).magnitude() < 1e-15);
+    }
+
+    #[test]
+    fn kahan_sum_of_tiny_contributions() {
+        // Each 1e-16 T term is below half an ulp of 1 T and is lost by the
+        // naive sum, the exact total is 1 + 1e-12 T
+        let mut collection = MagnetCollection2D::new();
+        collection.push(UniformField2D::new(Point2::new(1.0, -1.0)));
+        for _ in 0..10_000 {
+            collection.push(UniformField2D::new(Point2::new(1e-16, -1e-16)));
+        }
+        let reference = Point2::new(1.0 + 1e-12, -1.0 - 1e-12);
+
+        let point = Point2::new(0.0, 1.0);
+        let naive = collection.get_field(&point).unwrap();
+        let kahan = collection.total_field_kahan(&point).unwrap();
+
+        let naive_error = (naive - reference).magnitude();
+        let kahan_error = (kahan - reference).magnitude();
+        assert!(naive_error > 1e-13);
+        assert!(kahan_error < 1e-15);
+    }
+
+    #[test]
+    fn saturated_points_flagged() {
+        let mut collection = MagnetCollection2D::new();
+        collection.push(Rectangle::new(1.0, 1.0, (0.0, 0.0), 0.0, 1.0, 90.0));
+
+        // At the centre |B| = 0.5 T, just outside the pole face it is ~0.5 T
+        // but the point carries no magnetisation
+        let points = vec![
+            Point2::new(0.0, 0.0),
+            Point2::new(0.0, 0.51),
+            Point2::new(0.2, 0.1),
+        ];
+        let mask = saturation_mask(&collection, 0.4, &points).unwrap();
+        assert_eq!(vec![true, false, true], mask);
+
+        let mask = saturation_mask(&collection, 0.9, &points).unwrap();
+        assert_eq!(vec![false, false, false], mask);
+    }
+
+    #[test]
+    fn negative_saturation() {
+        let collection = MagnetCollection2D::new();
+        assert!(saturation_mask(&collection, -1.0, &[Point2::zero()]).is_err());
+    }
+
+    #[test]
+    fn chunked_field_matches_pointwise() {
+        let mut collection = MagnetCollection2D::new();
+        collection.push(Rectangle::new(1.0, 1.0, (-1.0, 0.0), 0.0, 1.0, 90.0));
+        collection.push(Rectangle::new(0.5, 2.0, (1.0, 0.5), 10.0, 1.0, 0.0));
+        let points: Vec<Point2> = (0..103)
+            .map(|i| Point2::new(-2.0 + 0.04 * i as f64, 1.5 - 0.03 * i as f64))
+            .collect();
+
+        let pointwise: Vec<Point2> = points
+            .iter()
+            .map(|point| collection.get_field(point).unwrap())
+            .collect();
+        for chunk_size in &[1, 10, 103, 500] {
+            let chunked = get_field_collection_chunked(&collection, &points, *chunk_size).unwrap();
+            assert_eq!(pointwise, chunked);
+        }
+        assert!(get_field_collection_chunked(&collection, &points, 0).is_err());
+    }
+
+    #[cfg(not(feature = "rayon"))]
+    #[test]
+    fn collects_magnets_that_are_not_thread_safe() {
+        use crate::magnets::Magnet;
+        use std::error::Error;
+        use std::rc::Rc;
+
+        struct SharedRectangle(Rc<Rectangle>);
+        impl Magnet for SharedRectangle {}
+        impl Magnet2D<Point2> for SharedRectangle {
+            fn get_field(&self, point: &Point2) -> Result<Point2, Box<dyn Error>> {
+                self.0.get_field(point)
+            }
+            fn get_center(&self) -> Point2 {
+                self.0.get_center()
+            }
+            fn get_size(&self) -> Point2 {
+                self.0.get_size()
+            }
+            fn get_j(self) -> Point2 {
+                self.0.get_j()
+            }
+        }
+
+        let magnet = Rc::new(Rectangle::default());
+        let mut collection = MagnetCollection2D::new();
+        collection.push(SharedRectangle(Rc::clone(&magnet)));
+        let point = Point2::new(0.5, 1.5);
+        assert_eq!(
+            magnet.get_field(&point).unwrap(),
+            collection.get_field(&point).unwrap()
+        );
+    }
+
+    #[test]
+    fn total_field_skips_invalid_magnets() {
+        let valid = Rectangle::new(1.0, 1.0, (0.0, 0.0), 0.0, 1.0, 90.0);
+        let mut collection = MagnetCollection2D::new();
+        collection.push(Rectangle::new(f64::NAN, 1.0, (2.0, 0.0), 0.0, 1.0, 90.0));
+        collection.push(valid);
+        collection.push(Rectangle::new(1.0, 1.0, (-2.0, 0.0), 0.0, 0.0, 90.0));
+
+        let point = Point2::new(0.3, 1.2);
+        let mut skipped = Vec::new();
+        let field = collection
+            .total_field(&point, |k, _| skipped.push(k))
+            .unwrap();
+        assert_eq!(skipped, vec![0, 2]);
+        assert!((field - valid.get_field(&point).unwrap()).magnitude() < 1e-15);
+    }
+
+    #[test]
+    fn isolated_element_has_small_crosstalk() {
+        // A sensor array of small magnets 10 mm apart, probed 0.5 mm above one
+        let mut collection = MagnetCollection2D::new();
+        for k in 0..4 {
+            collection.push(Rectangle::new(
+                1e-3,
+                1e-3,
+                (1e-2 * k as f64, 0.0),
+                0.0,
+                1.0,
+                90.0,
+            ));
+        }
+        let probe = Point2::new(1e-2, 1e-3);
+        let isolated = crosstalk(&collection, 1, probe).unwrap();
+        assert!(isolated > 0.0 && isolated < 0.05);
+
+        // Probed above its neighbour the element is the minority
+        let neighbour = crosstalk(&collection, 2, probe).unwrap();
+        assert!(neighbour > 0.9 && neighbour < 1.0);
+        assert!(crosstalk(&collection, 4, probe).is_err());
+    }
+
+    #[test]
+    fn weights_select_and_scale_magnets() {
+        let first = Rectangle::new(1.0, 1.0, (-1.0, 0.0), 0.0, 1.0, 90.0);
+        let second = Rectangle::new(1.0, 1.0, (1.0, 0.0), 0.0, 1.0, 45.0);
+        let mut collection = MagnetCollection2D::new();
+        collection.push(first);
+        collection.push(second);
+
+        let point = Point2::new(0.3, 1.2);
+        let total = collection.total_field(&point, |_, _| {}).unwrap();
+        let unit = weighted_field(&collection, &[1.0, 1.0], point).unwrap();
+        assert!((unit - total).magnitude() < 1e-15);
+
+        let isolated = weighted_field(&collection, &[0.0, 2.5], point).unwrap();
+        let expected = second.get_field(&point).unwrap().scale(2.5);
+        assert!((isolated - expected).magnitude() < 1e-15);
+        assert!(weighted_field(&collection, &[1.0], point).is_err());
+    }
+}
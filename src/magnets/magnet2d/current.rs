@@ -0,0 +1,245 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/.
+Copyright 2021 Peter Dunne */
+
+//! Line currents in the plane
+//!
+//! Thin conductors lying in the xy plane, modelled alongside the magnets.
+//! A current flowing in the plane produces a field normal to it at points in
+//! the plane, so these routines return $`B_z`$ rather than an in-plane
+//! vector. Conductors normal to the plane, as in [`force_on_current_loop`],
+//! instead feel an in-plane force from the field of a magnet.
+
+use crate::errors::MagnetError;
+use crate::magnets::magnet2d::{GetCenter2D, Magnet2D};
+use crate::utils::points::Points;
+use crate::utils::points2::{Point2, Points2};
+use crate::utils::quadrature::gauss_legendre;
+use crate::{I_4PI, MU0, PI};
+use std::fmt;
+
+/// Default number of quadrature nodes used for arc currents
+pub const ARC_RESOLUTION: usize = 64;
+
+/// A thin circular arc carrying a current `current`, in A, in the xy plane
+///
+/// The arc of radius `radius` about `center` runs anticlockwise from
+/// `start_angle` to `end_angle`, in degrees from +x, and a positive current
+/// flows anticlockwise. The field is integrated numerically with
+/// `resolution` Gauss-Legendre nodes, `ARC_RESOLUTION` by default; increase
+/// it for points close to the wire.
+///
+/// # Example
+/// ```rust
+/// use magnet_rs::magnets::magnet2d::current::ArcCurrent2D;
+/// use magnet_rs::utils::points2::Point2;
+/// let arc = ArcCurrent2D::new((0.0, 0.0), 0.1, 0.0, 90.0, 5.0);
+/// let b_z = arc.get_field(&Point2::new(0.02, 0.03)).unwrap();
+/// println!("{}: B_z = {} T", arc, b_z);
+/// ```
+#[derive(Copy, Clone)]
+pub struct ArcCurrent2D {
+    pub center: Point2,
+    pub radius: f64,
+    pub start_angle: f64,
+    pub end_angle: f64,
+    pub current: f64,
+    pub resolution: usize,
+}
+
+impl ArcCurrent2D {
+    pub fn new<C: GetCenter2D>(
+        center: C,
+        radius: f64,
+        start_angle: f64,
+        end_angle: f64,
+        current: f64,
+    ) -> ArcCurrent2D {
+        ArcCurrent2D {
+            center: center.get_center(),
+            radius,
+            start_angle,
+            end_angle,
+            current,
+            resolution: ARC_RESOLUTION,
+        }
+    }
+
+    /// Returns the field $`B_z`$, in T, at a `point` in the plane of the arc,
+    /// from the Biot-Savart law
+    ///
+    /// ```math
+    /// B_z = \frac{\mu_0 I}{4 \pi} \int \frac{(d\mathbf{l} \times \mathbf{R})_z}{R^3}
+    /// ```
+    ///
+    /// with $`\mathbf{R}`$ from the wire element to the point. The field
+    /// diverges on the wire, where an error is returned.
+    pub fn get_field(&self, point: &Point2) -> Result<f64, MagnetError> {
+        if self.resolution == 0 {
+            return Err(MagnetError::InvalidParameter(
+                "arc resolution must be at least 1".to_string(),
+            ));
+        }
+
+        let start = self.start_angle * PI / 180.;
+        let end = self.end_angle * PI / 180.;
+        let half_span = (end - start) / 2.0;
+        let (nodes, weights) = gauss_legendre(self.resolution);
+
+        let relative = *point - self.center;
+        let mut integral = 0.0;
+        for (x, w) in nodes.iter().zip(&weights) {
+            let angle = start + half_span * (x + 1.0);
+            let (sin, cos) = angle.sin_cos();
+            let r = relative - Point2::new(self.radius * cos, self.radius * sin);
+            let distance = r.magnitude();
+            if distance == 0.0 {
+                return Err(MagnetError::InvalidParameter(format!(
+                    "point {} lies on the wire",
+                    point
+                )));
+            }
+
+            // dl = radius (-sin, cos) d(angle)
+            let cross = -sin * r.y - cos * r.x;
+            integral += w * self.radius * cross / distance.powi(3);
+        }
+
+        Ok(MU0 * I_4PI * self.current * integral * half_span)
+    }
+}
+
+/// Implements Display for arc currents.
+impl fmt::Display for ArcCurrent2D {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[c: {},\tr: {},\tangles: ({}, {}),\tI: {}]",
+            self.center, self.radius, self.start_angle, self.end_angle, self.current
+        )
+    }
+}
+
+/// Returns the force per unit length, in N/m, on each of the conductors of
+/// [`force_on_current_loop`], in the order of `loop_path`
+fn conductor_forces(
+    magnet: &dyn Magnet2D<Point2>,
+    loop_path: &[Point2],
+    current: f64,
+) -> Result<Vec<Point2>, MagnetError> {
+    if loop_path.is_empty() {
+        return Err(MagnetError::InvalidParameter(
+            "a current loop needs at least 1 conductor".to_string(),
+        ));
+    }
+
+    loop_path
+        .iter()
+        .map(|point| {
+            let b = magnet.get_field(point)?;
+            // I z x B
+            Ok(Point2::new(-b.y, b.x).scale(current))
+        })
+        .collect()
+}
+
+/// Returns the net in-plane force per unit length, in N/m, on a set of long
+/// straight conductors due to the field of `magnet`.
+///
+/// As for the magnets, the conductors are infinitely long in z, and each
+/// point of `loop_path` is where one crosses the plane, carrying `current`
+/// in A along +z. Each feels the Lorentz force per unit length
+/// ```math
+/// \mathbf{F} = I \hat{\mathbf{z}} \times \mathbf{B} = I (-B_y, B_x)
+/// ```
+/// and the forces are summed. For conductors spread evenly around a magnet
+/// they squeeze and stretch the loop while largely cancelling, so the net
+/// force is much smaller than that on any one conductor. Returns an error if
+/// `loop_path` is empty or a conductor lies where the field is undefined.
+pub fn force_on_current_loop(
+    magnet: &dyn Magnet2D<Point2>,
+    loop_path: &[Point2],
+    current: f64,
+) -> Result<Point2, MagnetError> {
+    let mut force = Point2::zero();
+    for conductor in conductor_forces(magnet, loop_path, current)? {
+        force += conductor;
+    }
+    Ok(force)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::magnets::magnet2d::current::{
+        conductor_forces, force_on_current_loop, ArcCurrent2D,
+    };
+    use crate::magnets::magnet2d::{Magnet2D, Rectangle};
+    use crate::utils::comparison::nearly_equal;
+    use crate::utils::points2::{Point2, Points2};
+    use crate::MU0;
+
+    #[test]
+    fn loop_center_field() {
+        let radius = 0.05;
+        let current = 3.0;
+        let center = Point2::new(0.2, -0.1);
+        let full = ArcCurrent2D::new(center, radius, 30.0, 390.0, current);
+        let expected = MU0 * current / (2.0 * radius);
+        assert!(nearly_equal(full.get_field(&center).unwrap(), expected));
+
+        let half = ArcCurrent2D::new(center, radius, 0.0, 180.0, current);
+        assert!(nearly_equal(
+            half.get_field(&center).unwrap(),
+            expected / 2.0
+        ));
+    }
+
+    #[test]
+    fn off_center_converges() {
+        let mut arc = ArcCurrent2D::new((0.0, 0.0), 1.0, -45.0, 135.0, 1.0);
+        let point = Point2::new(0.3, 0.4);
+        let coarse = arc.get_field(&point).unwrap();
+        arc.resolution = 256;
+        let fine = arc.get_field(&point).unwrap();
+        assert!((fine - coarse).abs() < 1e-10 * fine.abs());
+    }
+
+    #[test]
+    fn symmetric_loop_has_no_net_force() {
+        let magnet = Rectangle::new(1.0, 1.0, (0.0, 0.0), 0.0, 1.0, 90.0);
+        let square = [
+            Point2::new(1.0, 1.0),
+            Point2::new(-1.0, 1.0),
+            Point2::new(-1.0, -1.0),
+            Point2::new(1.0, -1.0),
+        ];
+        let current = 2.0;
+        let forces = conductor_forces(&magnet, &square, current).unwrap();
+        let net = force_on_current_loop(&magnet, &square, current).unwrap();
+
+        // The conductors at (1, y) and (1, -y) are pushed in mirror image
+        // directions, pinching or stretching the loop along y
+        let size = forces[0].magnitude();
+        assert!(size > 1e-2);
+        for (upper, lower) in [(0, 3), (1, 2)].iter() {
+            let (upper, lower) = (forces[*upper], forces[*lower]);
+            assert!(upper.y.abs() > 1e-2);
+            assert!((upper.x - lower.x).abs() < 1e-12 * size);
+            assert!((upper.y + lower.y).abs() < 1e-12 * size);
+        }
+        assert!(net.magnitude() < 1e-12 * size);
+    }
+
+    #[test]
+    fn single_conductor_force() {
+        let magnet = Rectangle::new(1.0, 0.5, (0.0, 0.0), 10.0, 1.0, 30.0);
+        let point = Point2::new(1.5, 0.8);
+        let current = 5.0;
+        let b = magnet.get_field(&point).unwrap();
+        let force = force_on_current_loop(&magnet, &[point], current).unwrap();
+        assert!(nearly_equal(force.x, -current * b.y));
+        assert!(nearly_equal(force.y, current * b.x));
+        assert!(force_on_current_loop(&magnet, &[], current).is_err());
+    }
+}
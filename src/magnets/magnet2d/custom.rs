@@ -0,0 +1,182 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/.
+Copyright 2021 Peter Dunne */
+
+//! Magnets with arbitrary magnetisation patterns
+//!
+//! The magnetisation is sampled on a grid of cells covering the magnet, and
+//! the field approximated by the sum of the line dipoles of the cells.
+
+use crate::errors::MagnetError;
+use crate::magnets::magnet2d::dipole_field::get_field_line_dipole;
+use crate::magnets::magnet2d::{Magnet2D, Rectangle};
+use crate::magnets::{check_finite, check_size, Magnet};
+use crate::utils::points::Points;
+use crate::utils::points2::{Point2, Points2};
+use std::error::Error;
+use std::fmt;
+
+/// Default number of cells along each side of a custom magnet
+pub const CUSTOM_RESOLUTION: usize = 32;
+
+/// A magnet with a magnetisation set by a closure, e.g. a printed pattern
+///
+/// The closure maps a point in the global frame to the magnetisation, in T,
+/// there. The magnet occupies the rectangle `bounds`, whose own magnetisation
+/// is ignored; other shapes are obtained by returning zero from the closure
+/// outside them.
+///
+/// The bounds are divided into `resolution` x `resolution` cells,
+/// `CUSTOM_RESOLUTION` by default, each treated as a line dipole with the
+/// magnetisation at its centre. The field converges as the square of the
+/// cell size away from the magnet, but is not accurate within a few cell
+/// sizes of it. Inside, `get_field` adds the local magnetisation to the dipole
+/// sum.
+///
+/// # Example
+/// ```rust
+/// use magnet_rs::magnets::magnet2d::custom::CustomMagnet2D;
+/// use magnet_rs::magnets::magnet2d::{Magnet2D, Rectangle};
+/// use magnet_rs::utils::points2::Point2;
+/// // Magnetisation rotating along x, as in a Halbach array
+/// let magnet = CustomMagnet2D::new(Rectangle::new(4.0, 1.0, (0.0, 0.0), 0.0, 1.0, 0.0), |p| {
+///     Point2::new(p.x.cos(), p.x.sin())
+/// });
+/// let field = magnet.get_field(&Point2::new(0.0, 1.5)).unwrap();
+/// ```
+#[derive(Copy, Clone)]
+pub struct CustomMagnet2D<F: Fn(Point2) -> Point2> {
+    pub bounds: Rectangle,
+    pub magnetisation: F,
+    pub resolution: usize,
+}
+
+impl<F: Fn(Point2) -> Point2> CustomMagnet2D<F> {
+    pub fn new(bounds: Rectangle, magnetisation: F) -> CustomMagnet2D<F> {
+        CustomMagnet2D {
+            bounds,
+            magnetisation,
+            resolution: CUSTOM_RESOLUTION,
+        }
+    }
+
+    /// Returns the centres, in the global frame, and the area of the cells
+    fn cells(&self) -> (Vec<Point2>, f64) {
+        let n = self.resolution;
+        let cells = self.bounds.cells(n, n);
+        let centres = cells.iter().map(|cell| cell.center).collect();
+        (
+            centres,
+            self.bounds.width * self.bounds.height / (n * n) as f64,
+        )
+    }
+}
+
+/// Implements Display for custom magnets, showing the bounds.
+impl<F: Fn(Point2) -> Point2> fmt::Display for CustomMagnet2D<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[custom w: {},\th: {},\tc: {},\talpha:{}\tcells: {}]",
+            self.bounds.width,
+            self.bounds.height,
+            self.bounds.center,
+            self.bounds.alpha,
+            self.resolution.pow(2)
+        )
+    }
+}
+
+impl<F: Fn(Point2) -> Point2> Magnet for CustomMagnet2D<F> {}
+
+impl<F: Fn(Point2) -> Point2> Magnet2D<Point2> for CustomMagnet2D<F> {
+    fn get_field(&self, point: &Point2) -> Result<Point2, Box<dyn Error>> {
+        if self.resolution == 0 {
+            return Err("custom magnet resolution must be at least 1".into());
+        }
+
+        let (centres, area) = self.cells();
+        let mut field = Point2::zero();
+        for centre in centres {
+            let moment = (self.magnetisation)(centre).scale(area);
+            field += get_field_line_dipole(&moment, &(*point - centre));
+        }
+
+        // The dipoles give mu_0 H, inside the magnet B = mu_0 H + J
+        if self.contains(point) {
+            field += (self.magnetisation)(*point);
+        }
+        Ok(field)
+    }
+
+    fn get_center(&self) -> Point2 {
+        self.bounds.center
+    }
+
+    fn get_size(&self) -> Point2 {
+        Point2::new(self.bounds.width, self.bounds.height)
+    }
+
+    /// Returns the magnetisation at the centre of the magnet
+    fn get_j(self) -> Point2 {
+        (self.magnetisation)(self.bounds.center)
+    }
+
+    fn contains(&self, point: &Point2) -> bool {
+        self.bounds.contains(point)
+    }
+
+    /// Checks the bounds and resolution; the magnetisation function is only
+    /// known by evaluating it
+    fn validate(&self) -> Result<(), MagnetError> {
+        check_size("width", self.bounds.width)?;
+        check_size("height", self.bounds.height)?;
+        check_finite("center", &[self.bounds.center.x, self.bounds.center.y])?;
+        if self.resolution == 0 {
+            return Err(MagnetError::InvalidParameter(
+                "custom magnet resolution must be at least 1".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::magnets::magnet2d::custom::CustomMagnet2D;
+    use crate::magnets::magnet2d::{Magnet2D, Rectangle};
+    use crate::utils::points2::{Point2, Points2};
+
+    #[test]
+    fn constant_magnetisation_matches_rectangle() {
+        let rectangle = Rectangle::new(1.0, 0.5, (0.2, -0.1), 30.0, 1.0, 60.0);
+        let j = rectangle.magnetisation_vector();
+        let custom = CustomMagnet2D::new(rectangle, move |_| j);
+
+        for point in &[Point2::new(1.5, 0.8), Point2::new(-1.0, -1.2)] {
+            let expected = rectangle.get_field(point).unwrap();
+            let field = custom.get_field(point).unwrap();
+            assert!((field - expected).magnitude() < 1e-3 * expected.magnitude());
+        }
+    }
+
+    #[test]
+    fn zero_outside_shape_is_partial_magnet() {
+        // Magnetising only the left half of the bounds gives the left half magnet
+        let bounds = Rectangle::new(2.0, 1.0, (0.0, 0.0), 0.0, 1.0, 90.0);
+        let half = Rectangle::new(1.0, 1.0, (-0.5, 0.0), 0.0, 1.0, 90.0);
+        let custom = CustomMagnet2D::new(bounds, |p| {
+            if p.x < 0.0 {
+                Point2::new(0.0, 1.0)
+            } else {
+                Point2::zero()
+            }
+        });
+
+        let point = Point2::new(-0.5, 2.0);
+        let expected = half.get_field(&point).unwrap();
+        let field = custom.get_field(&point).unwrap();
+        assert!((field - expected).magnitude() < 1e-3 * expected.magnitude());
+    }
+}
@@ -0,0 +1,465 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/.
+Copyright 2021 Peter Dunne */
+
+//! Design of 2D magnet assemblies
+//!
+//! Routines building magnet arrays and choosing their parameters to meet a
+//! target, e.g. a uniform field in a region.
+
+use crate::errors::MagnetError;
+use crate::magnets::magnet2d::analysis::field_uniformity;
+use crate::magnets::magnet2d::collection::MagnetCollection2D;
+use crate::magnets::magnet2d::{Rectangle, Wedge};
+use crate::utils::optimize::golden_section;
+use crate::utils::points::Points;
+use crate::utils::points2::{Point2, Points2};
+use crate::PI;
+
+/// Number of spacings sampled to bracket the optimum before refining it
+const SPACING_SCAN_POINTS: usize = 32;
+
+/// Returns a line of `n` copies of `template` along x, with centres a
+/// distance `spacing` apart, centred on the template's center.
+pub fn linear_array(template: &Rectangle, n: usize, spacing: f64) -> MagnetCollection2D {
+    let mut array = MagnetCollection2D::new();
+    let offset = (n as f64 - 1.0) / 2.0;
+    for k in 0..n {
+        let mut magnet = *template;
+        magnet.center = template.center + Point2::new((k as f64 - offset) * spacing, 0.0);
+        array.push(magnet);
+    }
+    array
+}
+
+/// Returns a closed ring of `n` copies of `template` evenly spaced on a
+/// circle of `ring_radius` about the template's center.
+///
+/// Each magnet is rotated so that its local x axis, its width, runs along
+/// the ring, and keeps the magnitude of the template's magnetisation. With
+/// `radial_magnetisation` the magnetisation points away from the center of
+/// the ring, otherwise it runs anticlockwise along the ring, closing the flux
+/// path around it.
+pub fn magnet_ring(
+    template: &Rectangle,
+    n: usize,
+    ring_radius: f64,
+    radial_magnetisation: bool,
+) -> MagnetCollection2D {
+    let remanence = template.magnetisation_vector().magnitude();
+    let mut ring = MagnetCollection2D::new();
+    for k in 0..n {
+        let phi = 2.0 * PI * k as f64 / n as f64;
+        let radial = Point2::new(phi.cos(), phi.sin());
+        let direction = if radial_magnetisation {
+            radial
+        } else {
+            Point2::new(-phi.sin(), phi.cos())
+        };
+
+        let mut magnet = *template;
+        magnet.center = template.center + radial.scale(ring_radius);
+        magnet.alpha = phi * 180. / PI + 90.0;
+        ring.push(magnet.with_magnetisation(direction.scale(remanence)));
+    }
+    ring
+}
+
+/// Returns `2 * order` square magnets around a bore of `bore_radius`,
+/// magnetised alternately outward and inward with remanence `strength`, in
+/// T, so that their poles approximate a pure `2 * order`-pole field near the
+/// center.
+///
+/// `order = 1` gives a dipole with a uniform central field along x,
+/// `order = 2` a quadrupole, `order = 3` a sextupole and so on. Near the
+/// center the field magnitude grows as $`r^{\,order - 1}`$, and the
+/// alternating symmetry cancels all but the harmonics of order
+/// `(2k + 1) * order`. The magnets sit with their inner faces on the bore,
+/// with a side of `bore_radius * min(1, tan(90° / order))`.
+pub fn multipole(order: usize, bore_radius: f64, strength: f64) -> MagnetCollection2D {
+    let mut collection = MagnetCollection2D::new();
+    if order == 0 {
+        return collection;
+    }
+
+    let n = 2 * order;
+    let side = bore_radius * (PI / n as f64).tan().min(1.0);
+    for k in 0..n {
+        let phi = 2.0 * PI * k as f64 / n as f64;
+        let radial = Point2::new(phi.cos(), phi.sin());
+        let sign = if k % 2 == 0 { 1.0 } else { -1.0 };
+        let center = radial.scale(bore_radius + side / 2.0);
+        let magnet = Rectangle::new(side, side, center, phi * 180. / PI, 0.0, 0.0);
+        collection.push(magnet.with_magnetisation(radial.scale(sign * strength)));
+    }
+    collection
+}
+
+/// Returns a printed multi-pole ring of `n_poles` wedge segments between
+/// `inner_r` and `outer_r`, magnetised alternately outward and inward along
+/// their bisectors with `remanence`, in T, as in a magnetic encoder wheel.
+///
+/// Segment `k` spans the angles `k` to `k + 1` times `360° / n_poles`, and
+/// with a uniform magnetisation approximates the radial pattern of the print.
+/// The radial field at a fixed radius changes sign at each pole, so it runs
+/// through `n_poles / 2` periods, one per pole pair, around the ring. An odd
+/// `n_poles` leaves two like poles side by side at angle 0.
+pub fn printed_ring(
+    n_poles: usize,
+    inner_r: f64,
+    outer_r: f64,
+    remanence: f64,
+) -> MagnetCollection2D {
+    let mut ring = MagnetCollection2D::new();
+    let span = 360.0 / n_poles as f64;
+    for k in 0..n_poles {
+        let start = k as f64 * span;
+        let bisector = start + span / 2.0;
+        let theta = if k % 2 == 0 {
+            bisector
+        } else {
+            bisector + 180.0
+        };
+        ring.push(Wedge::new(
+            (0.0, 0.0),
+            inner_r,
+            outer_r,
+            start,
+            start + span,
+            remanence,
+            theta,
+        ));
+    }
+    ring
+}
+
+/// A manufacturing defect of one element of a [`defect_array`]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Defect {
+    /// The magnet is absent
+    Missing,
+    /// The magnet keeps this fraction of its remanence, 0 for fully
+    /// demagnetised
+    Demagnetised(f64),
+}
+
+/// Returns an `nx` x `ny` grid of copies of `template`, with centres `pitch`
+/// apart in x and y about the template's center, where the elements listed
+/// in `defects` are missing or partially demagnetised.
+///
+/// Element `(i, j)` has index `j * nx + i`. With `alternate` set, the
+/// magnetisation of elements with odd `i + j` is reversed, giving a
+/// checkerboard. Returns an error for an index outside the grid.
+pub fn defect_array(
+    template: &Rectangle,
+    nx: usize,
+    ny: usize,
+    pitch: f64,
+    alternate: bool,
+    defects: &[(usize, Defect)],
+) -> Result<MagnetCollection2D, MagnetError> {
+    if let Some((index, _)) = defects.iter().find(|(index, _)| *index >= nx * ny) {
+        return Err(MagnetError::InvalidParameter(format!(
+            "defect index {} is outside the {} x {} grid",
+            index, nx, ny
+        )));
+    }
+
+    let j = template.magnetisation_vector();
+    let offset = Point2::new(nx as f64 - 1.0, ny as f64 - 1.0).scale(pitch / 2.0);
+    let mut array = MagnetCollection2D::new();
+    for row in 0..ny {
+        for column in 0..nx {
+            let index = row * nx + column;
+            let sign = if alternate && (row + column) % 2 == 1 {
+                -1.0
+            } else {
+                1.0
+            };
+            let remanence = match defects.iter().rev().find(|(i, _)| *i == index) {
+                Some((_, Defect::Missing)) => continue,
+                Some((_, Defect::Demagnetised(fraction))) => *fraction,
+                None => 1.0,
+            };
+
+            let mut magnet = template.with_magnetisation(j.scale(sign * remanence));
+            magnet.center =
+                template.center + Point2::new(column as f64 * pitch, row as f64 * pitch) - offset;
+            array.push(magnet);
+        }
+    }
+
+    Ok(array)
+}
+
+/// Returns a Halbach array of length `length` and `thickness`, whose
+/// magnetisation of magnitude `remanence` rotates continuously with period
+/// `wavelength`, approximated by `n_segments` uniformly magnetised
+/// rectangles.
+///
+/// The array is centred on the origin along x, below the plane `y = 0`, with
+/// the magnetisation angle $`\theta = \pi/2 + 2\pi x/\lambda`$ so that the
+/// strong side faces +y. There an infinitely long ideal array has a field of
+/// constant magnitude
+/// $`B_r (1 - e^{-2\pi t/\lambda}) e^{-2\pi y/\lambda}`$; the discrete
+/// segments reduce it by $`\mathrm{sinc}(\pi/m)`$ for $`m`$ segments per
+/// wavelength.
+pub fn halbach_continuous(
+    length: f64,
+    wavelength: f64,
+    thickness: f64,
+    remanence: f64,
+    n_segments: usize,
+) -> MagnetCollection2D {
+    let mut array = MagnetCollection2D::new();
+    let width = length / n_segments as f64;
+    for k in 0..n_segments {
+        let x = -length / 2.0 + (k as f64 + 0.5) * width;
+        let theta = 90.0 + 360.0 * x / wavelength;
+        array.push(Rectangle::new(
+            width,
+            thickness,
+            (x, -thickness / 2.0),
+            0.0,
+            remanence,
+            theta,
+        ));
+    }
+    array
+}
+
+/// Returns the spacing of a [`linear_array`] of `n` copies of `template`
+/// that minimises the [`field_uniformity`] over `target_region`.
+///
+/// Spacings from touching magnets, the template width, up to four times the
+/// larger of the width and the x extent of the target region are first
+/// scanned coarsely, and the best one refined by golden section search
+/// between its neighbours.
+pub fn optimize_spacing(
+    template: &Rectangle,
+    n: usize,
+    target_region: &[Point2],
+) -> Result<f64, MagnetError> {
+    if n < 2 {
+        return Err(MagnetError::InvalidParameter(format!(
+            "at least 2 magnets are needed to have a spacing, got {}",
+            n
+        )));
+    }
+    if target_region.is_empty() {
+        return Err(MagnetError::InvalidParameter(
+            "the target region has no points".to_string(),
+        ));
+    }
+
+    let non_uniformity = |spacing: f64| -> Result<f64, MagnetError> {
+        let array = linear_array(template, n, spacing);
+        let field = target_region
+            .iter()
+            .map(|point| array.get_field(point))
+            .collect::<Result<Vec<Point2>, MagnetError>>()?;
+        Ok(field_uniformity(&field))
+    };
+
+    let (x_min, x_max) = target_region
+        .iter()
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), p| {
+            (lo.min(p.x), hi.max(p.x))
+        });
+    let lower = template.width;
+    let upper = 4.0 * template.width.max(x_max - x_min);
+    let step = (upper - lower) / (SPACING_SCAN_POINTS - 1) as f64;
+
+    let mut best = (0, f64::INFINITY);
+    for i in 0..SPACING_SCAN_POINTS {
+        let u = non_uniformity(lower + step * i as f64)?;
+        if u < best.1 {
+            best = (i, u);
+        }
+    }
+
+    let centre = lower + step * best.0 as f64;
+    let a = (centre - step).max(lower);
+    let b = (centre + step).min(upper);
+    // The closure has already succeeded over the whole range, so a failure
+    // here is reported as NAN rather than lost
+    let spacing = golden_section(
+        |spacing| non_uniformity(spacing).unwrap_or(f64::NAN),
+        a,
+        b,
+        1e-6 * template.width,
+    )?;
+
+    Ok(spacing)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::magnets::magnet2d::analysis::field_uniformity;
+    use crate::magnets::magnet2d::design::{
+        defect_array, halbach_continuous, linear_array, magnet_ring, multipole, optimize_spacing,
+        printed_ring, Defect,
+    };
+    use crate::magnets::magnet2d::Rectangle;
+    use crate::utils::points::Points;
+    use crate::utils::points2::{Point2, Points2};
+    use crate::PI;
+
+    fn uniformity_at(template: &Rectangle, spacing: f64, region: &[Point2]) -> f64 {
+        let array = linear_array(template, 3, spacing);
+        let field: Vec<Point2> = region.iter().map(|p| array.get_field(p).unwrap()).collect();
+        field_uniformity(&field)
+    }
+
+    #[test]
+    fn linear_array_is_centred() {
+        let template = Rectangle::new(1.0, 1.0, (0.5, 2.0), 0.0, 1.0, 90.0);
+        let array = linear_array(&template, 3, 1.5);
+        let centres: Vec<Point2> = array.iter().map(|m| m.get_center()).collect();
+        assert_eq!(
+            vec![
+                Point2::new(-1.0, 2.0),
+                Point2::new(0.5, 2.0),
+                Point2::new(2.0, 2.0)
+            ],
+            centres
+        );
+    }
+
+    #[test]
+    fn optimized_spacing_beats_bad_spacing() {
+        let template = Rectangle::new(1.0, 1.0, (0.0, 0.0), 0.0, 1.0, 90.0);
+        let region: Vec<Point2> = (0..21)
+            .map(|i| Point2::new(-2.0 + 0.2 * i as f64, 1.5))
+            .collect();
+
+        let spacing = optimize_spacing(&template, 3, &region).unwrap();
+        let optimum = uniformity_at(&template, spacing, &region);
+        for bad in &[1.0, 6.0] {
+            assert!(optimum < uniformity_at(&template, *bad, &region));
+        }
+        assert!(optimize_spacing(&template, 1, &region).is_err());
+    }
+
+    #[test]
+    fn defect_reduces_local_field() {
+        let template = Rectangle::new(0.8, 0.8, (0.0, 0.0), 0.0, 1.0, 90.0);
+        let point = Point2::new(0.0, 0.6);
+        let field = |defects: &[(usize, Defect)]| {
+            defect_array(&template, 5, 3, 1.0, false, defects)
+                .unwrap()
+                .get_field(&point)
+                .unwrap()
+                .magnitude()
+        };
+
+        // Element 7 is the centre of the grid, directly below the point
+        let perfect = field(&[]);
+        let weakened = field(&[(7, Defect::Demagnetised(0.5))]);
+        let missing = field(&[(7, Defect::Missing)]);
+        assert!(missing < weakened && weakened < perfect);
+
+        let array = defect_array(&template, 5, 3, 1.0, true, &[(0, Defect::Missing)]).unwrap();
+        assert_eq!(14, array.len());
+        assert!(defect_array(&template, 5, 3, 1.0, false, &[(15, Defect::Missing)]).is_err());
+    }
+
+    #[test]
+    fn halbach_approaches_ideal_amplitude() {
+        let (wavelength, thickness, remanence) = (1.0, 0.25, 1.2);
+        let height = 0.1;
+        let k = 2.0 * PI / wavelength;
+        let ideal = remanence * (1.0 - (-k * thickness).exp()) * (-k * height).exp();
+
+        let error = |segments_per_wavelength: usize| {
+            let array = halbach_continuous(
+                20.0 * wavelength,
+                wavelength,
+                thickness,
+                remanence,
+                20 * segments_per_wavelength,
+            );
+            let strong = array.get_field(&Point2::new(0.1, height)).unwrap();
+            let weak = array
+                .get_field(&Point2::new(0.1, -thickness - height))
+                .unwrap();
+            assert!(weak.magnitude() < 0.2 * strong.magnitude());
+            (strong.magnitude() - ideal).abs() / ideal
+        };
+
+        let coarse = error(4);
+        let fine = error(32);
+        assert!(fine < coarse && fine < 0.01);
+    }
+
+    #[test]
+    fn ring_orientation_and_central_field() {
+        let template = Rectangle::new(0.5, 0.3, (1.0, -1.0), 0.0, 1.0, 0.0);
+        let radial = magnet_ring(&template, 8, 1.0, true);
+        let tangential = magnet_ring(&template, 8, 1.0, false);
+        assert_eq!(8, radial.len());
+
+        // Just beyond the outer face of the magnet at 45 degrees, the pole of
+        // the radial ring pushes the field outwards, while the tangential
+        // magnet's field runs along the ring
+        let direction = Point2::new(1.0, 1.0).unit();
+        let point = template.center + direction.scale(1.2);
+        let b_radial = radial.get_field(&point).unwrap();
+        let b_tangential = tangential.get_field(&point).unwrap();
+        assert!(b_radial.dot(&direction) > 0.9 * b_radial.magnitude());
+        assert!(b_tangential.dot(&direction).abs() < 1e-9);
+
+        // With eight-fold symmetry the field vanishes at the center of both
+        for ring in &[radial, tangential] {
+            assert!(ring.get_field(&template.center).unwrap().magnitude() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn printed_ring_alternates_with_poles() {
+        for n_poles in &[4, 8, 12] {
+            let ring = printed_ring(*n_poles, 1.0, 1.5, 0.4);
+            assert_eq!(*n_poles, ring.len());
+
+            // Radial field just outside the ring, between the pole centres
+            let radial: Vec<f64> = (0..360)
+                .map(|k| {
+                    let phi = (k as f64 + 0.5) * PI / 180.;
+                    let direction = Point2::new(phi.cos(), phi.sin());
+                    ring.get_field(&direction.scale(1.7))
+                        .unwrap()
+                        .dot(&direction)
+                })
+                .collect();
+            let sign_changes = (0..360)
+                .filter(|k| radial[*k] * radial[(k + 1) % 360] < 0.0)
+                .count();
+            assert_eq!(*n_poles, sign_changes);
+
+            // Outward over the first pole, inward over the second
+            let pole = 180.0 / *n_poles as f64;
+            assert!(radial[pole as usize] > 0.0 && radial[3 * pole as usize] < 0.0);
+        }
+    }
+
+    #[test]
+    fn multipole_field_scales_with_order() {
+        for order in 1..=4 {
+            let magnets = multipole(order, 1.0, 1.2);
+            assert_eq!(2 * order, magnets.len());
+
+            let direction = Point2::new(0.6, 0.8);
+            let near = magnets.get_field(&direction.scale(0.01)).unwrap();
+            let far = magnets.get_field(&direction.scale(0.02)).unwrap();
+            let exponent = (far.magnitude() / near.magnitude()).log2();
+            assert!((exponent - (order - 1) as f64).abs() < 1e-2);
+        }
+
+        // The dipole field points from the inward towards the outward magnetised
+        // magnet
+        let dipole = multipole(1, 1.0, 1.0).get_field(&Point2::zero()).unwrap();
+        assert!(dipole.x > 0.0 && dipole.y.abs() < 1e-12);
+        assert!(multipole(0, 1.0, 1.0).is_empty());
+    }
+}
@@ -0,0 +1,40 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/.
+Copyright 2021 Peter Dunne */
+
+use crate::utils::points::Points;
+use crate::utils::points2::{Point2, Points2};
+use crate::I_2PI;
+
+/// Returns the field of a line dipole along z through the origin
+///
+/// The moment per unit length is given as magnetisation times area,
+/// $`\mathbf{m} = \mathbf{J} A`$ in T m^2, so that
+/// ```math
+/// \mathbf{B} = \frac{1}{2\pi} \frac{2 (\mathbf{m} \cdot \hat{\mathbf{r}}) \hat{\mathbf{r}} - \mathbf{m}}{r^2}
+/// ```
+pub fn get_field_line_dipole(moment: &Point2, point: &Point2) -> Point2 {
+    let r = point.magnitude();
+    let r_hat = point.scale(1.0 / r);
+    let m_dot_r = moment.dot(&r_hat);
+    (r_hat.scale(2.0 * m_dot_r) - *moment).scale(I_2PI / r.powi(2))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::magnets::magnet2d::dipole_field::get_field_line_dipole;
+    use crate::magnets::magnet2d::{Circle, Magnet2D};
+    use crate::utils::points::Points;
+    use crate::utils::points2::{Point2, Points2};
+    use crate::PI;
+
+    #[test]
+    fn matches_circle_outside() {
+        let magnet = Circle::new(0.5, (0.0, 0.0), 0.0, 1.0, 30.0);
+        let moment = Point2::new(magnet.jx, magnet.jy).scale(PI * 0.25);
+        let point = Point2::new(0.7, -1.1);
+        let field = magnet.get_field(&point).unwrap();
+        assert!((field - get_field_line_dipole(&moment, &point)).magnitude() < 1e-15);
+    }
+}
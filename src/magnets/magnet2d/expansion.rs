@@ -0,0 +1,196 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/.
+Copyright 2021 Peter Dunne */
+
+//! Reduced far-field models of magnet assemblies
+//!
+//! Outside all of its sources the conjugate field $`B_x - i B_y`$ of a 2D
+//! assembly is an analytic function of $`z = x + i y`$ that vanishes at
+//! infinity, so it has the exterior multipole expansion
+//! $`\sum_n c_n / (z - z_0)^{n + 1}`$, with the dipole at $`n = 1`$.
+
+use crate::errors::MagnetError;
+use crate::magnets::magnet2d::collection::MagnetCollection2D;
+use crate::utils::complex::{dft, Complex};
+use crate::utils::points::Points;
+use crate::utils::points2::{Point2, Points2};
+use crate::PI;
+
+/// Minimum number of field samples on the circle used to fit an expansion
+pub const EXPANSION_SAMPLES: usize = 64;
+
+/// An exterior multipole expansion of the field of a magnet assembly,
+/// returned by [`reduce_to_dipole_expansion`]
+///
+/// `coefficients[n]` is $`c_n`$ about `center`, with
+/// $`B_x - i B_y = \sum_n c_n / (z - z_0)^{n + 1}`$. The monopole $`c_0`$
+/// vanishes for magnets. The expansion converges outside the circle of
+/// radius `radius` about `center` that encloses every magnet.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DipoleExpansion {
+    pub center: Point2,
+    pub radius: f64,
+    pub coefficients: Vec<Complex>,
+}
+
+impl DipoleExpansion {
+    /// Returns the field at `point` from the truncated expansion
+    ///
+    /// The truncation error falls as $`(r_0 / r)^{N + 2}`$ for a point at a
+    /// distance `r` from the centre, with `r_0 = radius` and `N` the highest
+    /// order. Inside the enclosing circle the series does not converge and
+    /// the result is meaningless.
+    pub fn field(&self, point: Point2) -> Point2 {
+        let w = Complex::new(point.x - self.center.x, point.y - self.center.y);
+        let inverse = Complex::new(1.0, 0.0) / w;
+
+        let mut power = inverse;
+        let mut sum = Complex::new(0.0, 0.0);
+        for c in &self.coefficients {
+            sum += *c * power;
+            power = power * inverse;
+        }
+        Point2::new(sum.re, -sum.im)
+    }
+
+    /// Returns the dipole moment per unit length, in T m², of the assembly
+    pub fn dipole_moment(&self) -> Point2 {
+        // The line dipole m gives conj(B) = (m_x + i m_y) / (2 pi w^2)
+        self.coefficients
+            .get(1)
+            .map_or(Point2::zero(), |c| Point2::new(c.re, c.im).scale(2.0 * PI))
+    }
+}
+
+/// Returns the exterior multipole expansion, up to `order`, of the field of
+/// `collection` about the mean of the magnet centres.
+///
+/// The coefficients are the Fourier coefficients of the field sampled on a
+/// circle of twice the radius enclosing all magnets, so the aliasing of the
+/// neglected orders is of order $`2^{-N}`$ for `N` samples. Each far-field
+/// query then costs `order + 1` complex products instead of a field
+/// evaluation of every magnet. Background fields, e.g. a
+/// [`crate::magnets::magnet2d::UniformField2D`], do not decay and are not
+/// represented.
+pub fn reduce_to_dipole_expansion(
+    collection: &MagnetCollection2D,
+    order: usize,
+) -> Result<DipoleExpansion, MagnetError> {
+    if collection.is_empty() {
+        return Err(MagnetError::InvalidParameter(
+            "cannot expand the field of an empty collection".to_string(),
+        ));
+    }
+
+    let n_magnets = collection.len() as f64;
+    let center = collection
+        .iter()
+        .fold(Point2::zero(), |sum, magnet| sum + magnet.get_center())
+        .scale(1.0 / n_magnets);
+    // The full size bounds the distance from a magnet's centre to its edge,
+    // whether a shape reports its extent or its radius
+    let radius = collection
+        .iter()
+        .map(|magnet| (magnet.get_center() - center).magnitude() + magnet.get_size().magnitude())
+        .fold(0.0, f64::max);
+    if !(radius.is_finite() && radius > 0.0) {
+        return Err(MagnetError::InvalidParameter(format!(
+            "collection has no finite extent, got radius {}",
+            radius
+        )));
+    }
+
+    let n_samples = (2 * (order + 2)).next_power_of_two().max(EXPANSION_SAMPLES);
+    let sample_radius = 2.0 * radius;
+    let samples = (0..n_samples)
+        .map(|m| {
+            let phi = 2.0 * PI * m as f64 / n_samples as f64;
+            let point = center + Point2::new(phi.cos(), phi.sin()).scale(sample_radius);
+            let field = collection.get_field(&point)?;
+            Ok(Complex::new(field.x, -field.y))
+        })
+        .collect::<Result<Vec<_>, MagnetError>>()?;
+
+    // A term c_n / w^(n+1) on the circle is c_n R^-(n+1) e^(-i (n+1) phi),
+    // which the forward transform puts in bin N - (n+1)
+    let spectrum = dft(&samples);
+    let coefficients = (0..=order)
+        .map(|n| {
+            let bin = (n_samples - (n + 1) % n_samples) % n_samples;
+            spectrum[bin].scale(sample_radius.powi(n as i32 + 1) / n_samples as f64)
+        })
+        .collect();
+
+    Ok(DipoleExpansion {
+        center,
+        radius,
+        coefficients,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::magnets::magnet2d::collection::MagnetCollection2D;
+    use crate::magnets::magnet2d::expansion::reduce_to_dipole_expansion;
+    use crate::magnets::magnet2d::{Circle, Rectangle};
+    use crate::utils::points::Points;
+    use crate::utils::points2::{Point2, Points2};
+
+    fn assembly() -> MagnetCollection2D {
+        let mut collection = MagnetCollection2D::new();
+        collection.push(Rectangle::new(1.0, 0.5, (-1.0, 0.0), 20.0, 1.0, 90.0));
+        collection.push(Rectangle::new(0.5, 1.0, (1.0, 0.5), 0.0, 1.2, 0.0));
+        collection.push(Circle::new(0.4, (0.0, -1.0), 0.0, 0.8, 45.0));
+        collection
+    }
+
+    #[test]
+    fn expansion_matches_far_field() {
+        let collection = assembly();
+        let expansion = reduce_to_dipole_expansion(&collection, 8).unwrap();
+
+        for phi in &[0.3_f64, 1.9, 4.0] {
+            let point = expansion.center + Point2::new(phi.cos(), phi.sin()).scale(15.0);
+            let expected = collection.get_field(&point).unwrap();
+            let field = expansion.field(point);
+            assert!((field - expected).magnitude() < 1e-6 * expected.magnitude());
+        }
+    }
+
+    #[test]
+    fn error_falls_with_order() {
+        let collection = assembly();
+        let point = Point2::new(6.0, 5.0);
+        let expected = collection.get_field(&point).unwrap();
+        let errors: Vec<f64> = [1, 3, 6]
+            .iter()
+            .map(|order| {
+                let expansion = reduce_to_dipole_expansion(&collection, *order).unwrap();
+                (expansion.field(point) - expected).magnitude()
+            })
+            .collect();
+        assert!(errors[0] > errors[1] && errors[1] > errors[2]);
+    }
+
+    #[test]
+    fn dipole_moment_is_total_moment() {
+        let magnets = [
+            Rectangle::new(1.0, 0.5, (-1.0, 0.0), 20.0, 1.0, 90.0),
+            Rectangle::new(0.5, 1.0, (1.0, 0.5), 0.0, 1.2, 30.0),
+        ];
+        let mut collection = MagnetCollection2D::new();
+        let mut moment = Point2::zero();
+        for magnet in &magnets {
+            collection.push(*magnet);
+            moment += magnet
+                .magnetisation_vector()
+                .scale(magnet.width * magnet.height);
+        }
+
+        let expansion = reduce_to_dipole_expansion(&collection, 2).unwrap();
+        assert!((expansion.dipole_moment() - moment).magnitude() < 1e-12);
+        assert!(expansion.coefficients[0].norm() < 1e-12);
+        assert!(reduce_to_dipole_expansion(&MagnetCollection2D::new(), 2).is_err());
+    }
+}
@@ -0,0 +1,119 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/.
+Copyright 2021 Peter Dunne */
+
+//! 2D magnets of finite depth
+//!
+//! A 2D magnet is infinitely long along z. A real bar of depth `L` has the
+//! same field at its mid-section only far from its ends, and otherwise needs
+//! the field of the equivalent 3D prism.
+
+use crate::errors::MagnetError;
+use crate::magnets::magnet2d::{Magnet2D, Rectangle};
+use crate::magnets::magnet3d::{Magnet3D, Prism};
+use crate::utils::points2::{Point2, Points2};
+use crate::utils::points3::{Point3, Points3};
+
+/// Relative error of the 2D field below which [`auto_field`] uses it
+pub const AUTO_FIELD_TOLERANCE: f64 = 1e-3;
+
+/// Returns the prism equivalent to `magnet` extruded over `depth` along z,
+/// centred on the plane `z = 0`
+pub fn extruded_prism(magnet: &Rectangle, depth: f64) -> Prism {
+    Prism::new(
+        magnet.width,
+        magnet.height,
+        depth,
+        (magnet.center.x, magnet.center.y, 0.0),
+        magnet.jr,
+        90.0,
+        magnet.theta,
+    )
+    .rotate(Point3::k_hat(), magnet.alpha)
+}
+
+/// Returns the field at `point` of `magnet` extruded over `depth` along z,
+/// centred on the plane `z = 0`, using the 2D field where it is accurate
+/// and the 3D prism field otherwise.
+///
+/// The 2D field neglects the ends of the bar, an error that falls as the
+/// square of the ratio of the size of the problem to the distance to the
+/// nearest end. With `r` the in-plane distance from the centre of the magnet
+/// to the point plus the half diagonal of the cross-section, and `d` the
+/// distance along z to the nearer end face, the relative error is below
+/// $`(r/d)^2/2`$. The 2D field is used where this is below
+/// [`AUTO_FIELD_TOLERANCE`], i.e. for `d > 22 r`, so only for bars more than
+/// about 45 times longer than their cross-section, and never beyond an end.
+pub fn auto_field(magnet_2d: &Rectangle, depth: f64, point: Point3) -> Result<Point3, MagnetError> {
+    if !(depth.is_finite() && depth > 0.0) {
+        return Err(MagnetError::InvalidParameter(format!(
+            "depth must be positive, got {}",
+            depth
+        )));
+    }
+
+    let in_plane = Point2::new(point.x, point.y);
+    let half_diagonal = 0.5 * magnet_2d.width.hypot(magnet_2d.height);
+    let r = (in_plane - magnet_2d.center).magnitude() + half_diagonal;
+    let d = 0.5 * depth - point.z.abs();
+
+    if d > 0.0 && 0.5 * (r / d).powi(2) < AUTO_FIELD_TOLERANCE {
+        let field = magnet_2d.get_field(&in_plane)?;
+        Ok(Point3::new(field.x, field.y, 0.0))
+    } else {
+        Ok(extruded_prism(magnet_2d, depth).get_field(&point)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::magnets::magnet2d::extrusion::{auto_field, extruded_prism, AUTO_FIELD_TOLERANCE};
+    use crate::magnets::magnet2d::{Magnet2D, Rectangle};
+    use crate::magnets::magnet3d::Magnet3D;
+    use crate::utils::points2::{Point2, Points2};
+    use crate::utils::points3::{Point3, Points3};
+
+    #[test]
+    fn long_bar_uses_2d_field() {
+        let magnet = Rectangle::new(1.0, 0.5, (0.2, -0.1), 30.0, 1.0, 60.0);
+        let point = Point3::new(0.8, 1.0, 2.0);
+        let field = auto_field(&magnet, 500.0, point).unwrap();
+
+        let planar = magnet.get_field(&Point2::new(0.8, 1.0)).unwrap();
+        assert_eq!(Point3::new(planar.x, planar.y, 0.0), field);
+
+        let full = extruded_prism(&magnet, 500.0).get_field(&point).unwrap();
+        assert!((field - full).magnitude() < AUTO_FIELD_TOLERANCE * full.magnitude());
+    }
+
+    #[test]
+    fn cube_uses_3d_field() {
+        let magnet = Rectangle::new(1.0, 1.0, (0.0, 0.0), 0.0, 1.0, 90.0);
+        let point = Point3::new(0.3, 1.0, 0.2);
+        let field = auto_field(&magnet, 1.0, point).unwrap();
+        let full = extruded_prism(&magnet, 1.0).get_field(&point).unwrap();
+        assert_eq!(full, field);
+
+        // The 2D field is far from that of a cube
+        let planar = magnet.get_field(&Point2::new(0.3, 1.0)).unwrap();
+        let planar = Point3::new(planar.x, planar.y, 0.0);
+        assert!((planar - field).magnitude() > 0.1 * field.magnitude());
+        assert!(auto_field(&magnet, 0.0, point).is_err());
+    }
+
+    #[test]
+    fn extruded_prism_matches_rotated_rectangle() {
+        // Far along a long bar the prism field tends to the 2D field
+        let magnet = Rectangle::new(1.0, 0.5, (0.2, -0.1), 30.0, 1.0, 60.0);
+        let prism = extruded_prism(&magnet, 1e4);
+        for point in &[Point2::new(1.5, 0.8), Point2::new(0.3, 0.0)] {
+            let planar = magnet.get_field(point).unwrap();
+            let full = prism
+                .get_field(&Point3::new(point.x, point.y, 0.0))
+                .unwrap();
+            let full = Point2::new(full.x, full.y);
+            assert!((full - planar).magnitude() < 1e-6 * planar.magnitude());
+        }
+    }
+}
@@ -0,0 +1,454 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/.
+Copyright 2021 Peter Dunne */
+
+//! Fitting 2D magnet models to measured fields
+
+use crate::errors::MagnetError;
+use crate::magnets::magnet2d::collection::MagnetCollection2D;
+use crate::magnets::magnet2d::{Magnet2D, Rectangle};
+use crate::utils::linalg::least_squares;
+use crate::utils::points::Points;
+use crate::utils::points2::{Point2, Points2};
+
+/// Number of Kaczmarz sweeps over all measurements in
+/// `reconstruct_from_line_integrals`
+pub const ART_SWEEPS: usize = 200;
+
+/// Number of samples along each line in `reconstruct_from_line_integrals`
+pub const LINE_SAMPLES: usize = 64;
+
+/// Checks that measured points and fields pair up and are not empty
+fn check_measurements(
+    measured_points: &[Point2],
+    measured_field: &[Point2],
+) -> Result<(), MagnetError> {
+    if measured_points.len() != measured_field.len() {
+        return Err(MagnetError::InvalidParameter(format!(
+            "{} measurement points but {} field values",
+            measured_points.len(),
+            measured_field.len()
+        )));
+    }
+    if measured_points.is_empty() {
+        return Err(MagnetError::InvalidParameter(
+            "no measurements given".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Returns the root mean square residual, in T, between the field of `model`
+/// and `measured_field` at `measured_points`,
+///
+/// ```math
+/// R = \sqrt{\frac{1}{N} \sum_i |\mathbf{B}(\mathbf{r}_i) - \mathbf{B}_i|^2}
+/// ```
+///
+/// the objective function minimised when fitting model parameters.
+pub fn fit_residual(
+    model: &MagnetCollection2D,
+    measured_points: &[Point2],
+    measured_field: &[Point2],
+) -> Result<f64, MagnetError> {
+    check_measurements(measured_points, measured_field)?;
+
+    let mut sum_sq = 0.0;
+    for (point, measured) in measured_points.iter().zip(measured_field) {
+        sum_sq += (model.get_field(point)? - *measured).magnitude_squared();
+    }
+
+    Ok((sum_sq / measured_points.len() as f64).sqrt())
+}
+
+/// Returns the magnetisation vectors, in T and in the global frame, of the
+/// magnets of known `geometry` that best fit `measured_field` at
+/// `measured_points` in the least squares sense.
+///
+/// The field is linear in the magnetisations, so the fit is a single linear
+/// least squares solve for the $`2N`$ components, followed by up to
+/// `max_iter` rounds of iterative refinement on the residual to recover
+/// precision lost for poorly conditioned geometries. At least $`N`$
+/// measurement points are needed, and they must distinguish the magnets.
+pub fn fit_magnetisation(
+    geometry: &[Rectangle],
+    measured_points: &[Point2],
+    measured_field: &[Point2],
+    max_iter: usize,
+) -> Result<Vec<Point2>, MagnetError> {
+    check_measurements(measured_points, measured_field)?;
+    if geometry.is_empty() {
+        return Err(MagnetError::InvalidParameter(
+            "no magnets to fit".to_string(),
+        ));
+    }
+
+    // Basis fields of each magnet for unit magnetisation along global x and y,
+    // two rows per measurement point
+    let mut matrix = vec![Vec::with_capacity(2 * geometry.len()); 2 * measured_points.len()];
+    for magnet in geometry {
+        for direction in &[Point2::i_hat(), Point2::j_hat()] {
+            let basis = magnet.with_magnetisation(*direction);
+            for (i, point) in measured_points.iter().enumerate() {
+                let field = basis.get_field(point)?;
+                matrix[2 * i].push(field.x);
+                matrix[2 * i + 1].push(field.y);
+            }
+        }
+    }
+    let rhs: Vec<f64> = measured_field.iter().flat_map(|b| vec![b.x, b.y]).collect();
+
+    let singular = || {
+        MagnetError::Calculation("the measurements do not determine the magnetisations".to_string())
+    };
+    let mut solution = least_squares(&matrix, &rhs).ok_or_else(singular)?;
+    for _ in 0..max_iter {
+        let residual: Vec<f64> = matrix
+            .iter()
+            .zip(&rhs)
+            .map(|(row, b)| b - row.iter().zip(&solution).map(|(a, x)| a * x).sum::<f64>())
+            .collect();
+        let correction = least_squares(&matrix, &residual).ok_or_else(singular)?;
+        for (x, dx) in solution.iter_mut().zip(&correction) {
+            *x += dx;
+        }
+    }
+
+    Ok(solution
+        .chunks(2)
+        .map(|j| Point2::new(j[0], j[1]))
+        .collect())
+}
+
+/// Returns the shim coefficients of `field` at `points`, the normal and skew
+/// multipole terms up to `max_order` about `center`, fitted in the least
+/// squares sense.
+///
+/// A field free of sources in the region is analytic in
+/// $`z = x + iy`$, and is expanded as
+/// ```math
+/// B_y + i B_x = \sum_{n=0}^{N} (b_n + i a_n) (z - z_0)^n
+/// ```
+/// with the uniform field at order 0, the linear gradients at order 1, and
+/// so on. The coefficients are returned in T/mⁿ as
+/// `[b_0, a_0, b_1, a_1, ..., b_N, a_N]`, so a pure gradient
+/// $`(B_x, B_y) = (G y, G x)`$ gives only $`b_1 = G`$. The coordinates are
+/// scaled by the largest distance from `center` for the fit, which needs at
+/// least `max_order + 1` points spread around it.
+pub fn shim_coefficients_2d(
+    field: &[Point2],
+    points: &[Point2],
+    center: Point2,
+    max_order: usize,
+) -> Result<Vec<f64>, MagnetError> {
+    check_measurements(points, field)?;
+    let radius = points
+        .iter()
+        .fold(0.0_f64, |max, point| max.max((*point - center).magnitude()));
+    if radius == 0.0 {
+        return Err(MagnetError::InvalidParameter(
+            "points must not all lie at the center".to_string(),
+        ));
+    }
+
+    // Two rows per point, for B_x and B_y, and two columns, b_n and a_n, per
+    // order, with (p + iq) the powers of the scaled coordinate
+    let mut matrix = Vec::with_capacity(2 * points.len());
+    let mut rhs = Vec::with_capacity(2 * points.len());
+    for (point, b) in points.iter().zip(field) {
+        let w = (*point - center).scale(1.0 / radius);
+        let (mut p, mut q) = (1.0, 0.0);
+        let mut row_x = Vec::with_capacity(2 * max_order + 2);
+        let mut row_y = Vec::with_capacity(2 * max_order + 2);
+        for _ in 0..=max_order {
+            row_x.extend_from_slice(&[q, p]);
+            row_y.extend_from_slice(&[p, -q]);
+            let next = p * w.x - q * w.y;
+            q = p * w.y + q * w.x;
+            p = next;
+        }
+        matrix.push(row_x);
+        matrix.push(row_y);
+        rhs.push(b.x);
+        rhs.push(b.y);
+    }
+
+    let scaled = least_squares(&matrix, &rhs).ok_or_else(|| {
+        MagnetError::Calculation("the points do not determine the shim terms".to_string())
+    })?;
+
+    Ok(scaled
+        .iter()
+        .enumerate()
+        .map(|(k, c)| c / radius.powi((k / 2) as i32))
+        .collect())
+}
+
+/// Returns the field at the `grid` points reconstructed from measured line
+/// integrals $`\int_{\mathbf{a}}^{\mathbf{b}} \mathbf{B} \cdot d\mathbf{l}`$,
+/// given as `(a, b, integral)` triples, by the algebraic reconstruction
+/// technique (ART).
+///
+/// The field is taken as constant around each grid point, so each line,
+/// sampled at `LINE_SAMPLES` midpoints, gives one linear equation in the
+/// field of the grid points nearest to it. The equations are solved by
+/// `ART_SWEEPS` sweeps of Kaczmarz's method starting from zero, which
+/// converges to the smallest field consistent with the measurements. The
+/// lines should lie within the region covered by the grid and cross it in
+/// many directions. Grid points crossed by no line are left at zero.
+pub fn reconstruct_from_line_integrals(
+    integrals: &[(Point2, Point2, f64)],
+    grid: &[Point2],
+) -> Result<Vec<Point2>, MagnetError> {
+    if integrals.is_empty() || grid.is_empty() {
+        return Err(MagnetError::InvalidParameter(
+            "at least one line integral and grid point are needed".to_string(),
+        ));
+    }
+
+    let nearest = |point: Point2| {
+        grid.iter()
+            .enumerate()
+            .fold((0, f64::INFINITY), |best, (i, node)| {
+                let distance = (*node - point).magnitude_squared();
+                if distance < best.1 {
+                    (i, distance)
+                } else {
+                    best
+                }
+            })
+            .0
+    };
+
+    // Sparse rows: the weight of the field at each node is the length of
+    // line nearest to it, along the line
+    let rows: Vec<Vec<(usize, Point2)>> = integrals
+        .iter()
+        .map(|(start, end, _)| {
+            let step = (*end - *start).scale(1.0 / LINE_SAMPLES as f64);
+            let mut row: Vec<(usize, Point2)> = Vec::new();
+            for k in 0..LINE_SAMPLES {
+                let node = nearest(*start + step.scale(k as f64 + 0.5));
+                match row.iter_mut().find(|(i, _)| *i == node) {
+                    Some((_, weight)) => *weight += step,
+                    None => row.push((node, step)),
+                }
+            }
+            row
+        })
+        .collect();
+
+    let mut field = vec![Point2::zero(); grid.len()];
+    for _ in 0..ART_SWEEPS {
+        for (row, (_, _, integral)) in rows.iter().zip(integrals) {
+            let norm: f64 = row.iter().map(|(_, w)| w.magnitude_squared()).sum();
+            if norm == 0.0 {
+                continue;
+            }
+            let predicted: f64 = row.iter().map(|(i, w)| w.dot(&field[*i])).sum();
+            let correction = (integral - predicted) / norm;
+            for (i, w) in row {
+                field[*i] += w.scale(correction);
+            }
+        }
+    }
+
+    Ok(field)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::magnets::magnet2d::collection::MagnetCollection2D;
+    use crate::magnets::magnet2d::fitting::{
+        fit_magnetisation, fit_residual, reconstruct_from_line_integrals, shim_coefficients_2d,
+    };
+    use crate::magnets::magnet2d::{Magnet2D, Rectangle};
+    use crate::utils::comparison::nearly_equal;
+    use crate::utils::points::Points;
+    use crate::utils::points2::{Point2, Points2};
+    use crate::utils::quadrature::integrate;
+
+    fn model() -> MagnetCollection2D {
+        let mut model = MagnetCollection2D::new();
+        model.push(Rectangle::new(1.0, 0.5, (-1.0, 0.0), 0.0, 1.0, 90.0));
+        model.push(Rectangle::new(1.0, 0.5, (1.0, 0.0), 0.0, 1.0, -90.0));
+        model
+    }
+
+    #[test]
+    fn model_matches_own_output() {
+        let model = model();
+        let points: Vec<Point2> = (0..10)
+            .map(|i| Point2::new(-2.0 + 0.45 * i as f64, 0.8))
+            .collect();
+        let field: Vec<Point2> = points.iter().map(|p| model.get_field(p).unwrap()).collect();
+        assert_eq!(0.0, fit_residual(&model, &points, &field).unwrap());
+    }
+
+    #[test]
+    fn residual_of_constant_offset() {
+        let model = model();
+        let points = vec![Point2::new(0.0, 1.0), Point2::new(0.5, 2.0)];
+        let field: Vec<Point2> = points
+            .iter()
+            .map(|p| model.get_field(p).unwrap() + Point2::new(0.03, -0.04))
+            .collect();
+        assert!(nearly_equal(
+            fit_residual(&model, &points, &field).unwrap(),
+            0.05
+        ));
+        assert!(fit_residual(&model, &points, &field[..1]).is_err());
+    }
+
+    #[test]
+    fn fit_recovers_synthetic_magnetisation() {
+        let truth = [
+            Rectangle::new(1.0, 0.5, (-1.0, 0.0), 20.0, 1.2, 75.0),
+            Rectangle::new(0.5, 0.5, (1.0, 0.5), -40.0, 0.9, 160.0),
+            Rectangle::new(2.0, 0.2, (0.0, -1.5), 0.0, 1.0, 0.0),
+        ];
+        let points: Vec<Point2> = (0..24)
+            .map(|i| Point2::new(3.0, 0.0).rotate(i as f64 * 0.26))
+            .collect();
+        let field: Vec<Point2> = points
+            .iter()
+            .map(|p| {
+                truth
+                    .iter()
+                    .map(|m| m.get_field(p).unwrap())
+                    .fold(Point2::zero(), |a, b| a + b)
+            })
+            .collect();
+
+        // The fit only uses the geometry, the magnetisations are discarded
+        let geometry: Vec<Rectangle> = truth
+            .iter()
+            .map(|m| m.with_magnetisation(Point2::new(0.0, 1.0)))
+            .collect();
+        let fitted = fit_magnetisation(&geometry, &points, &field, 2).unwrap();
+
+        for (magnet, j) in truth.iter().zip(&fitted) {
+            assert!((magnet.magnetisation_vector() - *j).magnitude() < 1e-10);
+        }
+        assert!(fit_magnetisation(&geometry, &points[..1], &field[..1], 0).is_err());
+    }
+
+    #[test]
+    fn line_integrals_recover_smooth_field() {
+        let magnet = Rectangle::new(1.0, 1.0, (0.0, 0.0), 0.0, 1.0, 60.0);
+        let n = 5;
+        let (x_0, y_0, size) = (2.5, 1.0, 2.0);
+        let spacing = size / (n - 1) as f64;
+        let grid: Vec<Point2> = (0..n * n)
+            .map(|k| {
+                Point2::new(
+                    x_0 + (k % n) as f64 * spacing,
+                    y_0 + (k / n) as f64 * spacing,
+                )
+            })
+            .collect();
+
+        // Chords between points spread around the square enclosing the grid
+        let margin = spacing / 2.0;
+        let perimeter = |t: f64| {
+            let (lo, span) = (-margin, size + 2.0 * margin);
+            let u = 4.0 * t.fract();
+            let s = u.fract() * span;
+            let local = match u as usize {
+                0 => Point2::new(lo + s, lo),
+                1 => Point2::new(lo + span, lo + s),
+                2 => Point2::new(lo + span - s, lo + span),
+                _ => Point2::new(lo, lo + span - s),
+            };
+            local + Point2::new(x_0, y_0)
+        };
+        let integrals: Vec<(Point2, Point2, f64)> = (0..40)
+            .flat_map(|i| (i + 5..i + 36).step_by(3).map(move |j| (i, j)))
+            .map(|(i, j)| {
+                let (a, b) = (perimeter(i as f64 / 40.0), perimeter(j as f64 / 40.0));
+                let tangent = b - a;
+                let integral = integrate(
+                    |t| {
+                        magnet
+                            .get_field(&(a + tangent.scale(t)))
+                            .unwrap()
+                            .dot(&tangent)
+                    },
+                    0.0,
+                    1.0,
+                    32,
+                );
+                (a, b, integral)
+            })
+            .collect();
+
+        let field = reconstruct_from_line_integrals(&integrals, &grid).unwrap();
+        let mut error = 0.0;
+        let mut norm = 0.0;
+        for (point, b) in grid.iter().zip(&field) {
+            let exact = magnet.get_field(point).unwrap();
+            error += (*b - exact).magnitude_squared();
+            norm += exact.magnitude_squared();
+        }
+        assert!((error / norm).sqrt() < 0.2);
+        assert!(reconstruct_from_line_integrals(&[], &grid).is_err());
+    }
+
+    #[test]
+    fn linear_gradient_gives_first_order_term() {
+        let center = Point2::new(0.3, -0.1);
+        let gradient = 2.5;
+        let points: Vec<Point2> = (0..40)
+            .map(|k| {
+                let angle = 0.7 * k as f64;
+                center + Point2::new(angle.cos(), angle.sin()).scale(0.01 * (1 + k % 5) as f64)
+            })
+            .collect();
+        let field: Vec<Point2> = points
+            .iter()
+            .map(|p| {
+                let d = *p - center;
+                Point2::new(gradient * d.y, gradient * d.x)
+            })
+            .collect();
+
+        let coefficients = shim_coefficients_2d(&field, &points, center, 3).unwrap();
+        assert_eq!(8, coefficients.len());
+        assert!((coefficients[2] - gradient).abs() < 1e-10);
+        for (k, c) in coefficients.iter().enumerate() {
+            if k != 2 {
+                assert!(c.abs() < 1e-9, "coefficient {} is {}", k, c);
+            }
+        }
+    }
+
+    #[test]
+    fn shim_terms_of_magnet_field() {
+        // The field of a magnet is reproduced from its fitted terms
+        let magnet = Rectangle::new(1.0, 1.0, (0.0, -1.0), 0.0, 1.0, 90.0);
+        let points: Vec<Point2> = (0..60)
+            .map(|k| Point2::new(-0.1 + 0.2 * (k % 6) as f64 / 5.0, 0.02 * (k / 6) as f64))
+            .collect();
+        let field: Vec<Point2> = points
+            .iter()
+            .map(|p| magnet.get_field(p).unwrap())
+            .collect();
+        let c = shim_coefficients_2d(&field, &points, Point2::zero(), 6).unwrap();
+
+        let point = Point2::new(0.05, 0.1);
+        let mut expansion = Point2::zero();
+        let (mut p, mut q) = (1.0, 0.0);
+        for n in 0..=6 {
+            expansion += Point2::new(
+                c[2 * n + 1] * p + c[2 * n] * q,
+                c[2 * n] * p - c[2 * n + 1] * q,
+            );
+            let next = p * point.x - q * point.y;
+            q = p * point.y + q * point.x;
+            p = next;
+        }
+        assert!((expansion - magnet.get_field(&point).unwrap()).magnitude() < 1e-6);
+        assert!(shim_coefficients_2d(&field[..3], &points[..3], Point2::zero(), 6).is_err());
+    }
+}
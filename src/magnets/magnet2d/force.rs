@@ -0,0 +1,444 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/.
+Copyright 2021 Peter Dunne */
+
+//! Forces and Energies of 2D magnets
+//!
+//! Interactions of a rigid magnet with the field of other magnets, per unit
+//! length of the (infinitely long) magnets. Integrals over the cross-section
+//! of a magnet use the midpoint rule on a grid of `QUADRATURE_ORDER` x
+//! `QUADRATURE_ORDER` cells.
+
+use crate::errors::MagnetError;
+use crate::magnets::magnet2d::collection::MagnetCollection2D;
+use crate::magnets::magnet2d::{Magnet2D, Rectangle};
+use crate::utils::points::Points;
+use crate::utils::points2::{Point2, Points2};
+use crate::utils::quadrature::gauss_legendre;
+use crate::{MU0, PI};
+
+/// Number of cells along each side of a magnet used for area integrals
+pub const QUADRATURE_ORDER: usize = 16;
+
+/// Number of Gauss-Legendre nodes on each interval of line integrals
+pub const LINE_QUADRATURE_ORDER: usize = 48;
+
+/// Rotation step, in radians, of the finite difference angular stiffness in
+/// [`rocking_frequency`]
+const ROCKING_STEP: f64 = 1e-3;
+
+/// Returns the midpoints, in the global frame, and the area of the cells
+/// dividing `magnet` into `n` x `n` cells
+fn rectangle_cells(magnet: &Rectangle, n: usize) -> (Vec<Point2>, f64) {
+    let cells = magnet.cells(n, n);
+    let area = magnet.width * magnet.height / (n * n) as f64;
+    (cells.iter().map(|cell| cell.center).collect(), area)
+}
+
+/// Returns the interaction energy, in J/m, of `magnet` in the field of
+/// `fixed`,
+///
+/// ```math
+/// U = -\frac{1}{\mu_0} \int \mathbf{J} \cdot \mathbf{B}_{ext} \, dA
+/// ```
+pub fn interaction_energy(
+    fixed: &MagnetCollection2D,
+    magnet: &Rectangle,
+) -> Result<f64, MagnetError> {
+    let j = magnet.magnetisation_vector();
+    let (points, area) = rectangle_cells(magnet, QUADRATURE_ORDER);
+
+    let mut flux = 0.0;
+    for point in &points {
+        flux += j.dot(&fixed.get_field(point)?);
+    }
+    Ok(-flux * area / MU0)
+}
+
+/// Returns the interaction energy, in J/m, of `movable` with the field of
+/// `fixed` when the center of `movable` is moved to each of `positions`.
+///
+/// Minima of the energy are stable equilibria and maxima unstable ones.
+pub fn energy_landscape(
+    fixed: &MagnetCollection2D,
+    movable: &Rectangle,
+    positions: &[Point2],
+) -> Result<Vec<f64>, MagnetError> {
+    positions
+        .iter()
+        .map(|position| {
+            let mut magnet = *movable;
+            magnet.center = *position;
+            interaction_energy(fixed, &magnet)
+        })
+        .collect()
+}
+
+/// Returns the stiffness, in N/m², of the magnetic spring formed by
+/// `movable` in the field of `fixed` for displacements of `movable` along
+/// `displacement_axis`, about its current position.
+///
+/// The stiffness is the spring constant $`k = -\partial F/\partial s`$, with
+/// $`F`$ the force along the unit axis $`\hat{\mathbf{s}}`$, obtained as the
+/// second derivative of the [`interaction_energy`] by central differences with
+/// step `h`. A positive value is a restoring force, a negative one an
+/// unstable equilibrium.
+pub fn stiffness(
+    fixed: &Rectangle,
+    movable: &Rectangle,
+    displacement_axis: Point2,
+    h: f64,
+) -> Result<f64, MagnetError> {
+    if !(h.is_finite() && h > 0.0) {
+        return Err(MagnetError::InvalidParameter(format!(
+            "finite difference step must be positive, got {}",
+            h
+        )));
+    }
+    if displacement_axis.magnitude() == 0.0 {
+        return Err(MagnetError::InvalidParameter(
+            "displacement axis must be non-zero".to_string(),
+        ));
+    }
+
+    let mut source = MagnetCollection2D::new();
+    source.push(*fixed);
+    let step = displacement_axis.unit().scale(h);
+    let positions = [movable.center - step, movable.center, movable.center + step];
+    let energy = energy_landscape(&source, movable, &positions)?;
+
+    Ok((energy[0] - 2.0 * energy[1] + energy[2]) / h.powi(2))
+}
+
+/// Returns the frequency, in Hz, of small rocking oscillations of `movable`
+/// about its center in the field of `fixed`, for a magnet of `density` in
+/// kg/m³.
+///
+/// The angular stiffness $`\kappa = \partial^2 U/\partial \alpha^2`$ of
+/// the [`interaction_energy`] is found by central differences, rotating the
+/// magnet together with its magnetisation, and with the moment of inertia
+/// per unit length $`I`$ from [`Rectangle::mass_and_inertia`] the frequency is
+/// $`f = \sqrt{\kappa/I}/2\pi`$. Returns an error if the orientation is
+/// not a stable equilibrium, $`\kappa \le 0`$.
+pub fn rocking_frequency(
+    fixed: &MagnetCollection2D,
+    movable: &Rectangle,
+    density: f64,
+) -> Result<f64, MagnetError> {
+    if !(density.is_finite() && density > 0.0) {
+        return Err(MagnetError::InvalidParameter(format!(
+            "density must be positive, got {}",
+            density
+        )));
+    }
+
+    let mut energy = [0.0; 3];
+    for (k, sign) in [-1.0, 0.0, 1.0].iter().enumerate() {
+        let mut magnet = *movable;
+        magnet.alpha += sign * ROCKING_STEP * 180. / PI;
+        energy[k] = interaction_energy(fixed, &magnet)?;
+    }
+    let angular_stiffness = (energy[0] - 2.0 * energy[1] + energy[2]) / ROCKING_STEP.powi(2);
+    if angular_stiffness.is_nan() || angular_stiffness <= 0.0 {
+        return Err(MagnetError::Calculation(format!(
+            "orientation is not stable, angular stiffness {} J/m",
+            angular_stiffness
+        )));
+    }
+
+    let inertia = movable.mass_and_inertia(density).1;
+    Ok((angular_stiffness / inertia).sqrt() / (2.0 * PI))
+}
+
+/// Returns the corners, in the global frame, of `magnet`
+fn rectangle_corners(magnet: &Rectangle) -> Vec<Point2> {
+    let alpha = magnet.alpha * PI / 180.;
+    [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)]
+        .iter()
+        .map(|(u, v)| magnet.center + Point2::new(u * magnet.a, v * magnet.b).rotate(alpha))
+        .collect()
+}
+
+/// Returns the holding force, in N/m, pulling `magnet` towards a flat steel
+/// plate whose surface is the line `y = plate_y`, filling the half plane on
+/// the far side from the magnet.
+///
+/// The steel is treated as ideal iron, infinitely permeable and unsaturated,
+/// so its response is that of the mirror image of the magnet in the surface,
+/// with the tangential magnetisation reversed. By symmetry the field on the
+/// surface is normal to it, and the Maxwell stress integrated along the
+/// surface gives the attraction
+///
+/// ```math
+/// F = \frac{1}{2 \mu_0} \int_{-\infty}^{\infty} B_y^2 \, dx
+/// ```
+///
+/// For a magnet touching the plate the force is largest, tending to
+/// $`J^2 w / (2 \mu_0)`$ for a long magnet of width $`w`$ magnetised normal
+/// to the plate. Real plates saturate and have a rough surface, so this is an
+/// upper bound. Returns an error if the magnet crosses the surface.
+pub fn pull_force_to_plate(magnet: &Rectangle, plate_y: f64) -> Result<f64, MagnetError> {
+    let corners = rectangle_corners(magnet);
+    let side = (magnet.center.y - plate_y).signum();
+    let size = magnet.width.max(magnet.height);
+    if side == 0.0
+        || corners
+            .iter()
+            .any(|c| side * (c.y - plate_y) < -1e-12 * size)
+    {
+        return Err(MagnetError::InvalidParameter(format!(
+            "magnet crosses the plate surface at y = {}",
+            plate_y
+        )));
+    }
+
+    let mirrored = Point2::new(magnet.center.x, 2.0 * plate_y - magnet.center.y);
+    let j = magnet.magnetisation_vector();
+    let image = Rectangle::new(
+        magnet.width,
+        magnet.height,
+        mirrored,
+        -magnet.alpha,
+        0.0,
+        0.0,
+    )
+    .with_magnetisation(Point2::new(-j.x, j.y));
+
+    // B_y is continuous across the surface, so it is sampled just on the
+    // magnet's side, clear of the image's face when touching
+    let y = plate_y + side * 1e-9 * size;
+    let stress = |x: f64| -> Result<f64, MagnetError> {
+        let point = Point2::new(x, y);
+        let b_y = magnet.get_field(&point)?.y + image.get_field(&point)?.y;
+        Ok(b_y.powi(2) / (2.0 * MU0))
+    };
+
+    // Split at the corners, where the field changes abruptly, and map the
+    // tails to finite intervals with x = x_k -/+ w tan(t)
+    let mut breaks: Vec<f64> = corners.iter().map(|c| c.x).collect();
+    breaks.sort_by(|a, b| a.total_cmp(b));
+    let (nodes, weights) = gauss_legendre(LINE_QUADRATURE_ORDER);
+    let mut force = 0.0;
+
+    for pair in breaks.windows(2) {
+        let (mid, half) = ((pair[0] + pair[1]) / 2.0, (pair[1] - pair[0]) / 2.0);
+        for (t, w) in nodes.iter().zip(&weights) {
+            force += w * half * stress(mid + half * t)?;
+        }
+    }
+    for (edge, direction) in &[(breaks[0], -1.0), (breaks[3], 1.0)] {
+        for (t, w) in nodes.iter().zip(&weights) {
+            let angle = PI / 4.0 * (t + 1.0);
+            let dx = size / angle.cos().powi(2);
+            force += w * PI / 4.0 * dx * stress(edge + direction * size * angle.tan())?;
+        }
+    }
+
+    Ok(force)
+}
+
+/// Relative tolerance, as a fraction of the magnet size, of the gap found by
+/// [`pull_off_gap`]
+pub const PULL_OFF_GAP_TOLERANCE: f64 = 1e-9;
+
+/// Returns the gap, in m, between `magnet` and a steel plate at `y = plate_y`
+/// at which the [`pull_force_to_plate`] falls to `force_threshold`, in N/m.
+///
+/// The magnet is moved normal to the plate, keeping its x position and the
+/// side it is on, and the gap is measured from its nearest corner. The force
+/// falls monotonically with the gap, so the gap is found by bisection once
+/// the upper end of the bracket is grown to a gap with a smaller force.
+/// Returns an error if the threshold is not positive or is above the contact
+/// force, when the latch never holds.
+pub fn pull_off_gap(
+    magnet: &Rectangle,
+    plate_y: f64,
+    force_threshold: f64,
+) -> Result<f64, MagnetError> {
+    if force_threshold.is_nan() || force_threshold <= 0.0 {
+        return Err(MagnetError::InvalidParameter(format!(
+            "force threshold must be positive, got {}",
+            force_threshold
+        )));
+    }
+    let side = (magnet.center.y - plate_y).signum();
+    if side == 0.0 {
+        return Err(MagnetError::InvalidParameter(format!(
+            "magnet is centred on the plate surface at y = {}",
+            plate_y
+        )));
+    }
+
+    let nearest = rectangle_corners(magnet)
+        .iter()
+        .map(|c| side * (c.y - plate_y))
+        .fold(f64::INFINITY, f64::min);
+    let force_at_gap = |gap: f64| -> Result<f64, MagnetError> {
+        let mut moved = *magnet;
+        moved.center.y += side * (gap - nearest);
+        pull_force_to_plate(&moved, plate_y)
+    };
+
+    let contact = force_at_gap(0.0)?;
+    if force_threshold > contact {
+        return Err(MagnetError::Calculation(format!(
+            "force threshold {} N/m is above the contact force {} N/m",
+            force_threshold, contact
+        )));
+    }
+
+    let size = magnet.width.max(magnet.height);
+    let (mut lo, mut hi) = (0.0, size);
+    while force_at_gap(hi)? > force_threshold {
+        lo = hi;
+        hi *= 2.0;
+        if !hi.is_finite() {
+            return Err(MagnetError::Calculation(
+                "pull force does not fall to the threshold".to_string(),
+            ));
+        }
+    }
+
+    while hi - lo > PULL_OFF_GAP_TOLERANCE * size {
+        let mid = 0.5 * (lo + hi);
+        if force_at_gap(mid)? > force_threshold {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+    Ok(0.5 * (lo + hi))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::magnets::magnet2d::collection::MagnetCollection2D;
+    use crate::magnets::magnet2d::force::{
+        energy_landscape, pull_force_to_plate, pull_off_gap, rocking_frequency, stiffness,
+    };
+    use crate::magnets::magnet2d::Rectangle;
+    use crate::utils::points2::Point2;
+    use crate::MU0;
+
+    #[test]
+    fn energy_minimum_when_aligned() {
+        let mut fixed = MagnetCollection2D::new();
+        fixed.push(Rectangle::new(1.0, 1.0, (0.0, 0.0), 0.0, 1.0, 90.0));
+        let movable = Rectangle::new(1.0, 1.0, (0.0, 0.0), 0.0, 1.0, 90.0);
+
+        // Sliding along x above the fixed magnet, attracting when stacked
+        let positions: Vec<Point2> = (0..21)
+            .map(|i| Point2::new(-2.0 + 0.2 * i as f64, 1.2))
+            .collect();
+        let energy = energy_landscape(&fixed, &movable, &positions).unwrap();
+
+        let (i_min, _) = energy
+            .iter()
+            .enumerate()
+            .fold(
+                (0, f64::INFINITY),
+                |acc, (i, e)| if *e < acc.1 { (i, *e) } else { acc },
+            );
+        assert_eq!(10, i_min);
+        assert!(energy[10] < 0.0);
+        assert!((energy[5] - energy[15]).abs() < 1e-9 * energy[10].abs());
+    }
+
+    #[test]
+    fn repulsion_when_antiparallel() {
+        let mut fixed = MagnetCollection2D::new();
+        fixed.push(Rectangle::new(1.0, 1.0, (0.0, 0.0), 0.0, 1.0, 90.0));
+        let movable = Rectangle::new(1.0, 1.0, (0.0, 0.0), 0.0, 1.0, 270.0);
+
+        let positions = vec![Point2::new(0.0, 1.2), Point2::new(0.0, 2.0)];
+        let energy = energy_landscape(&fixed, &movable, &positions).unwrap();
+        assert!(energy[0] > energy[1] && energy[1] > 0.0);
+    }
+
+    #[test]
+    fn long_magnet_on_plate_pull_force() {
+        // Magnet and image form a continuous bar with B_y close to J at the
+        // contact face
+        let magnet = Rectangle::new(1.0, 20.0, (0.0, 10.0), 0.0, 1.0, 90.0);
+        let force = pull_force_to_plate(&magnet, 0.0).unwrap();
+        let expected = 1.0 / (2.0 * MU0);
+        assert!((force - expected).abs() < 0.05 * expected);
+    }
+
+    #[test]
+    fn pull_force_decreases_with_gap() {
+        let forces: Vec<f64> = [0.0, 0.05, 0.2, 1.0]
+            .iter()
+            .map(|gap| {
+                let magnet = Rectangle::new(1.0, 0.5, (0.0, -0.25 - gap), 0.0, 1.0, 90.0);
+                pull_force_to_plate(&magnet, 0.0).unwrap()
+            })
+            .collect();
+        for pair in forces.windows(2) {
+            assert!(pair[0] > pair[1] && pair[1] > 0.0);
+        }
+
+        let crossing = Rectangle::new(1.0, 0.5, (0.0, 0.1), 0.0, 1.0, 90.0);
+        assert!(pull_force_to_plate(&crossing, 0.0).is_err());
+    }
+
+    #[test]
+    fn pull_off_gap_shrinks_with_threshold() {
+        let magnet = Rectangle::new(1.0, 0.5, (0.0, -1.0), 0.0, 1.0, 90.0);
+        let contact =
+            pull_force_to_plate(&Rectangle::new(1.0, 0.5, (0.0, -0.25), 0.0, 1.0, 90.0), 0.0)
+                .unwrap();
+
+        let gaps: Vec<f64> = [0.05, 0.2, 0.5]
+            .iter()
+            .map(|fraction| pull_off_gap(&magnet, 0.0, fraction * contact).unwrap())
+            .collect();
+        assert!(gaps[0] > gaps[1] && gaps[1] > gaps[2] && gaps[2] > 0.0);
+
+        // The force at the returned gap is the threshold
+        let at_gap = Rectangle::new(1.0, 0.5, (0.0, -0.25 - gaps[1]), 0.0, 1.0, 90.0);
+        let force = pull_force_to_plate(&at_gap, 0.0).unwrap();
+        assert!((force - 0.2 * contact).abs() < 1e-6 * contact);
+
+        assert!(pull_off_gap(&magnet, 0.0, 2.0 * contact).is_err());
+        assert!(pull_off_gap(&magnet, 0.0, 0.0).is_err());
+    }
+
+    #[test]
+    fn repelling_magnets_are_stiff_when_separated() {
+        let fixed = Rectangle::new(1.0, 1.0, (0.0, 0.0), 0.0, 1.0, 90.0);
+        let movable = Rectangle::new(1.0, 1.0, (0.0, 1.5), 0.0, 1.0, 270.0);
+        let k = stiffness(&fixed, &movable, Point2::new(0.0, 1.0), 1e-3).unwrap();
+        assert!(k > 0.0);
+
+        // Attracting magnets pull together when displaced towards each other
+        let attracting = Rectangle::new(1.0, 1.0, (0.0, 1.5), 0.0, 1.0, 90.0);
+        let k = stiffness(&fixed, &attracting, Point2::new(0.0, 1.0), 1e-3).unwrap();
+        assert!(k < 0.0);
+        assert!(stiffness(&fixed, &movable, Point2::new(0.0, 0.0), 1e-3).is_err());
+    }
+
+    #[test]
+    fn stiffer_suspension_rocks_faster() {
+        let density = 7500.0;
+        let source = |jr: f64, y: f64| {
+            let mut fixed = MagnetCollection2D::new();
+            fixed.push(Rectangle::new(1.0, 1.0, (0.0, y), 0.0, jr, 90.0));
+            fixed
+        };
+        let movable = Rectangle::new(0.5, 0.2, (0.0, 0.0), 0.0, 1.0, 90.0);
+
+        let far = rocking_frequency(&source(1.0, -2.0), &movable, density).unwrap();
+        let near = rocking_frequency(&source(1.0, -1.0), &movable, density).unwrap();
+        let strong = rocking_frequency(&source(2.0, -2.0), &movable, density).unwrap();
+        assert!(far > 0.0 && near > far);
+        // The stiffness is linear in the fixed magnet's remanence
+        assert!((strong / far - 2.0_f64.sqrt()).abs() < 1e-4);
+
+        // Anti-aligned with the field is an unstable orientation
+        let flipped = Rectangle::new(0.5, 0.2, (0.0, 0.0), 0.0, 1.0, 270.0);
+        assert!(rocking_frequency(&source(1.0, -2.0), &flipped, density).is_err());
+        assert!(rocking_frequency(&source(1.0, -2.0), &movable, 0.0).is_err());
+    }
+}
@@ -0,0 +1,378 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/.
+Copyright 2021 Peter Dunne */
+
+//! 2D Magnetic Field Gradients
+//!
+//! Spatial derivatives of the field of any `Magnet2D`, and derivatives with
+//! respect to the parameters of a magnet, computed by central finite
+//! differences with step `h`.
+
+use crate::errors::MagnetError;
+use crate::magnets::magnet2d::{Magnet2D, Rectangle};
+use crate::utils::points::Points;
+use crate::utils::points2::Point2;
+
+/// Returns the field derivatives `(dB/dx, dB/dy)` at `point` using central
+/// differences with step `h`.
+///
+/// The x and y components of the first vector are $`\partial B_x/\partial x`$
+/// and $`\partial B_y/\partial x`$, and likewise for the second.
+pub fn field_gradient(
+    magnet: &dyn Magnet2D<Point2>,
+    point: &Point2,
+    h: f64,
+) -> Result<(Point2, Point2), MagnetError> {
+    if !(h.is_finite() && h > 0.0) {
+        return Err(MagnetError::InvalidParameter(format!(
+            "finite difference step must be positive, got {}",
+            h
+        )));
+    }
+
+    let step_x = Point2::new(h, 0.0);
+    let step_y = Point2::new(0.0, h);
+    let scale = 1.0 / (2.0 * h);
+
+    let d_dx = magnet.get_field(&(*point + step_x))? - magnet.get_field(&(*point - step_x))?;
+    let d_dy = magnet.get_field(&(*point + step_y))? - magnet.get_field(&(*point - step_y))?;
+
+    Ok((
+        Point2::new(d_dx.x * scale, d_dx.y * scale),
+        Point2::new(d_dy.x * scale, d_dy.y * scale),
+    ))
+}
+
+/// Returns an interleaved `f32` buffer of
+/// `(Bx, By, dBx/dx, dBx/dy, dBy/dx, dBy/dy)` for each point, ready to be
+/// uploaded to a GPU as a buffer or texture.
+pub fn field_and_gradient_grid(
+    magnet: &dyn Magnet2D<Point2>,
+    points: &[Point2],
+    h: f64,
+) -> Result<Vec<f32>, MagnetError> {
+    let mut buffer = Vec::with_capacity(6 * points.len());
+
+    for point in points {
+        let field = magnet.get_field(point)?;
+        let (d_dx, d_dy) = field_gradient(magnet, point, h)?;
+        buffer.extend_from_slice(&[
+            field.x as f32,
+            field.y as f32,
+            d_dx.x as f32,
+            d_dy.x as f32,
+            d_dx.y as f32,
+            d_dy.y as f32,
+        ]);
+    }
+
+    Ok(buffer)
+}
+
+/// Returns $`|(\mathbf{B} \cdot \nabla) \mathbf{B}|`$, in T²/m, at each
+/// point, using central differences with step `h`.
+///
+/// The force on a small paramagnetic particle of volume $`V`$ and
+/// susceptibility $`\chi \ll 1`$ is
+/// $`\mathbf{F} = \frac{\chi V}{\mu_0} (\mathbf{B} \cdot \nabla) \mathbf{B}`$,
+/// so this maps where a magnetic separator captures particles.
+pub fn separation_force_grid(
+    magnet: &dyn Magnet2D<Point2>,
+    points: &[Point2],
+    h: f64,
+) -> Result<Vec<f64>, MagnetError> {
+    points
+        .iter()
+        .map(|point| {
+            let field = magnet.get_field(point)?;
+            let (d_dx, d_dy) = field_gradient(magnet, point, h)?;
+            let force_x = field.x * d_dx.x + field.y * d_dy.x;
+            let force_y = field.x * d_dx.y + field.y * d_dy.y;
+            Ok(force_x.hypot(force_y))
+        })
+        .collect()
+}
+
+/// Returns $`\nabla(|\mathbf{B}|^2)`$, in T²/m, at each point, using central
+/// differences with step `h`.
+///
+/// In magnetophoresis the drift velocity of a small particle is proportional
+/// to $`\nabla(B^2)`$, so the vectors give the direction particles are
+/// carried in, up to the sign of their susceptibility contrast with the fluid.
+pub fn magnetophoretic_velocity_factor(
+    magnet: &dyn Magnet2D<Point2>,
+    points: &[Point2],
+    h: f64,
+) -> Result<Vec<Point2>, MagnetError> {
+    points
+        .iter()
+        .map(|point| {
+            let field = magnet.get_field(point)?;
+            let (d_dx, d_dy) = field_gradient(magnet, point, h)?;
+            Ok(Point2::new(
+                2.0 * (field.x * d_dx.x + field.y * d_dx.y),
+                2.0 * (field.x * d_dy.x + field.y * d_dy.y),
+            ))
+        })
+        .collect()
+}
+
+/// Returns the gradient of the field angle $`\theta = \tan^{-1}(B_y/B_x)`$,
+/// in rad/m, at `point`, i.e. how fast the field direction rotates in space.
+///
+/// The gradient is computed as
+/// $`\nabla\theta = (B_x \nabla B_y - B_y \nabla B_x)/|\mathbf{B}|^2`$ from
+/// central differences with step `h`, which avoids the branch cut of the
+/// angle. Returns an error where the field vanishes and its direction is
+/// undefined.
+pub fn field_angle_gradient(
+    magnet: &dyn Magnet2D<Point2>,
+    point: Point2,
+    h: f64,
+) -> Result<Point2, MagnetError> {
+    let (d_dx, d_dy) = field_gradient(magnet, &point, h)?;
+    let field = magnet.get_field(&point)?;
+    let magnitude_sq = field.x.powi(2) + field.y.powi(2);
+    if magnitude_sq == 0.0 {
+        return Err(MagnetError::Calculation(format!(
+            "the field direction is undefined at {}, where B = 0",
+            point
+        )));
+    }
+
+    Ok(Point2::new(
+        (field.x * d_dx.y - field.y * d_dx.x) / magnitude_sq,
+        (field.x * d_dy.y - field.y * d_dy.x) / magnitude_sq,
+    ))
+}
+
+/// Returns the rate of change of the field, in T/s, at a fixed observation
+/// `point` as `magnet` moves with `velocity`, in m/s.
+///
+/// The field of the moving magnet is $`\mathbf{B}(\mathbf{r} - \mathbf{v}t)`$,
+/// so that $`\partial \mathbf{B}/\partial t = -(\mathbf{v} \cdot \nabla)\mathbf{B}`$,
+/// evaluated by central differences with step `h`. Its flux through a pickup
+/// coil gives the induced EMF.
+pub fn dfield_dt(
+    magnet: &Rectangle,
+    velocity: Point2,
+    point: Point2,
+    h: f64,
+) -> Result<Point2, MagnetError> {
+    let (d_dx, d_dy) = field_gradient(magnet, &point, h)?;
+    Ok(Point2::new(
+        -(velocity.x * d_dx.x + velocity.y * d_dy.x),
+        -(velocity.x * d_dx.y + velocity.y * d_dy.y),
+    ))
+}
+
+/// A parameter of a [`Rectangle`] for [`field_sensitivity_to_dimension`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DimensionParam {
+    Width,
+    Height,
+    /// The remanence `jr`
+    Magnetisation,
+}
+
+/// Returns the derivative of the field at `point` with respect to the
+/// `param` of `magnet`, in T/m for a dimension and T/T for the
+/// magnetisation, by central differences with step `h` in the parameter.
+///
+/// The magnet keeps its center, rotation and magnetisation direction, so a
+/// dimension grows symmetrically about the center. The field is linear in
+/// the magnetisation, making that derivative exact. Returns an error if the
+/// step would shrink a dimension to zero.
+pub fn field_sensitivity_to_dimension(
+    magnet: &Rectangle,
+    point: Point2,
+    param: DimensionParam,
+    h: f64,
+) -> Result<Point2, MagnetError> {
+    if !(h.is_finite() && h > 0.0) {
+        return Err(MagnetError::InvalidParameter(format!(
+            "finite difference step must be positive, got {}",
+            h
+        )));
+    }
+
+    let (width, height, jr) = (magnet.width, magnet.height, magnet.jr);
+    let shifted = |dw: f64, dh: f64, dj: f64| {
+        Rectangle::new(
+            width + dw,
+            height + dh,
+            magnet.center,
+            magnet.alpha,
+            jr + dj,
+            magnet.theta,
+        )
+    };
+    let (plus, minus) = match param {
+        DimensionParam::Width => (shifted(h, 0.0, 0.0), shifted(-h, 0.0, 0.0)),
+        DimensionParam::Height => (shifted(0.0, h, 0.0), shifted(0.0, -h, 0.0)),
+        DimensionParam::Magnetisation => (shifted(0.0, 0.0, h), shifted(0.0, 0.0, -h)),
+    };
+    if minus.width <= 0.0 || minus.height <= 0.0 {
+        return Err(MagnetError::InvalidParameter(format!(
+            "step {} is too large for a magnet of {} x {}",
+            h, width, height
+        )));
+    }
+
+    let difference = plus.get_field(&point)? - minus.get_field(&point)?;
+    Ok(difference.scale(1.0 / (2.0 * h)))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::magnets::magnet2d::gradient::{
+        dfield_dt, field_and_gradient_grid, field_angle_gradient, field_gradient,
+        field_sensitivity_to_dimension, magnetophoretic_velocity_factor, separation_force_grid,
+        DimensionParam,
+    };
+    use crate::magnets::magnet2d::{Circle, Magnet2D, Rectangle, UniformField2D};
+    use crate::utils::points::Points;
+    use crate::utils::points2::{Point2, Points2};
+
+    #[test]
+    fn buffer_length() {
+        let magnet = Rectangle::default();
+        let points: Vec<Point2> = (0..7).map(|i| Point2::new(i as f64, 2.0)).collect();
+        let buffer = field_and_gradient_grid(&magnet, &points, 1e-4).unwrap();
+        assert_eq!(buffer.len(), 6 * points.len());
+
+        let field = magnet.get_field(&points[3]).unwrap();
+        assert_eq!(buffer[18], field.x as f32);
+        assert_eq!(buffer[19], field.y as f32);
+    }
+
+    #[test]
+    fn gradient_is_curl_and_divergence_free() {
+        // Outside the magnet, dBx/dx = -dBy/dy and dBx/dy = dBy/dx
+        let magnet = Rectangle::new(1.0, 2.0, (0.0, 0.0), 0.0, 1.0, 30.0);
+        let (d_dx, d_dy) = field_gradient(&magnet, &Point2::new(1.2, 0.7), 1e-4).unwrap();
+        assert!((d_dx.x + d_dy.y).abs() < 1e-6);
+        assert!((d_dy.x - d_dx.y).abs() < 1e-6);
+    }
+
+    #[test]
+    fn separation_force_peaks_at_edges() {
+        let magnet = Rectangle::new(1.0, 1.0, (0.0, 0.0), 0.0, 1.0, 90.0);
+        let points: Vec<Point2> = (0..61)
+            .map(|i| Point2::new(-1.5 + 0.05 * i as f64, 0.6))
+            .collect();
+        let force = separation_force_grid(&magnet, &points, 1e-5).unwrap();
+
+        let (i_max, _) =
+            force.iter().enumerate().fold(
+                (0, 0.0),
+                |acc, (i, f)| if *f > acc.1 { (i, *f) } else { acc },
+            );
+        assert!((points[i_max].x.abs() - 0.5).abs() < 0.15);
+        // Above the middle of the pole face the field is nearly uniform
+        assert!(force[30] < 0.5 * force[i_max]);
+    }
+
+    #[test]
+    fn angle_gradient_of_uniform_direction() {
+        let uniform = UniformField2D::new(Point2::new(0.3, -0.2));
+        let gradient = field_angle_gradient(&uniform, Point2::new(1.0, 2.0), 1e-4).unwrap();
+        assert_eq!(Point2::new(0.0, 0.0), gradient);
+
+        // The field inside a circular magnet is uniform
+        let magnet = Circle::new(1.0, (0.0, 0.0), 0.0, 1.0, 40.0);
+        let gradient = field_angle_gradient(&magnet, Point2::new(0.2, -0.3), 1e-4).unwrap();
+        assert!(gradient.x.abs() < 1e-9 && gradient.y.abs() < 1e-9);
+    }
+
+    #[test]
+    fn angle_gradient_of_dipole() {
+        // Outside a circle magnetised along x, the field angle is twice the
+        // polar angle, so its gradient is 2 (-y, x) / r^2
+        let magnet = Circle::new(0.5, (0.0, 0.0), 0.0, 1.0, 0.0);
+        let point = Point2::new(0.8, 0.6);
+        let gradient = field_angle_gradient(&magnet, point, 1e-5).unwrap();
+        assert!((gradient.x + 1.2).abs() < 1e-6 && (gradient.y - 1.6).abs() < 1e-6);
+    }
+
+    #[test]
+    fn moving_magnet_field_rate() {
+        let magnet = Rectangle::new(1.0, 1.0, (0.0, 0.0), 0.0, 1.0, 90.0);
+        let point = Point2::new(0.7, 0.9);
+        let stationary = dfield_dt(&magnet, Point2::zero(), point, 1e-4).unwrap();
+        assert_eq!(Point2::zero(), stationary);
+
+        // Moving the magnet by v dt is equivalent to moving the point by -v dt
+        let velocity = Point2::new(2.0, -1.0);
+        let dt = 1e-6;
+        let rate = dfield_dt(&magnet, velocity, point, 1e-5).unwrap();
+        let before = magnet.get_field(&(point + velocity.scale(dt))).unwrap();
+        let after = magnet.get_field(&(point - velocity.scale(dt))).unwrap();
+        let expected = (after - before).scale(1.0 / (2.0 * dt));
+        assert!((rate - expected).magnitude() < 1e-5 * expected.magnitude());
+    }
+
+    #[test]
+    fn magnetophoretic_drift_towards_stronger_field() {
+        let magnet = Rectangle::new(1.0, 1.0, (0.0, 0.0), 0.0, 1.0, 90.0);
+        let points = [
+            Point2::new(0.0, 0.8),
+            Point2::new(0.9, 0.4),
+            Point2::new(-0.3, -1.2),
+        ];
+        let drift = magnetophoretic_velocity_factor(&magnet, &points, 1e-5).unwrap();
+
+        // Directly above the magnet the field increases towards the pole face
+        assert!(drift[0].y < 0.0 && drift[0].x.abs() < 1e-9);
+
+        // A small step along the drift increases |B|
+        for (point, v) in points.iter().zip(drift.iter()) {
+            let step = v.unit().scale(1e-3);
+            let before = magnet.get_field(point).unwrap().magnitude();
+            let after = magnet.get_field(&(*point + step)).unwrap().magnitude();
+            assert!(after > before);
+        }
+    }
+
+    #[test]
+    fn invalid_step() {
+        let magnet = Rectangle::default();
+        assert!(field_gradient(&magnet, &Point2::new(1.0, 1.0), 0.0).is_err());
+    }
+
+    #[test]
+    fn magnetisation_sensitivity_is_linear() {
+        let magnet = Rectangle::new(1.0, 0.5, (0.2, -0.1), 30.0, 1.2, 60.0);
+        let point = Point2::new(0.9, 1.1);
+        let sensitivity =
+            field_sensitivity_to_dimension(&magnet, point, DimensionParam::Magnetisation, 0.1)
+                .unwrap();
+
+        // B is proportional to jr, so dB/djr = B / jr and a step adds dB
+        let field = magnet.get_field(&point).unwrap();
+        assert!((sensitivity - field.scale(1.0 / 1.2)).magnitude() < 1e-12);
+        let stronger = Rectangle::new(1.0, 0.5, (0.2, -0.1), 30.0, 1.5, 60.0);
+        let increase = stronger.get_field(&point).unwrap() - field;
+        assert!((increase - sensitivity.scale(0.3)).magnitude() < 1e-12);
+    }
+
+    #[test]
+    fn dimension_sensitivity_matches_difference() {
+        let magnet = Rectangle::new(1.0, 0.5, (0.0, 0.0), 0.0, 1.0, 90.0);
+        let point = Point2::new(0.3, 1.0);
+        let d_dw =
+            field_sensitivity_to_dimension(&magnet, point, DimensionParam::Width, 1e-4).unwrap();
+        let wider = Rectangle::new(1.01, 0.5, (0.0, 0.0), 0.0, 1.0, 90.0);
+        let change = wider.get_field(&point).unwrap() - magnet.get_field(&point).unwrap();
+        assert!((change.scale(100.0) - d_dw).magnitude() < 1e-2 * d_dw.magnitude());
+
+        // A taller magnet has a stronger field above it
+        let d_dh =
+            field_sensitivity_to_dimension(&magnet, point, DimensionParam::Height, 1e-4).unwrap();
+        assert!(d_dh.y > 0.0);
+        assert!(
+            field_sensitivity_to_dimension(&magnet, point, DimensionParam::Height, 0.5).is_err()
+        );
+    }
+}
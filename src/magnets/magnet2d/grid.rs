@@ -0,0 +1,506 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/.
+Copyright 2021 Peter Dunne */
+
+//! Fields cached on regular 2D grids
+//!
+//! A field sampled once on a grid can be interpolated cheaply at arbitrary
+//! points, e.g. when tracing field lines through a slow to evaluate
+//! assembly.
+
+use crate::errors::MagnetError;
+use crate::magnets::magnet2d::Magnet2D;
+use crate::utils::points::Points;
+use crate::utils::points2::{Point2, Points2};
+use crate::PI;
+
+/// A field sampled on a regular `nx` x `ny` grid spanning `start` to `end`,
+/// including both corners
+///
+/// The samples are stored with x the fastest varying index, so node `(i, j)`
+/// is at `values[j * nx + i]`.
+///
+/// # Example
+/// ```rust
+/// use magnet_rs::magnets::magnet2d::grid::FieldGrid;
+/// use magnet_rs::magnets::magnet2d::Rectangle;
+/// use magnet_rs::utils::points2::Point2;
+/// let magnet = Rectangle::default();
+/// let grid = FieldGrid::from_magnet(&magnet, Point2::new(-2.0, 1.0), Point2::new(2.0, 3.0), 41, 21)
+///     .unwrap();
+/// let field = grid.interpolate_bicubic(Point2::new(0.33, 1.52)).unwrap();
+/// ```
+#[derive(Clone, Debug)]
+pub struct FieldGrid {
+    pub start: Point2,
+    pub end: Point2,
+    pub nx: usize,
+    pub ny: usize,
+    pub values: Vec<Point2>,
+}
+
+impl FieldGrid {
+    /// Samples `field` at the nodes of the grid
+    pub fn from_fn<F>(
+        start: Point2,
+        end: Point2,
+        nx: usize,
+        ny: usize,
+        mut field: F,
+    ) -> Result<FieldGrid, MagnetError>
+    where
+        F: FnMut(Point2) -> Result<Point2, MagnetError>,
+    {
+        if nx < 2 || ny < 2 {
+            return Err(MagnetError::InvalidParameter(format!(
+                "a grid needs at least 2 x 2 nodes, got {} x {}",
+                nx, ny
+            )));
+        }
+        if !(end.x > start.x && end.y > start.y) {
+            return Err(MagnetError::InvalidParameter(format!(
+                "grid end {} must lie above and right of start {}",
+                end, start
+            )));
+        }
+
+        let mut grid = FieldGrid {
+            start,
+            end,
+            nx,
+            ny,
+            values: Vec::with_capacity(nx * ny),
+        };
+        for j in 0..ny {
+            for i in 0..nx {
+                let value = field(grid.node(i, j))?;
+                grid.values.push(value);
+            }
+        }
+        Ok(grid)
+    }
+
+    /// Samples the field of `magnet` at the nodes of the grid
+    pub fn from_magnet(
+        magnet: &dyn Magnet2D<Point2>,
+        start: Point2,
+        end: Point2,
+        nx: usize,
+        ny: usize,
+    ) -> Result<FieldGrid, MagnetError> {
+        FieldGrid::from_fn(start, end, nx, ny, |point| Ok(magnet.get_field(&point)?))
+    }
+
+    /// Returns the grid spacing in x and y
+    pub fn spacing(&self) -> Point2 {
+        Point2::new(
+            (self.end.x - self.start.x) / (self.nx - 1) as f64,
+            (self.end.y - self.start.y) / (self.ny - 1) as f64,
+        )
+    }
+
+    /// Returns the position of node `(i, j)`
+    pub fn node(&self, i: usize, j: usize) -> Point2 {
+        let spacing = self.spacing();
+        self.start + Point2::new(i as f64 * spacing.x, j as f64 * spacing.y)
+    }
+
+    /// Returns the cell `(i, j)` containing `point` and the fractional
+    /// position `(t, u)` within it, or `None` outside the grid
+    fn locate(&self, point: Point2) -> Option<(usize, usize, f64, f64)> {
+        let spacing = self.spacing();
+        let s = (point.x - self.start.x) / spacing.x;
+        let r = (point.y - self.start.y) / spacing.y;
+        let (x_max, y_max) = ((self.nx - 1) as f64, (self.ny - 1) as f64);
+        if !(s >= 0.0 && s <= x_max && r >= 0.0 && r <= y_max) {
+            return None;
+        }
+
+        let i = (s.floor() as usize).min(self.nx - 2);
+        let j = (r.floor() as usize).min(self.ny - 2);
+        Some((i, j, s - i as f64, r - j as f64))
+    }
+
+    /// Returns the sample at node `(i, j)`, extrapolating quadratically, or
+    /// linearly for 2 nodes, one node beyond each edge
+    fn value(&self, i: isize, j: isize) -> Point2 {
+        let (nx, ny) = (self.nx as isize, self.ny as isize);
+        let ghost = |p: [Point2; 3], n: isize| {
+            if n > 2 {
+                (p[0] - p[1]).scale(3.0) + p[2]
+            } else {
+                p[0].scale(2.0) - p[1]
+            }
+        };
+
+        if i < 0 {
+            ghost(
+                [
+                    self.value(0, j),
+                    self.value(1, j),
+                    self.value(2.min(nx - 1), j),
+                ],
+                nx,
+            )
+        } else if i >= nx {
+            let p = [
+                self.value(nx - 1, j),
+                self.value(nx - 2, j),
+                self.value((nx - 3).max(0), j),
+            ];
+            ghost(p, nx)
+        } else if j < 0 {
+            ghost(
+                [
+                    self.value(i, 0),
+                    self.value(i, 1),
+                    self.value(i, 2.min(ny - 1)),
+                ],
+                ny,
+            )
+        } else if j >= ny {
+            let p = [
+                self.value(i, ny - 1),
+                self.value(i, ny - 2),
+                self.value(i, (ny - 3).max(0)),
+            ];
+            ghost(p, ny)
+        } else {
+            self.values[(j * nx + i) as usize]
+        }
+    }
+
+    /// Returns the bilinear interpolation of the field at `point`, or `None`
+    /// outside the grid
+    ///
+    /// The interpolant is continuous, but its derivatives jump across cell
+    /// boundaries.
+    pub fn interpolate(&self, point: Point2) -> Option<Point2> {
+        let (i, j, t, u) = self.locate(point)?;
+        let at = |i: usize, j: usize| self.values[j * self.nx + i];
+
+        Some(
+            at(i, j).scale((1.0 - t) * (1.0 - u))
+                + at(i + 1, j).scale(t * (1.0 - u))
+                + at(i, j + 1).scale((1.0 - t) * u)
+                + at(i + 1, j + 1).scale(t * u),
+        )
+    }
+
+    /// Returns the bicubic interpolation of the field at `point`, or `None`
+    /// outside the grid
+    ///
+    /// Catmull-Rom splines through the 4 x 4 surrounding nodes give an
+    /// interpolant with continuous first derivatives and third order
+    /// accuracy. Beyond the edges the samples are extrapolated quadratically,
+    /// which keeps the accuracy in the edge cells.
+    pub fn interpolate_bicubic(&self, point: Point2) -> Option<Point2> {
+        let (i, j, t, u) = self.locate(point)?;
+        let (i, j) = (i as isize, j as isize);
+
+        let rows: Vec<Point2> = (-1..=2)
+            .map(|dj| {
+                catmull_rom(
+                    [
+                        self.value(i - 1, j + dj),
+                        self.value(i, j + dj),
+                        self.value(i + 1, j + dj),
+                        self.value(i + 2, j + dj),
+                    ],
+                    t,
+                )
+            })
+            .collect();
+        Some(catmull_rom([rows[0], rows[1], rows[2], rows[3]], u))
+    }
+}
+
+/// Returns the nodes and field of a square grid of nodes over the box from
+/// `bbox.0` to `bbox.1`, refined until the map is converged.
+///
+/// Starting from 5 x 5 nodes, the number of cells along each side is doubled,
+/// keeping the previous nodes, until the bilinear interpolation of the
+/// previous grid matches the field at every new node to within `rel_tol` of
+/// the largest field magnitude. The nodes are ordered with x the fastest
+/// varying index. Returns an error if converging needs more than `max_res`
+/// nodes along a side. The field must be continuous in the box, i.e. the box
+/// must not cut through a magnet's surface.
+pub fn converged_field_map(
+    magnet: &dyn Magnet2D<Point2>,
+    bbox: (Point2, Point2),
+    rel_tol: f64,
+    max_res: usize,
+) -> Result<(Vec<Point2>, Vec<Point2>), MagnetError> {
+    if !(rel_tol.is_finite() && rel_tol > 0.0) {
+        return Err(MagnetError::InvalidParameter(format!(
+            "tolerance must be positive, got {}",
+            rel_tol
+        )));
+    }
+
+    let (start, end) = bbox;
+    let mut n = 5;
+    let mut coarse = FieldGrid::from_magnet(magnet, start, end, n, n)?;
+    while 2 * n - 1 <= max_res {
+        n = 2 * n - 1;
+        let fine = FieldGrid::from_magnet(magnet, start, end, n, n)?;
+
+        let mut max_change = 0.0_f64;
+        let mut max_field = 0.0_f64;
+        for j in 0..n {
+            for i in 0..n {
+                let value = fine.values[j * n + i];
+                // Nodes of the fine grid lie within the coarse one
+                let estimate = coarse.interpolate(fine.node(i, j)).unwrap_or(value);
+                max_change = max_change.max((value - estimate).magnitude());
+                max_field = max_field.max(value.magnitude());
+            }
+        }
+
+        if max_change <= rel_tol * max_field {
+            let points = (0..n * n).map(|k| fine.node(k % n, k / n)).collect();
+            return Ok((points, fine.values));
+        }
+        coarse = fine;
+    }
+
+    Err(MagnetError::Calculation(format!(
+        "field map not converged to {} with at most {} nodes per side",
+        rel_tol, max_res
+    )))
+}
+
+/// Returns the points and field of `magnet` on a log-polar grid about
+/// `center`, for conformal analysis in the coordinates
+/// $`w = \ln(z - z_0) = \ln \rho + i \theta`$.
+///
+/// The `n_rho` radii are spaced geometrically from `rho_range.0` to
+/// `rho_range.1`, so uniformly in $`\ln \rho`$, and the `n_theta` angles
+/// uniformly from `theta_range.0` to `theta_range.1` in degrees, both
+/// including the ends. The points are ordered with the radius the fastest
+/// varying index, and the field is in Cartesian components in the global
+/// frame.
+pub fn field_log_polar(
+    magnet: &dyn Magnet2D<Point2>,
+    center: Point2,
+    rho_range: (f64, f64),
+    theta_range: (f64, f64),
+    n_rho: usize,
+    n_theta: usize,
+) -> Result<(Vec<Point2>, Vec<Point2>), MagnetError> {
+    let (rho_min, rho_max) = rho_range;
+    if !(rho_min.is_finite() && rho_max.is_finite() && rho_min > 0.0 && rho_max > rho_min) {
+        return Err(MagnetError::InvalidParameter(format!(
+            "radii must satisfy 0 < min < max, got {:?}",
+            rho_range
+        )));
+    }
+    if !(theta_range.0.is_finite() && theta_range.1.is_finite()) {
+        return Err(MagnetError::InvalidParameter(format!(
+            "angles must be finite, got {:?}",
+            theta_range
+        )));
+    }
+    if n_rho < 2 || n_theta < 2 {
+        return Err(MagnetError::InvalidParameter(format!(
+            "a log-polar grid needs at least 2 x 2 points, got {} x {}",
+            n_rho, n_theta
+        )));
+    }
+
+    let log_step = (rho_max / rho_min).ln() / (n_rho - 1) as f64;
+    let theta_step = (theta_range.1 - theta_range.0) / (n_theta - 1) as f64;
+    let mut points = Vec::with_capacity(n_rho * n_theta);
+    for j in 0..n_theta {
+        let theta = (theta_range.0 + j as f64 * theta_step) * PI / 180.;
+        for i in 0..n_rho {
+            let rho = rho_min * (i as f64 * log_step).exp();
+            points.push(center + Point2::new(theta.cos(), theta.sin()).scale(rho));
+        }
+    }
+
+    let field = points
+        .iter()
+        .map(|point| Ok(magnet.get_field(point)?))
+        .collect::<Result<Vec<Point2>, MagnetError>>()?;
+
+    Ok((points, field))
+}
+
+/// A component of a 2D field, signed except for the magnitude
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Component {
+    X,
+    Y,
+    /// Projection on the direction at an angle, in degrees, from the x axis
+    Along(f64),
+    /// The magnitude $`|\mathbf{B}|`$
+    Magnitude,
+}
+
+impl Component {
+    /// Returns this component of `field`
+    pub fn of(&self, field: Point2) -> f64 {
+        match self {
+            Component::X => field.x,
+            Component::Y => field.y,
+            Component::Along(angle) => {
+                let angle = angle * PI / 180.;
+                field.x * angle.cos() + field.y * angle.sin()
+            }
+            Component::Magnitude => field.magnitude(),
+        }
+    }
+}
+
+/// Returns the signed `component` of the field of `magnet` at each of
+/// `points`, for plotting with a diverging colour map centred on zero.
+///
+/// Unlike $`|\mathbf{B}|`$, the sign distinguishes the return flux of a
+/// magnet from its forward field, e.g. $`B_y`$ of a magnet magnetised along
+/// y is negative beside it and positive above and below.
+pub fn field_component_signed_grid(
+    magnet: &dyn Magnet2D<Point2>,
+    points: &[Point2],
+    component: Component,
+) -> Result<Vec<f64>, MagnetError> {
+    points
+        .iter()
+        .map(|point| Ok(component.of(magnet.get_field(point)?)))
+        .collect()
+}
+
+/// Returns the Catmull-Rom spline through `p[1]` and `p[2]` at fraction `t`
+/// between them
+fn catmull_rom(p: [Point2; 4], t: f64) -> Point2 {
+    let t_2 = t * t;
+    let t_3 = t_2 * t;
+    (p[0].scale(-t_3 + 2.0 * t_2 - t)
+        + p[1].scale(3.0 * t_3 - 5.0 * t_2 + 2.0)
+        + p[2].scale(-3.0 * t_3 + 4.0 * t_2 + t)
+        + p[3].scale(t_3 - t_2))
+    .scale(0.5)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::magnets::magnet2d::grid::{
+        converged_field_map, field_component_signed_grid, field_log_polar, Component, FieldGrid,
+    };
+    use crate::magnets::magnet2d::{Magnet2D, Rectangle};
+    use crate::utils::points2::{Point2, Points2};
+
+    fn analytic(point: Point2) -> Point2 {
+        Point2::new(point.x.sin() * point.y.cos(), (point.x * point.y).exp())
+    }
+
+    #[test]
+    fn interpolation_is_exact_at_nodes() {
+        let magnet = Rectangle::new(1.0, 1.0, (0.0, 0.0), 0.0, 1.0, 45.0);
+        let grid =
+            FieldGrid::from_magnet(&magnet, Point2::new(-1.0, 0.6), Point2::new(1.0, 2.0), 5, 8)
+                .unwrap();
+        let node = grid.node(3, 2);
+        let expected = magnet.get_field(&node).unwrap();
+        assert!((grid.interpolate(node).unwrap() - expected).magnitude() < 1e-14);
+        assert!((grid.interpolate_bicubic(node).unwrap() - expected).magnitude() < 1e-14);
+        assert!(grid.interpolate(Point2::new(1.1, 1.0)).is_none());
+    }
+
+    #[test]
+    fn bicubic_beats_bilinear() {
+        let grid = FieldGrid::from_fn(Point2::new(0.0, 0.0), Point2::new(2.0, 1.0), 21, 11, |p| {
+            Ok(analytic(p))
+        })
+        .unwrap();
+
+        let mut bilinear_error = 0.0_f64;
+        let mut bicubic_error = 0.0_f64;
+        for k in 0..50 {
+            let point = Point2::new(0.013 + 0.0397 * k as f64, 0.5 + 0.45 * (k as f64).sin());
+            let exact = analytic(point);
+            bilinear_error =
+                bilinear_error.max((grid.interpolate(point).unwrap() - exact).magnitude());
+            bicubic_error =
+                bicubic_error.max((grid.interpolate_bicubic(point).unwrap() - exact).magnitude());
+        }
+        assert!(bicubic_error < 0.1 * bilinear_error);
+    }
+
+    #[test]
+    fn looser_tolerance_converges_sooner() {
+        let magnet = Rectangle::new(1.0, 1.0, (0.0, 0.0), 0.0, 1.0, 90.0);
+        let bbox = (Point2::new(-2.0, 0.8), Point2::new(2.0, 3.0));
+        let (loose_points, loose_field) = converged_field_map(&magnet, bbox, 1e-2, 1025).unwrap();
+        let (tight_points, _) = converged_field_map(&magnet, bbox, 1e-4, 1025).unwrap();
+
+        assert_eq!(loose_points.len(), loose_field.len());
+        assert!(loose_points.len() < tight_points.len());
+        assert_eq!(magnet.get_field(&loose_points[7]).unwrap(), loose_field[7]);
+        assert!(converged_field_map(&magnet, bbox, 1e-4, 9).is_err());
+    }
+
+    #[test]
+    fn log_polar_grid_dimensions_and_continuity() {
+        let magnet = Rectangle::new(1.0, 0.5, (0.2, 0.1), 20.0, 1.0, 60.0);
+        let center = Point2::new(0.2, 0.1);
+        let (n_rho, n_theta) = (40, 145);
+        let (points, field) =
+            field_log_polar(&magnet, center, (1.0, 10.0), (0.0, 360.0), n_rho, n_theta).unwrap();
+        assert_eq!(n_rho * n_theta, points.len());
+        assert_eq!(points.len(), field.len());
+
+        // Radii are uniform in ln(rho), and the first and last angles coincide
+        let ratio = (points[1] - center).magnitude() / (points[0] - center).magnitude();
+        assert!(((points[n_rho - 1] - center).magnitude() - 10.0).abs() < 1e-12);
+        assert!((ratio - 10.0_f64.powf(1.0 / 39.0)).abs() < 1e-12);
+        for i in 0..n_rho {
+            assert!((field[i] - field[(n_theta - 1) * n_rho + i]).magnitude() < 1e-12);
+        }
+
+        // Neighbouring samples differ by a small fraction of the largest
+        // field at that radius
+        for i in 0..n_rho - 1 {
+            let local = (0..n_theta)
+                .map(|j| field[j * n_rho + i].magnitude())
+                .fold(0.0, f64::max);
+            for j in 0..n_theta - 1 {
+                let k = j * n_rho + i;
+                assert!((field[k + 1] - field[k]).magnitude() < 0.2 * local);
+                assert!((field[k + n_rho] - field[k]).magnitude() < 0.2 * local);
+            }
+        }
+    }
+
+    #[test]
+    fn log_polar_invalid_ranges() {
+        let magnet = Rectangle::default();
+        let center = Point2::zero();
+        assert!(field_log_polar(&magnet, center, (0.0, 1.0), (0.0, 90.0), 4, 4).is_err());
+        assert!(field_log_polar(&magnet, center, (2.0, 1.0), (0.0, 90.0), 4, 4).is_err());
+        assert!(field_log_polar(&magnet, center, (1.0, 2.0), (0.0, 90.0), 1, 4).is_err());
+    }
+
+    #[test]
+    fn signed_component_changes_sign() {
+        let magnet = Rectangle::new(0.2, 0.2, (0.0, 0.0), 0.0, 1.0, 90.0);
+        let points: Vec<Point2> = (0..11)
+            .flat_map(|j| {
+                (0..11).map(move |i| Point2::new(-2.0 + 0.4 * i as f64, -2.0 + 0.4 * j as f64))
+            })
+            .collect();
+        let b_y = field_component_signed_grid(&magnet, &points, Component::Y).unwrap();
+        assert!(b_y.iter().any(|b| *b > 0.0) && b_y.iter().any(|b| *b < 0.0));
+
+        // The projection at 90 degrees is the y component
+        let along = field_component_signed_grid(&magnet, &points, Component::Along(90.0)).unwrap();
+        for (a, b) in along.iter().zip(&b_y) {
+            assert!((a - b).abs() < 1e-12);
+        }
+
+        let field = magnet.get_field(&points[0]).unwrap();
+        let b_x = field_component_signed_grid(&magnet, &points[..1], Component::X).unwrap();
+        assert_eq!(field.x, b_x[0]);
+    }
+}
@@ -3,9 +3,31 @@ License, v. 2.0. If a copy of the MPL was not distributed with this
 file, You can obtain one at https://mozilla.org/MPL/2.0/.
 Copyright 2021 Peter Dunne */
 
+use crate::utils::complex::Complex;
 use crate::utils::points2::Point2;
 use crate::PI;
 
+/// Returns the field of a straight line segment from `start` to `end` with a
+/// uniform surface charge `sigma` (in T), at `point`.
+///
+/// In complex notation, with $`\Delta = z_2 - z_1`$,
+///
+/// ```math
+/// B_x - i B_y = \frac{\sigma}{2 \pi} \frac{|\Delta|}{\Delta}
+/// \ln \left( \frac{z - z_1}{z - z_2} \right)
+/// ```
+pub fn charged_segment_field(sigma: f64, start: &Point2, end: &Point2, point: &Point2) -> Point2 {
+    let delta = Complex::new(end.x - start.x, end.y - start.y);
+    let z = Complex::new(point.x, point.y);
+    let z_1 = Complex::new(start.x, start.y);
+    let z_2 = Complex::new(end.x, end.y);
+
+    let conj_field = ((z - z_1) / (z - z_2)).ln() * Complex::new(delta.norm(), 0.0) / delta;
+    let conj_field = conj_field.scale(sigma / (2.0 * PI));
+
+    Point2::new(conj_field.re, -conj_field.im)
+}
+
 pub fn sheet_field(x: f64, y: f64, h: f64, kr: f64) -> Result<Point2, String> {
     let x_sq = x.powi(2);
     let y_sq = y.powi(2);
@@ -21,3 +43,27 @@ pub fn sheet_field(x: f64, y: f64, h: f64, kr: f64) -> Result<Point2, String> {
 
     Ok(Point2::new(bx, by))
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::magnets::magnet2d::line_field::charged_segment_field;
+    use crate::magnets::magnet2d::{Magnet2D, Rectangle};
+    use crate::utils::points2::{Point2, Points2};
+
+    #[test]
+    fn segments_reproduce_rectangle() {
+        // A rectangle magnetised in y is a pair of charged sheets at y = +/- b
+        let magnet = Rectangle::new(2.0, 1.0, (0.0, 0.0), 0.0, 1.0, 90.0);
+        let point = Point2::new(0.7, 1.3);
+        let top =
+            charged_segment_field(1.0, &Point2::new(-1.0, 0.5), &Point2::new(1.0, 0.5), &point);
+        let bottom = charged_segment_field(
+            -1.0,
+            &Point2::new(1.0, -0.5),
+            &Point2::new(-1.0, -0.5),
+            &point,
+        );
+        let field = magnet.get_field(&point).unwrap();
+        assert!((top + bottom - field).magnitude() < 1e-14);
+    }
+}
@@ -0,0 +1,81 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/.
+Copyright 2021 Peter Dunne */
+
+//! Periodic 2D Magnetic Field Routines
+//!
+//! Fields of magnets repeated periodically along x, e.g. magnetic gratings.
+
+use crate::errors::MagnetError;
+use crate::magnets::magnet2d::{Magnet2D, Rectangle};
+use crate::utils::points2::Point2;
+
+/// Returns the field at `point` of a rectangle repeated along x with spacing
+/// `period`, by summing the magnet and `n_images` translated copies on each
+/// side of it.
+///
+/// The field of each copy falls off as $`1/r^2`$, so the truncated image sum
+/// converges absolutely with an error of order $`1/N`$ for `N = n_images`.
+/// Points far from the central cell, $`|x| \gtrsim N \cdot`$ `period`, need
+/// correspondingly more images.
+pub fn periodic_field(
+    magnet: &Rectangle,
+    period: f64,
+    n_images: usize,
+    point: Point2,
+) -> Result<Point2, MagnetError> {
+    if !(period.is_finite() && period > 0.0) {
+        return Err(MagnetError::InvalidParameter(format!(
+            "period must be positive, got {}",
+            period
+        )));
+    }
+
+    let mut field = magnet.get_field(&point)?;
+    for k in 1..=n_images {
+        let shift = Point2::new(k as f64 * period, 0.0);
+        field += magnet.get_field(&(point - shift))?;
+        field += magnet.get_field(&(point + shift))?;
+    }
+
+    Ok(field)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::magnets::magnet2d::periodic::periodic_field;
+    use crate::magnets::magnet2d::Rectangle;
+    use crate::utils::points2::{Point2, Points2};
+
+    #[test]
+    fn image_sum_converges() {
+        let magnet = Rectangle::new(1.0, 1.0, (0.0, 0.0), 0.0, 1.0, 90.0);
+        let point = Point2::new(0.3, 1.0);
+        let reference = periodic_field(&magnet, 2.0, 400, point).unwrap();
+
+        let errors: Vec<f64> = [5, 20, 80]
+            .iter()
+            .map(|n| (periodic_field(&magnet, 2.0, *n, point).unwrap() - reference).magnitude())
+            .collect();
+
+        assert!(errors[0] > errors[1] && errors[1] > errors[2]);
+        assert!(errors[2] < 1e-3);
+    }
+
+    #[test]
+    fn field_is_periodic() {
+        let magnet = Rectangle::new(1.0, 0.5, (0.0, 0.0), 0.0, 1.0, 45.0);
+        let period = 1.5;
+        let point = Point2::new(0.2, 0.8);
+        let field = periodic_field(&magnet, period, 400, point).unwrap();
+        let shifted = periodic_field(&magnet, period, 400, point + Point2::new(period, 0.0));
+        assert!((field - shifted.unwrap()).magnitude() < 1e-4);
+    }
+
+    #[test]
+    fn invalid_period() {
+        let magnet = Rectangle::default();
+        assert!(periodic_field(&magnet, 0.0, 1, Point2::new(0.0, 1.0)).is_err());
+    }
+}
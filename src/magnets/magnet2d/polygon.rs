@@ -0,0 +1,262 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/.
+Copyright 2021 Peter Dunne */
+
+//! Uniformly magnetised polygons
+//!
+//! The field of a uniformly magnetised polygon is that of the surface charge
+//! $`\sigma = \mathbf{J} \cdot \mathbf{\hat{n}}`$ on its edges, each a
+//! uniformly charged segment, so any straight-sided cross-section, e.g. one
+//! taken from CAD, has a closed form field.
+
+use crate::errors::MagnetError;
+use crate::magnets::magnet2d::line_field::charged_segment_field;
+use crate::magnets::magnet2d::Magnet2D;
+use crate::magnets::{check_finite, check_magnetisation, Magnet};
+use crate::utils::points::Points;
+use crate::utils::points2::{Point2, Points2};
+use std::error::Error;
+use std::fmt;
+
+/// A uniformly magnetised simple polygon
+///
+/// The `vertices` are in the global frame and stored anticlockwise, the
+/// order of the input being reversed if needed. The magnetisation
+/// `(jx, jy)`, in T, is in the global frame. The polygon must not
+/// intersect itself.
+///
+/// # Example
+/// ```rust
+/// use magnet_rs::magnets::magnet2d::polygon::PolygonMagnet;
+/// use magnet_rs::magnets::magnet2d::Magnet2D;
+/// use magnet_rs::utils::points2::Point2;
+/// let vertices = vec![
+///     Point2::new(0.0, 0.0),
+///     Point2::new(1.0, 0.0),
+///     Point2::new(0.0, 1.0),
+/// ];
+/// let magnet = PolygonMagnet::new(vertices, Point2::new(0.0, 1.0)).unwrap();
+/// let field = magnet.get_field(&Point2::new(0.5, 1.5)).unwrap();
+/// ```
+#[derive(Clone, Debug)]
+pub struct PolygonMagnet {
+    pub vertices: Vec<Point2>,
+    pub jx: f64,
+    pub jy: f64,
+}
+
+impl PolygonMagnet {
+    pub fn new(
+        mut vertices: Vec<Point2>,
+        magnetisation: Point2,
+    ) -> Result<PolygonMagnet, MagnetError> {
+        if vertices.len() < 3 {
+            return Err(MagnetError::InvalidParameter(format!(
+                "a polygon needs at least 3 vertices, got {}",
+                vertices.len()
+            )));
+        }
+        for vertex in &vertices {
+            check_finite("vertex", &[vertex.x, vertex.y])?;
+        }
+
+        let area = signed_area(&vertices);
+        if area == 0.0 {
+            return Err(MagnetError::InvalidParameter(
+                "polygon has no area".to_string(),
+            ));
+        }
+        if area < 0.0 {
+            vertices.reverse();
+        }
+
+        Ok(PolygonMagnet {
+            vertices,
+            jx: magnetisation.x,
+            jy: magnetisation.y,
+        })
+    }
+
+    /// Returns the area of the polygon
+    pub fn area(&self) -> f64 {
+        signed_area(&self.vertices).abs()
+    }
+
+    /// Returns the edges of the polygon as `(start, end)` pairs
+    fn edges(&self) -> impl Iterator<Item = (Point2, Point2)> + '_ {
+        let n = self.vertices.len();
+        (0..n).map(move |k| (self.vertices[k], self.vertices[(k + 1) % n]))
+    }
+}
+
+/// Returns the area enclosed by `vertices` by the shoelace formula, positive
+/// when they run anticlockwise
+fn signed_area(vertices: &[Point2]) -> f64 {
+    let n = vertices.len();
+    (0..n)
+        .map(|k| {
+            let (p, q) = (vertices[k], vertices[(k + 1) % n]);
+            p.x * q.y - q.x * p.y
+        })
+        .sum::<f64>()
+        / 2.0
+}
+
+/// Implements Display for polygon magnets.
+impl fmt::Display for PolygonMagnet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[polygon vertices: {},\tarea: {},\tJ ({:.3}, {:.3})]",
+            self.vertices.len(),
+            self.area(),
+            self.jx,
+            self.jy
+        )
+    }
+}
+
+impl Magnet for PolygonMagnet {}
+
+impl Magnet2D<Point2> for PolygonMagnet {
+    fn get_field(&self, point: &Point2) -> Result<Point2, Box<dyn Error>> {
+        let j = Point2::new(self.jx, self.jy);
+        let mut field = Point2::zero();
+        for (start, end) in self.edges() {
+            let edge = end - start;
+            let length = edge.magnitude();
+            if length == 0.0 {
+                continue;
+            }
+            let sigma = j.dot(&Point2::new(edge.y, -edge.x).scale(1.0 / length));
+            if sigma != 0.0 {
+                field += charged_segment_field(sigma, &start, &end, point);
+            }
+        }
+
+        if !(field.x.is_finite() && field.y.is_finite()) {
+            return Err(format!("field is singular at the vertex {}", point).into());
+        }
+
+        // The surface charges give mu_0 H, inside the magnet B = mu_0 H + J
+        if self.contains(point) {
+            field += j;
+        }
+        Ok(field)
+    }
+
+    /// Returns the centroid of the area of the polygon
+    fn get_center(&self) -> Point2 {
+        let area = signed_area(&self.vertices);
+        let mut centroid = Point2::zero();
+        for (p, q) in self.edges() {
+            let cross = p.x * q.y - q.x * p.y;
+            centroid += (p + q).scale(cross);
+        }
+        centroid.scale(1.0 / (6.0 * area))
+    }
+
+    /// Returns the size of the bounding box of the polygon
+    fn get_size(&self) -> Point2 {
+        let (mut min, mut max) = (self.vertices[0], self.vertices[0]);
+        for vertex in &self.vertices {
+            min = Point2::new(min.x.min(vertex.x), min.y.min(vertex.y));
+            max = Point2::new(max.x.max(vertex.x), max.y.max(vertex.y));
+        }
+        max - min
+    }
+
+    fn get_j(self) -> Point2 {
+        Point2::new(self.jx, self.jy)
+    }
+
+    /// Uses the even-odd rule, counting crossings of a ray along +x
+    fn contains(&self, point: &Point2) -> bool {
+        let mut inside = false;
+        for (p, q) in self.edges() {
+            if (p.y > point.y) != (q.y > point.y) {
+                let x_cross = p.x + (point.y - p.y) * (q.x - p.x) / (q.y - p.y);
+                if point.x < x_cross {
+                    inside = !inside;
+                }
+            }
+        }
+        inside
+    }
+
+    fn validate(&self) -> Result<(), MagnetError> {
+        if self.vertices.len() < 3 {
+            return Err(MagnetError::InvalidParameter(
+                "a polygon needs at least 3 vertices".to_string(),
+            ));
+        }
+        for vertex in &self.vertices {
+            check_finite("vertex", &[vertex.x, vertex.y])?;
+        }
+        if signed_area(&self.vertices) == 0.0 {
+            return Err(MagnetError::InvalidParameter(
+                "polygon has no area".to_string(),
+            ));
+        }
+        check_magnetisation(self.jx.hypot(self.jy), &[self.jx, self.jy])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::magnets::magnet2d::polygon::PolygonMagnet;
+    use crate::magnets::magnet2d::{Magnet2D, Rectangle};
+    use crate::utils::points2::{Point2, Points2};
+
+    #[test]
+    fn square_matches_rectangle() {
+        let rectangle = Rectangle::new(1.0, 0.5, (0.2, -0.1), 0.0, 1.2, 60.0);
+        // Clockwise, to check that the order is normalised
+        let vertices = vec![
+            Point2::new(-0.3, -0.35),
+            Point2::new(-0.3, 0.15),
+            Point2::new(0.7, 0.15),
+            Point2::new(0.7, -0.35),
+        ];
+        let polygon = PolygonMagnet::new(vertices, rectangle.magnetisation_vector()).unwrap();
+        assert!((polygon.area() - 0.5).abs() < 1e-15);
+        assert!((polygon.get_center() - rectangle.center).magnitude() < 1e-15);
+
+        for point in &[Point2::new(1.5, 0.8), Point2::new(0.25, -0.05)] {
+            let expected = rectangle.get_field(point).unwrap();
+            let field = polygon.get_field(point).unwrap();
+            assert!((field - expected).magnitude() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn triangle_contains_and_far_field() {
+        let vertices = vec![
+            Point2::new(0.0, 0.0),
+            Point2::new(1.0, 0.0),
+            Point2::new(0.0, 1.0),
+        ];
+        let triangle = PolygonMagnet::new(vertices, Point2::new(0.0, 1.0)).unwrap();
+        assert!(triangle.contains(&Point2::new(0.2, 0.2)));
+        assert!(!triangle.contains(&Point2::new(0.6, 0.6)));
+
+        // Far away the field is that of a dipole of moment J A at the centroid
+        let far = Point2::new(0.0, 30.0);
+        let square = Rectangle::new(
+            0.5_f64.sqrt(),
+            0.5_f64.sqrt(),
+            (1.0 / 3.0, 1.0 / 3.0),
+            0.0,
+            1.0,
+            90.0,
+        );
+        let ratio = triangle.get_field(&far).unwrap().y / square.get_field(&far).unwrap().y;
+        assert!((ratio - 1.0).abs() < 1e-3);
+
+        assert!(
+            PolygonMagnet::new(vec![Point2::zero(), Point2::new(1.0, 1.0)], Point2::zero())
+                .is_err()
+        );
+    }
+}
@@ -7,9 +7,17 @@ Copyright 2021 Peter Dunne */
 //!
 //! This submodule exposes
 
+use crate::errors::MagnetError;
+use crate::magnets::magnet2d::line_field::charged_segment_field;
 use crate::magnets::magnet2d::Rectangle;
+use crate::utils::complex::Complex;
+#[cfg(feature = "interval")]
+use crate::utils::interval::Interval;
+#[cfg(feature = "lut")]
+use crate::utils::lut;
+use crate::utils::points::Points;
 use crate::utils::points2::{Point2, Points2};
-use crate::{FP_CUTOFF, I_2PI, I_4PI};
+use crate::{FP_CUTOFF, I_2PI, I_4PI, PI};
 use std::error::Error;
 
 /// Returns the magnetic field vector due to a rectangle of width `2a`, height  `2b`
@@ -17,7 +25,14 @@ use std::error::Error;
 ///
 /// with an arbitrary magnetisation $`\mathbf{J} = J_x \mathbf{\hat{x}} + J_y \mathbf{\hat{y}}`$
 ///
+/// At the centre, a symmetry point where the general formula relies on the
+/// cancellation of opposite corner terms, the exact value from
+/// [`center_field_rectangle`] is returned.
 pub fn get_field_rectangle(magnet: &Rectangle, point: &Point2) -> Result<Point2, Box<dyn Error>> {
+    if point.x == 0.0 && point.y == 0.0 {
+        return Ok(center_field_rectangle(magnet));
+    }
+
     let mut field = Point2::zero();
 
     field += if (magnet.jx / magnet.jr).abs() > FP_CUTOFF {
@@ -45,6 +60,65 @@ pub fn get_field_rectangle(magnet: &Rectangle, point: &Point2) -> Result<Point2,
     Ok(field)
 }
 
+/// The orientation of the coordinate system of points and fields
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Handedness {
+    /// x to the right and y up, the convention of the library
+    RightHanded,
+    /// x to the right and y down, as in image and screen coordinates
+    LeftHanded,
+}
+
+impl Handedness {
+    /// Converts a point or vector between this convention and the library's,
+    /// the conversion being its own inverse
+    pub fn convert(&self, vector: Point2) -> Point2 {
+        match self {
+            Handedness::RightHanded => vector,
+            Handedness::LeftHanded => Point2::new(vector.x, -vector.y),
+        }
+    }
+}
+
+/// Returns the field of a rectangle at `point`, with both the point and the
+/// returned field expressed in the coordinates given by `handedness`.
+///
+/// The magnet itself is defined in the library's right-handed frame, with
+/// `theta` measured anticlockwise from x with y up. A left-handed point
+/// `(x, y)` is the right-handed point `(x, -y)`, and the field there
+/// $`(B_x, B_y)`$ is returned as $`(B_x, -B_y)`$. This is a relabelling of
+/// the axes and leaves the physical field unchanged: a magnet magnetised
+/// along +y in the library frame has a field pointing towards negative
+/// left-handed y above it.
+pub fn get_field_rectangle_handed(
+    magnet: &Rectangle,
+    point: &Point2,
+    handedness: Handedness,
+) -> Result<Point2, Box<dyn Error>> {
+    let field = get_field_rectangle(magnet, &handedness.convert(*point))?;
+    Ok(handedness.convert(field))
+}
+
+/// Returns the field at the centre of a rectangle of width `2a` and height
+/// `2b`, in its local frame.
+///
+/// The charged faces normal to each magnetisation component subtend an angle
+/// $`4 \tan^{-1}(a/b)`$ or $`4 \tan^{-1}(b/a)`$ at the centre, so that
+/// ```math
+/// \mathbf{B} = \frac{2}{\pi} \left( J_x \tan^{-1}\frac{a}{b},\;
+/// J_y \tan^{-1}\frac{b}{a} \right)
+/// ```
+/// which is $`\mathbf{J}/2`$ for a square. These are the local demagnetising
+/// factors at the centre, not the averaged ones of
+/// [`Rectangle::demag_factors`].
+pub fn center_field_rectangle(magnet: &Rectangle) -> Point2 {
+    let (a, b) = (magnet.a, magnet.b);
+    Point2::new(
+        2.0 / PI * magnet.jx * (a / b).atan(),
+        2.0 / PI * magnet.jy * (b / a).atan(),
+    )
+}
+
 /// Returns the magnetic field vector at a point due to a rectangle magnetised in x
 fn magnetic_field_x(magnet: &Rectangle, point: &Point2) -> Result<Point2, Box<dyn Error>> {
     let field = Point2 {
@@ -147,12 +221,605 @@ fn field_in_y_for_y_mag(x: f64, y: f64, a: f64, b: f64, j: f64) -> Result<f64, B
     Ok(j * I_2PI * (top_1.atan2(bottom_1) - top_2.atan2(bottom_2)))
 }
 
+/// Returns the same field as [`get_field_rectangle`], evaluating `atan2` and
+/// `ln` with the lookup tables of [`crate::utils::lut`].
+///
+/// Each angle is within [`lut::ATAN2_MAX_ERROR`] and each logarithm within
+/// [`lut::LN_MAX_ERROR`] of the exact value, so the field differs from
+/// [`get_field_rectangle`] by at most about $`3 \times 10^{-7} |\mathbf{J}|`$.
+/// The tables take 16 kB and replace each transcendental function by a
+/// few multiplications and a memory access, which pays off on targets
+/// without fast floating point libraries; on desktop CPUs the gain is small.
+/// As with the direct formula, precision is lost far from the magnet.
+#[cfg(feature = "lut")]
+pub fn get_field_rectangle_fast(
+    magnet: &Rectangle,
+    point: &Point2,
+) -> Result<Point2, Box<dyn Error>> {
+    let (x, y, a, b) = (point.x, point.y, magnet.a, magnet.b);
+    let (x_plus_a_sq, x_minus_a_sq) = ((x + a).powi(2), (x - a).powi(2));
+    let (y_plus_b_sq, y_minus_b_sq) = ((y + b).powi(2), (y - b).powi(2));
+    let mut field = Point2::zero();
+
+    if (magnet.jx / magnet.jr).abs() > FP_CUTOFF {
+        let (a_sq, x_sq) = (a.powi(2), x.powi(2));
+        let angle = lut::fast_atan2(2.0 * a * (b + y), x_sq - a_sq + y_plus_b_sq)
+            + lut::fast_atan2(2.0 * a * (b - y), x_sq - a_sq + y_minus_b_sq);
+        let log = lut::fast_ln((x_minus_a_sq + y_minus_b_sq) / (x_plus_a_sq + y_minus_b_sq))
+            - lut::fast_ln((x_minus_a_sq + y_plus_b_sq) / (x_plus_a_sq + y_plus_b_sq));
+        field += Point2::new(magnet.jx * I_2PI * angle, -magnet.jx * I_4PI * log);
+    }
+
+    if (magnet.jy / magnet.jr).abs() > FP_CUTOFF {
+        let (b_sq, y_sq) = (b.powi(2), y.powi(2));
+        let log = lut::fast_ln((x_plus_a_sq + y_minus_b_sq) / (x_plus_a_sq + y_plus_b_sq))
+            - lut::fast_ln((x_minus_a_sq + y_minus_b_sq) / (x_minus_a_sq + y_plus_b_sq));
+        let angle = lut::fast_atan2(2.0 * b * (x + a), x_plus_a_sq + y_sq - b_sq)
+            - lut::fast_atan2(2.0 * b * (x - a), x_minus_a_sq + y_sq - b_sq);
+        field += Point2::new(magnet.jy * I_4PI * log, magnet.jy * I_2PI * angle);
+    }
+
+    Ok(field)
+}
+
+/// Returns rigorous lower and upper bounds on each component of the field
+/// of [`get_field_rectangle`] at a `point` in the global frame.
+///
+/// The same expressions are evaluated in the interval arithmetic of
+/// [`crate::utils::interval`], so the bounds hold for the magnet's stored
+/// dimensions, position, rotation and magnetisation components despite the
+/// rounding of every intermediate result, including the change to the
+/// magnet's frame and back. As in [`get_field_rectangle`], a magnetisation
+/// component below `FP_CUTOFF` of `jr` is neglected. The bounds are a few
+/// ulps apart away from the magnet, relative to the field and to the point's
+/// distance from the centre for a rotated magnet, and widen where the direct
+/// formula loses precision. An error is returned where they are unbounded,
+/// at a corner, or straddle a branch cut on a face.
+#[cfg(feature = "interval")]
+pub fn get_field_rectangle_interval(
+    magnet: &Rectangle,
+    point: Point2,
+) -> Result<(Point2, Point2), MagnetError> {
+    let alpha = Interval::point(magnet.alpha) * Interval::rounded(PI) / Interval::point(180.0);
+    let (sin, cos) = alpha.sin_cos();
+    let dx = Interval::point(point.x) - Interval::point(magnet.center.x);
+    let dy = Interval::point(point.y) - Interval::point(magnet.center.y);
+    let (x, y) = (cos * dx + sin * dy, cos * dy - sin * dx);
+    let (a, b) = (Interval::point(magnet.a), Interval::point(magnet.b));
+    let two = Interval::point(2.0);
+    let (i_2pi, i_4pi) = (Interval::rounded(I_2PI), Interval::rounded(I_4PI));
+    let mut field_x = Interval::point(0.0);
+    let mut field_y = Interval::point(0.0);
+
+    let x_plus_a_sq = (x + a).sqr();
+    let x_minus_a_sq = (x - a).sqr();
+    let y_plus_b_sq = (y + b).sqr();
+    let y_minus_b_sq = (y - b).sqr();
+
+    if (magnet.jx / magnet.jr).abs() > FP_CUTOFF {
+        let j = Interval::point(magnet.jx);
+        let xsq_minus_a_sq = x.sqr() - a.sqr();
+        let angle = (two * a * (b + y)).atan2(xsq_minus_a_sq + y_plus_b_sq)
+            + (two * a * (b - y)).atan2(xsq_minus_a_sq + y_minus_b_sq);
+        field_x = field_x + j * i_2pi * angle;
+
+        let log = ((x_minus_a_sq + y_minus_b_sq) / (x_plus_a_sq + y_minus_b_sq)).ln()
+            - ((x_minus_a_sq + y_plus_b_sq) / (x_plus_a_sq + y_plus_b_sq)).ln();
+        field_y = field_y - j * i_4pi * log;
+    }
+
+    if (magnet.jy / magnet.jr).abs() > FP_CUTOFF {
+        let j = Interval::point(magnet.jy);
+        let log = ((x_plus_a_sq + y_minus_b_sq) / (x_plus_a_sq + y_plus_b_sq)).ln()
+            - ((x_minus_a_sq + y_minus_b_sq) / (x_minus_a_sq + y_plus_b_sq)).ln();
+        field_x = field_x + j * i_4pi * log;
+
+        let y_sq_minus_b_sq = y.sqr() - b.sqr();
+        let angle = (two * b * (x + a)).atan2(x_plus_a_sq + y_sq_minus_b_sq)
+            - (two * b * (x - a)).atan2(x_minus_a_sq + y_sq_minus_b_sq);
+        field_y = field_y + j * i_2pi * angle;
+    }
+
+    if !(field_x.is_finite() && field_y.is_finite()) {
+        return Err(MagnetError::Calculation(format!(
+            "field bounds are unbounded at {}",
+            point
+        )));
+    }
+    if field_x.width() > magnet.jr.abs() || field_y.width() > magnet.jr.abs() {
+        return Err(MagnetError::Calculation(format!(
+            "field bounds straddle a branch cut at {}",
+            point
+        )));
+    }
+
+    let global_x = cos * field_x - sin * field_y;
+    let global_y = sin * field_x + cos * field_y;
+    Ok((
+        Point2::new(global_x.lo, global_y.lo),
+        Point2::new(global_x.hi, global_y.hi),
+    ))
+}
+
+/// Largest relative error of [`get_field_thin_film`] accepted by
+/// [`thin_film_validity`]
+pub const THIN_FILM_TOLERANCE: f64 = 1e-2;
+
+/// Above this modulus `ln_1p_ratio` falls back to the direct logarithms
+const LN_1P_CUTOFF: f64 = 0.5;
+
+/// Returns the same field as [`get_field_rectangle`], using expressions
+/// rearranged to avoid cancellation.
+///
+/// The direct expressions lose precision wherever the ratios in the
+/// logarithms approach one, or the pairs of angles nearly cancel: far from
+/// the magnet relative to its size (about `log10(r/a)` digits at a distance
+/// `r`), and close to the faces of thin magnets. Here
+/// - differences of logarithms are combined into a single `ln_1p` of an
+///   exactly simplified numerator, e.g.
+///   $`(x-a)^2 - (x+a)^2 = -4ax`$,
+/// - outside the magnet, pairs of `atan2` are combined into a single angle
+///   with an exactly simplified numerator,
+/// - differences of squares are factorised, $`x^2 - a^2 = (x-a)(x+a)`$,
+///
+/// which retains close to full double precision at all distances.
+pub fn get_field_rectangle_stable(
+    magnet: &Rectangle,
+    point: &Point2,
+) -> Result<Point2, Box<dyn Error>> {
+    let (x, y, a, b) = (point.x, point.y, magnet.a, magnet.b);
+    let inside = x.abs() < a && y.abs() < b;
+    let mut field = Point2::zero();
+
+    if (magnet.jx / magnet.jr).abs() > FP_CUTOFF {
+        let x_sq_minus_a_sq = (x - a) * (x + a);
+        let (top_1, bottom_1) = (2.0 * a * (b + y), x_sq_minus_a_sq + (b + y).powi(2));
+        let (top_2, bottom_2) = (2.0 * a * (b - y), x_sq_minus_a_sq + (b - y).powi(2));
+        let angle = if inside {
+            top_1.atan2(bottom_1) + top_2.atan2(bottom_2)
+        } else {
+            let numerator = 4.0 * a * b * (x_sq_minus_a_sq + (b - y) * (b + y));
+            numerator.atan2(bottom_1 * bottom_2 - top_1 * top_2)
+        };
+        field.x += magnet.jx * I_2PI * angle;
+
+        let d = (y - b).powi(2);
+        let e = (y + b).powi(2);
+        let log_ratio = ln_1p_ratio(
+            -16.0 * a * b * x * y,
+            ((x - a).powi(2) + d, (x + a).powi(2) + d),
+            ((x - a).powi(2) + e, (x + a).powi(2) + e),
+        );
+        field.y -= magnet.jx * I_4PI * log_ratio;
+    }
+
+    if (magnet.jy / magnet.jr).abs() > FP_CUTOFF {
+        let y_sq_minus_b_sq = (y - b) * (y + b);
+        let (top_1, bottom_1) = (2.0 * b * (x + a), (x + a).powi(2) + y_sq_minus_b_sq);
+        let (top_2, bottom_2) = (2.0 * b * (x - a), (x - a).powi(2) + y_sq_minus_b_sq);
+        let angle = if inside {
+            top_1.atan2(bottom_1) - top_2.atan2(bottom_2)
+        } else {
+            let numerator = 4.0 * a * b * (y_sq_minus_b_sq - (x - a) * (x + a));
+            numerator.atan2(bottom_1 * bottom_2 + top_1 * top_2)
+        };
+        field.y += magnet.jy * I_2PI * angle;
+
+        let c = (x + a).powi(2);
+        let a_sq = (x - a).powi(2);
+        let log_ratio = ln_1p_ratio(
+            16.0 * a * b * x * y,
+            (c + (y - b).powi(2), c + (y + b).powi(2)),
+            (a_sq + (y - b).powi(2), a_sq + (y + b).powi(2)),
+        );
+        field.x += magnet.jy * I_4PI * log_ratio;
+    }
+
+    Ok(field)
+}
+
+/// Returns the field of `magnet` at `point`, both in the global frame, from
+/// the complex potential of the charge on its boundary.
+///
+/// Each face carries a surface charge $`\sigma = \mathbf{J} \cdot
+/// \mathbf{\hat{n}}`$, and integrating the Cauchy kernel along the face
+/// gives the conjugate field in closed form,
+/// ```math
+/// B_x - i B_y = \sum_{\mathrm{faces}} \frac{\sigma}{2 \pi}
+/// \frac{|\Delta|}{\Delta} \ln \left( \frac{z - z_1}{z - z_2} \right)
+/// ```
+/// in the local frame, with $`\mathbf{J}`$ added inside the magnet. This
+/// shares no expressions with the real-valued formulae of
+/// [`get_field_rectangle`], so serves as an independent cross-check.
+pub fn get_field_rectangle_complex_potential(
+    magnet: &Rectangle,
+    point: Point2,
+) -> Result<Point2, MagnetError> {
+    let alpha = magnet.alpha * PI / 180.;
+    let local = (point - magnet.center).rotate(-alpha);
+    if !(local.x.is_finite() && local.y.is_finite()) {
+        return Err(MagnetError::InvalidParameter(format!(
+            "point {} is not finite",
+            point
+        )));
+    }
+
+    let (a, b) = (magnet.a, magnet.b);
+    let corners = [
+        Point2::new(a, -b),
+        Point2::new(a, b),
+        Point2::new(-a, b),
+        Point2::new(-a, -b),
+    ];
+    let normals = [
+        Point2::new(1.0, 0.0),
+        Point2::new(0.0, 1.0),
+        Point2::new(-1.0, 0.0),
+        Point2::new(0.0, -1.0),
+    ];
+    let j = Point2::new(magnet.jx, magnet.jy);
+
+    let mut field = Point2::zero();
+    for (k, normal) in normals.iter().enumerate() {
+        let sigma = j.x * normal.x + j.y * normal.y;
+        if sigma != 0.0 {
+            let (start, end) = (corners[k], corners[(k + 1) % 4]);
+            field += charged_segment_field(sigma, &start, &end, &local);
+        }
+    }
+
+    if !(field.x.is_finite() && field.y.is_finite()) {
+        return Err(MagnetError::Calculation(format!(
+            "field is singular at the corner {}",
+            point
+        )));
+    }
+
+    if local.x.abs() < a && local.y.abs() < b {
+        field += j;
+    }
+
+    Ok(field.rotate(alpha))
+}
+
+/// Returns the position of each element of the surface charge of `magnet`,
+/// with each face divided into `n_per_edge` elements, and its contribution
+/// to the field at `point`, all in the global frame.
+///
+/// Each element is a line charge $`\lambda = \sigma \, ds`$ at its midpoint,
+/// with $`\sigma = \mathbf{J} \cdot \mathbf{\hat{n}}`$, giving
+/// $`\lambda \mathbf{r} / (2 \pi r^2)`$ with $`\mathbf{r}`$ from the element
+/// to the point. Faces parallel to the magnetisation carry no charge and
+/// give no elements. The sum of the contributions converges to
+/// $`\mu_0 \mathbf{H}`$, which is [`get_field_rectangle`] outside the magnet,
+/// as the square of the element size away from the surface; inside, the
+/// magnetisation must be added.
+pub fn charge_field_contribution(
+    magnet: &Rectangle,
+    point: Point2,
+    n_per_edge: usize,
+) -> Result<Vec<(Point2, Point2)>, MagnetError> {
+    if n_per_edge == 0 {
+        return Err(MagnetError::InvalidParameter(
+            "each edge needs at least 1 element".to_string(),
+        ));
+    }
+
+    let alpha = magnet.alpha * PI / 180.;
+    let local = (point - magnet.center).rotate(-alpha);
+    let (a, b) = (magnet.a, magnet.b);
+    let corners = [
+        Point2::new(a, -b),
+        Point2::new(a, b),
+        Point2::new(-a, b),
+        Point2::new(-a, -b),
+    ];
+    let normals = [
+        Point2::new(1.0, 0.0),
+        Point2::new(0.0, 1.0),
+        Point2::new(-1.0, 0.0),
+        Point2::new(0.0, -1.0),
+    ];
+    let j = Point2::new(magnet.jx, magnet.jy);
+
+    let mut contributions = Vec::with_capacity(4 * n_per_edge);
+    for (k, normal) in normals.iter().enumerate() {
+        let sigma = j.x * normal.x + j.y * normal.y;
+        if sigma.abs() <= FP_CUTOFF * magnet.jr.abs() {
+            continue;
+        }
+
+        let (start, end) = (corners[k], corners[(k + 1) % 4]);
+        let step = (end - start).scale(1.0 / n_per_edge as f64);
+        let charge = sigma * step.magnitude();
+        for i in 0..n_per_edge {
+            let position = start + step.scale(i as f64 + 0.5);
+            let r = local - position;
+            let r_sq = r.x * r.x + r.y * r.y;
+            if r_sq == 0.0 {
+                return Err(MagnetError::Calculation(format!(
+                    "point {} lies on a charge element",
+                    point
+                )));
+            }
+            contributions.push((
+                magnet.center + position.rotate(alpha),
+                r.scale(charge * I_2PI / r_sq).rotate(alpha),
+            ));
+        }
+    }
+
+    Ok(contributions)
+}
+
+/// Returns the field of `magnet` at `point`, both in the global frame, in
+/// the thin-film approximation, which collapses the magnet onto its
+/// mid-line.
+///
+/// For a film of thickness `t` along its shorter side, with ends $`z_1`$ and
+/// $`z_2`$ and $`\Delta = z_2 - z_1`$, the perpendicular magnetisation
+/// becomes a dipole sheet and the parallel one a pair of line charges at the
+/// ends, which together give
+/// ```math
+/// B_x - i B_y = \frac{t}{2 \pi} (J_x + i J_y) \frac{|\Delta|}{\Delta}
+/// \left( \frac{1}{z - z_2} - \frac{1}{z - z_1} \right)
+/// ```
+/// The relative error is about $`t^2 / (12 d^2)`$ at a distance `d` from
+/// the magnet, see [`thin_film_validity`], and the approximation has no
+/// meaning inside the magnet.
+pub fn get_field_thin_film(magnet: &Rectangle, point: Point2) -> Result<Point2, MagnetError> {
+    let alpha = magnet.alpha * PI / 180.;
+    let local = (point - magnet.center).rotate(-alpha);
+    if !(local.x.is_finite() && local.y.is_finite()) {
+        return Err(MagnetError::InvalidParameter(format!(
+            "point {} is not finite",
+            point
+        )));
+    }
+
+    let (thickness, end) = if magnet.a >= magnet.b {
+        (2.0 * magnet.b, Complex::new(magnet.a, 0.0))
+    } else {
+        (2.0 * magnet.a, Complex::new(0.0, magnet.b))
+    };
+    let z = Complex::new(local.x, local.y);
+    let one = Complex::new(1.0, 0.0);
+    let delta = end.scale(2.0);
+
+    let conj_field = Complex::new(magnet.jx, magnet.jy) * Complex::new(delta.norm(), 0.0) / delta
+        * (one / (z - end) - one / (z + end));
+    let conj_field = conj_field.scale(thickness * I_2PI);
+    if !(conj_field.re.is_finite() && conj_field.im.is_finite()) {
+        return Err(MagnetError::Calculation(format!(
+            "thin-film field is singular at the end {}",
+            point
+        )));
+    }
+
+    Ok(Point2::new(conj_field.re, -conj_field.im).rotate(alpha))
+}
+
+/// Returns whether `point`, in the global frame, is far enough from `magnet`
+/// for [`get_field_thin_film`] to be within [`THIN_FILM_TOLERANCE`] of the
+/// exact field, relative to its magnitude.
+///
+/// Replacing a face of width `t` by a line at its centre changes the field
+/// at a distance `d` by the second moment of the charge, a relative amount
+/// of $`t^2 / (12 d^2)`$ to leading order. The point is accepted where
+/// $`t^2 / (10 d^2)`$, which bounds the observed error from outside the
+/// leading order, is below the tolerance, i.e. beyond about `3.2 t` from the
+/// magnet.
+pub fn thin_film_validity(magnet: &Rectangle, point: Point2) -> Result<bool, MagnetError> {
+    let alpha = magnet.alpha * PI / 180.;
+    let local = (point - magnet.center).rotate(-alpha);
+    if !(local.x.is_finite() && local.y.is_finite()) {
+        return Err(MagnetError::InvalidParameter(format!(
+            "point {} is not finite",
+            point
+        )));
+    }
+
+    let thickness = 2.0 * magnet.a.min(magnet.b);
+    let distance = (local.x.abs() - magnet.a)
+        .max(0.0)
+        .hypot((local.y.abs() - magnet.b).max(0.0));
+
+    Ok(thickness.powi(2) < 10.0 * THIN_FILM_TOLERANCE * distance.powi(2))
+}
+
+/// Returns an estimate of the floating point error, in T, of the field of
+/// `magnet` at `point` from [`get_field_rectangle`], both in the global
+/// frame.
+///
+/// Rounding the coordinate differences to the corners perturbs the
+/// arguments of each logarithm and angle by a relative amount of order
+/// $`\epsilon L / r_k`$, with $`L`$ the largest coordinate and $`r_k`$ the
+/// distance to corner $`k`$, while each term is itself rounded relative to
+/// its size. Summing over the corners,
+/// ```math
+/// \delta B \approx \epsilon |\mathbf{J}| \sum_k \left[
+/// \frac{|\ln r_k^2| + 2 L/r_k}{4\pi} + \frac{\pi + L/r_k}{2\pi} \right]
+/// ```
+/// which is a bound of the right order rather than a sharp one. It grows as
+/// $`1/r_k`$ towards a corner, and is infinite on one.
+pub fn field_error_estimate(magnet: &Rectangle, point: Point2) -> Result<f64, MagnetError> {
+    let alpha = magnet.alpha * PI / 180.;
+    let local = (point - magnet.center).rotate(-alpha);
+    let (x, y, a, b) = (local.x, local.y, magnet.a, magnet.b);
+    if !(x.is_finite() && y.is_finite()) {
+        return Err(MagnetError::InvalidParameter(format!(
+            "point {} is not finite",
+            point
+        )));
+    }
+
+    let scale = x.abs().max(y.abs()).max(a).max(b);
+    let mut sum = 0.0;
+    for (x_k, y_k) in &[
+        (x - a, y - b),
+        (x - a, y + b),
+        (x + a, y - b),
+        (x + a, y + b),
+    ] {
+        let r_k = x_k.hypot(*y_k);
+        if r_k == 0.0 {
+            return Ok(f64::INFINITY);
+        }
+        sum += (r_k.powi(2).ln().abs() + 2.0 * scale / r_k) * I_4PI;
+        sum += (PI + scale / r_k) * I_2PI;
+    }
+
+    Ok(f64::EPSILON * magnet.jr.abs() * sum)
+}
+
+/// Returns $`\ln(t_1/b_1) - \ln(t_2/b_2)`$ for `first = (t_1, b_1)` and
+/// `second = (t_2, b_2)`, where `numerator` is the exact value of
+/// $`t_1 b_2 - b_1 t_2`$.
+fn ln_1p_ratio(numerator: f64, first: (f64, f64), second: (f64, f64)) -> f64 {
+    let u = numerator / (first.1 * second.0);
+    if u.abs() < LN_1P_CUTOFF {
+        u.ln_1p()
+    } else {
+        (first.0 / first.1).ln() - (second.0 / second.1).ln()
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::magnets::magnet2d::rectangle_field::get_field_rectangle;
-    use crate::magnets::magnet2d::Rectangle;
+    use crate::magnets::magnet2d::rectangle_field::{
+        center_field_rectangle, charge_field_contribution, field_error_estimate,
+        get_field_rectangle, get_field_rectangle_complex_potential, get_field_rectangle_handed,
+        get_field_rectangle_stable, get_field_thin_film, thin_film_validity, Handedness,
+        THIN_FILM_TOLERANCE,
+    };
+    use crate::magnets::magnet2d::{Magnet2D, Rectangle};
     use crate::utils::comparison::nearly_equal;
-    use crate::utils::points2::Point2;
+    use crate::utils::points::Points;
+    use crate::utils::points2::{Point2, Points2};
+    use crate::PI;
+
+    #[test]
+    fn stable_matches_direct_near_magnet() {
+        for theta in &[0.0, 30.0, 90.0] {
+            let magnet = Rectangle::new(1.0, 2.0, (0.0, 0.0), 0.0, 1.0, *theta);
+            for point in &[
+                Point2::new(0.1, 0.2),
+                Point2::new(0.7, -0.4),
+                Point2::new(-0.2, 1.3),
+                Point2::new(2.0, 3.0),
+            ] {
+                let direct = get_field_rectangle(&magnet, point).unwrap();
+                let stable = get_field_rectangle_stable(&magnet, point).unwrap();
+                assert!((direct - stable).magnitude() < 1e-14);
+            }
+        }
+    }
+
+    #[test]
+    fn stable_far_field_precision() {
+        // Reference values computed with 50 digit arithmetic
+        let magnet_x = Rectangle::new(1.0, 1.0, (0.0, 0.0), 0.0, 1.0, 0.0);
+        let magnet_y = Rectangle::new(1.0, 1.0, (0.0, 0.0), 0.0, 1.0, 90.0);
+        let point = Point2::new(1e6, 2e6);
+        let expected_xx = -1.909_859_317_102_744e-14;
+        let expected_yx = 2.546_479_089_470_325_5e-14;
+
+        let field_x = get_field_rectangle_stable(&magnet_x, &point).unwrap();
+        let field_y = get_field_rectangle_stable(&magnet_y, &point).unwrap();
+        assert!(((field_x.x - expected_xx) / expected_xx).abs() < 1e-12);
+        assert!(((field_x.y - expected_yx) / expected_yx).abs() < 1e-12);
+        assert!(((field_y.x - expected_yx) / expected_yx).abs() < 1e-12);
+        assert!(((field_y.y + expected_xx) / expected_xx).abs() < 1e-12);
+
+        // The direct expressions lose most of their digits in the logarithms
+        let direct = get_field_rectangle(&magnet_x, &point).unwrap();
+        assert!(((direct.y - expected_yx) / expected_yx).abs() > 1e-8);
+    }
+
+    #[test]
+    fn stable_thin_film_precision() {
+        // Just above the face of a 2 um thick film, 50 digit reference values
+        let magnet = Rectangle::new(1.0, 2e-6, (0.0, 0.0), 0.0, 1.0, 90.0);
+        let point = Point2::new(0.2, 2e-6);
+        let expected = Point2::new(5.774_329_000_686_78e-12, 1.515_761_362_724_847e-6);
+
+        let stable = get_field_rectangle_stable(&magnet, &point).unwrap();
+        assert!(((stable.x - expected.x) / expected.x).abs() < 1e-12);
+        assert!(((stable.y - expected.y) / expected.y).abs() < 1e-12);
+
+        let direct = get_field_rectangle(&magnet, &point).unwrap();
+        assert!(((direct.x - expected.x) / expected.x).abs() > 1e-7);
+    }
+
+    #[test]
+    fn complex_potential_matches_direct() {
+        for theta in &[0.0, 30.0, 90.0, 200.0] {
+            let magnet = Rectangle::new(1.0, 2.0, (0.0, 0.0), 0.0, 1.2, *theta);
+            for point in &[
+                Point2::new(0.8, 0.3),
+                Point2::new(-0.6, -1.5),
+                Point2::new(0.2, 1.1),
+                Point2::new(3.0, -4.0),
+                Point2::new(0.1, 0.2),
+            ] {
+                let direct = get_field_rectangle(&magnet, point).unwrap();
+                let complex = get_field_rectangle_complex_potential(&magnet, *point).unwrap();
+                assert!((direct - complex).magnitude() < 1e-12);
+            }
+        }
+
+        // Points in the global frame, as for Magnet2D::get_field
+        let magnet = Rectangle::new(1.0, 2.0, (0.5, -1.0), 35.0, 1.2, 60.0);
+        for point in &[Point2::new(0.8, 0.3), Point2::new(0.5, -1.2)] {
+            let direct = magnet.get_field(point).unwrap();
+            let complex = get_field_rectangle_complex_potential(&magnet, *point).unwrap();
+            assert!((direct - complex).magnitude() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn complex_potential_singular_at_corner() {
+        let magnet = Rectangle::new(1.0, 2.0, (1.0, 1.0), 0.0, 1.0, 90.0);
+        let corner = magnet.center + Point2::new(magnet.a, magnet.b);
+        assert!(get_field_rectangle_complex_potential(&magnet, corner).is_err());
+    }
+
+    #[test]
+    fn thin_film_within_tolerance_where_valid() {
+        for theta in &[0.0, 30.0, 90.0] {
+            let magnet = Rectangle::new(0.02, 1.0, (0.3, -0.2), 25.0, 1.0, *theta);
+            let mut n_valid = 0;
+            for i in 0..60 {
+                for j in 0..60 {
+                    let point = Point2::new(-0.4 + 0.02 * i as f64, -0.9 + 0.03 * j as f64);
+                    if thin_film_validity(&magnet, point).unwrap() {
+                        n_valid += 1;
+                        let exact = magnet.get_field(&point).unwrap();
+                        let thin = get_field_thin_film(&magnet, point).unwrap();
+                        assert!(
+                            (thin - exact).magnitude() < THIN_FILM_TOLERANCE * exact.magnitude()
+                        );
+                    }
+                }
+            }
+            assert!(n_valid > 3000);
+        }
+    }
+
+    #[test]
+    fn thin_film_invalid_near_magnet() {
+        let magnet = Rectangle::new(1.0, 0.01, (0.0, 0.0), 0.0, 1.0, 90.0);
+        for point in &[
+            Point2::new(0.0, 0.0),
+            Point2::new(0.1, 0.006),
+            Point2::new(0.3, -0.02),
+            Point2::new(0.52, 0.0),
+        ] {
+            assert!(!thin_film_validity(&magnet, *point).unwrap());
+        }
+        assert!(thin_film_validity(&magnet, Point2::new(0.1, 0.05)).unwrap());
+        assert!(thin_film_validity(&magnet, Point2::new(f64::NAN, 0.0)).is_err());
+    }
 
     #[test]
     fn symmetry_field_in_y() {
@@ -181,4 +848,164 @@ mod tests {
             && nearly_equal(field.y, 0.5 / 2.0_f64.sqrt());
         assert!(result);
     }
+
+    #[test]
+    fn error_estimate_grows_towards_corner() {
+        let magnet = Rectangle::new(1.0, 1.0, (0.2, 0.1), 30.0, 1.0, 45.0);
+        let corner = magnet.center + Point2::new(0.5, 0.5).rotate(30.0 * PI / 180.);
+        let direction = Point2::new(1.0, 2.0).unit();
+
+        let estimates: Vec<f64> = [1e-1, 1e-4, 1e-8]
+            .iter()
+            .map(|d| field_error_estimate(&magnet, corner + direction.scale(*d)).unwrap())
+            .collect();
+        assert!(estimates[0] < 1e-14);
+        assert!(estimates[0] < estimates[1] && estimates[1] < estimates[2]);
+        assert!(estimates[2] > 1e-9);
+    }
+
+    #[cfg(feature = "interval")]
+    #[test]
+    fn interval_bounds_contain_direct_field() {
+        use crate::magnets::magnet2d::rectangle_field::get_field_rectangle_interval;
+
+        for theta in &[0.0, 30.0, 90.0, 225.0] {
+            let magnet = Rectangle::new(1.0, 2.0, (0.0, 0.0), 0.0, 1.2, *theta);
+            for point in &[
+                Point2::new(0.1, 0.2),
+                Point2::new(0.7, -0.4),
+                Point2::new(-0.2, 1.3),
+                Point2::new(2.0, 3.0),
+                Point2::new(-40.0, 15.0),
+            ] {
+                let field = get_field_rectangle(&magnet, point).unwrap();
+                let (lower, upper) = get_field_rectangle_interval(&magnet, *point).unwrap();
+                assert!(lower.x <= field.x && field.x <= upper.x);
+                assert!(lower.y <= field.y && field.y <= upper.y);
+                assert!((upper - lower).magnitude() < 1e-12);
+            }
+        }
+
+        // A moved and rotated magnet takes global points
+        let magnet = Rectangle::new(1.0, 2.0, (0.5, -0.3), 30.0, 1.2, 60.0);
+        for point in &[Point2::new(1.6, 0.4), Point2::new(-2.0, 3.0)] {
+            let field = magnet.get_field(point).unwrap();
+            let (lower, upper) = get_field_rectangle_interval(&magnet, *point).unwrap();
+            assert!(lower.x <= field.x && field.x <= upper.x);
+            assert!(lower.y <= field.y && field.y <= upper.y);
+            assert!((upper - lower).magnitude() < 1e-12);
+        }
+
+        let magnet = Rectangle::new(1.0, 2.0, (1.0, 2.0), 0.0, 1.0, 45.0);
+        let corner = magnet.center + Point2::new(magnet.a, magnet.b);
+        assert!(get_field_rectangle_interval(&magnet, corner).is_err());
+    }
+
+    #[cfg(feature = "lut")]
+    #[test]
+    fn fast_matches_exact_on_grid() {
+        use crate::magnets::magnet2d::rectangle_field::get_field_rectangle_fast;
+
+        for theta in &[0.0, 35.0, 90.0] {
+            let magnet = Rectangle::new(1.0, 0.6, (0.0, 0.0), 0.0, 1.3, *theta);
+            for i in 0..41 {
+                for j in 0..41 {
+                    let point = Point2::new(-2.0 + 0.1 * i as f64 + 0.013, -2.0 + 0.1 * j as f64);
+                    let exact = get_field_rectangle(&magnet, &point).unwrap();
+                    let fast = get_field_rectangle_fast(&magnet, &point).unwrap();
+                    assert!((fast - exact).magnitude() < 3e-7 * magnet.jr);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn exact_field_at_center() {
+        let square = Rectangle::new(1.0, 1.0, (0.0, 0.0), 0.0, 1.2, 30.0);
+        let field = get_field_rectangle(&square, &Point2::zero()).unwrap();
+        assert!(nearly_equal(field.x, 0.5 * square.jx) && nearly_equal(field.y, 0.5 * square.jy));
+
+        // The centre value is the limit of the general formula nearby
+        for (width, height) in &[(2.0, 0.5), (0.1, 3.0), (1e-3, 1.0), (1e3, 1.0)] {
+            for theta in &[0.0, 45.0, 90.0] {
+                let magnet = Rectangle::new(*width, *height, (0.0, 0.0), 0.0, 1.0, *theta);
+                let center = get_field_rectangle(&magnet, &Point2::zero()).unwrap();
+                assert_eq!(center_field_rectangle(&magnet), center);
+                assert!(center.x.is_finite() && center.y.is_finite());
+
+                let offset = Point2::new(1e-9 * width, -1e-9 * height);
+                let nearby = get_field_rectangle(&magnet, &offset).unwrap();
+                assert!((center - nearby).magnitude() < 1e-8);
+            }
+        }
+    }
+
+    #[test]
+    fn charge_contributions_converge_to_field() {
+        let magnet = Rectangle::new(1.0, 0.5, (0.0, 0.0), 0.0, 1.0, 60.0);
+        let point = Point2::new(0.7, 0.6);
+        let expected = get_field_rectangle(&magnet, &point).unwrap();
+
+        let errors: Vec<f64> = [4, 16, 64, 256]
+            .iter()
+            .map(|n| {
+                let contributions = charge_field_contribution(&magnet, point, *n).unwrap();
+                assert_eq!(4 * n, contributions.len());
+                let sum = contributions
+                    .iter()
+                    .fold(Point2::zero(), |sum, (_, b)| sum + *b);
+                (sum - expected).magnitude()
+            })
+            .collect();
+        for pair in errors.windows(2) {
+            assert!(pair[1] < pair[0]);
+        }
+        assert!(errors[3] < 1e-4 * expected.magnitude());
+
+        // Magnetised along x, the top and bottom faces carry no charge
+        let along_x = Rectangle::new(1.0, 0.5, (0.0, 0.0), 0.0, 1.0, 0.0);
+        assert_eq!(
+            8,
+            charge_field_contribution(&along_x, point, 4).unwrap().len()
+        );
+
+        // Positions and contributions are in the global frame
+        let moved = Rectangle::new(1.0, 0.5, (2.0, -1.0), 40.0, 1.0, 60.0);
+        let point = Point2::new(2.5, 0.2);
+        let contributions = charge_field_contribution(&moved, point, 256).unwrap();
+        let sum = contributions
+            .iter()
+            .fold(Point2::zero(), |sum, (_, b)| sum + *b);
+        let expected = moved.get_field(&point).unwrap();
+        assert!((sum - expected).magnitude() < 1e-4 * expected.magnitude());
+        for (position, _) in &contributions {
+            let local = (*position - moved.center).rotate(-40.0 * PI / 180.);
+            let on_x_face = (local.x.abs() - moved.a).abs() < 1e-12;
+            let on_y_face = (local.y.abs() - moved.b).abs() < 1e-12;
+            assert!(on_x_face || on_y_face);
+        }
+    }
+
+    #[test]
+    fn left_handed_field_is_mirrored() {
+        let magnet = Rectangle::new(1.0, 0.5, (0.0, 0.0), 0.0, 1.0, 60.0);
+        for point in &[Point2::new(0.7, 0.6), Point2::new(-1.2, -0.3)] {
+            let right =
+                get_field_rectangle_handed(&magnet, point, Handedness::RightHanded).unwrap();
+            assert_eq!(get_field_rectangle(&magnet, point).unwrap(), right);
+
+            let flipped = Point2::new(point.x, -point.y);
+            let left =
+                get_field_rectangle_handed(&magnet, &flipped, Handedness::LeftHanded).unwrap();
+            assert_eq!(Point2::new(right.x, -right.y), left);
+        }
+
+        // Magnetised along x the magnet is symmetric under y -> -y, so both
+        // conventions give the same components
+        let along_x = Rectangle::new(1.0, 0.5, (0.0, 0.0), 0.0, 1.0, 0.0);
+        let point = Point2::new(0.7, 0.6);
+        let right = get_field_rectangle_handed(&along_x, &point, Handedness::RightHanded).unwrap();
+        let left = get_field_rectangle_handed(&along_x, &point, Handedness::LeftHanded).unwrap();
+        assert!((right - left).magnitude() < 1e-12);
+    }
 }
@@ -0,0 +1,113 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/.
+Copyright 2021 Peter Dunne */
+
+//! Precomputed field responses of fixed magnet geometries
+//!
+//! The field is linear in the magnetisation, so for a fixed geometry it can
+//! be tabulated once and re-evaluated cheaply for new magnetisations.
+
+use crate::errors::MagnetError;
+use crate::magnets::magnet2d::{Magnet2D, Rectangle};
+use crate::utils::points2::{Point2, Points2};
+
+/// The field per unit magnetisation of a set of magnets at a set of points
+///
+/// For each magnet and point the cache holds the 2x2 matrix
+/// $`R_{ij} = \partial B_i / \partial J_j`$, with both the field and the
+/// magnetisation in the global frame, so that the total field is
+/// $`\mathbf{B} = \sum_m R_m \mathbf{J}_m`$.
+///
+/// # Example
+/// ```rust
+/// use magnet_rs::magnets::magnet2d::response::FieldResponse;
+/// use magnet_rs::magnets::magnet2d::Rectangle;
+/// use magnet_rs::utils::points2::Point2;
+/// let geometry = [Rectangle::default()];
+/// let points = [Point2::new(0.0, 1.0), Point2::new(1.0, 1.0)];
+/// let response = FieldResponse::new(&geometry, &points).unwrap();
+/// let field = response.field(&[Point2::new(0.0, 1.2)]).unwrap();
+/// ```
+#[derive(Clone, Debug)]
+pub struct FieldResponse {
+    pub points: Vec<Point2>,
+    /// Response matrices `[[dBx/dJx, dBx/dJy], [dBy/dJx, dBy/dJy]]`,
+    /// indexed by magnet then point
+    pub matrices: Vec<Vec<[[f64; 2]; 2]>>,
+}
+
+impl FieldResponse {
+    /// Tabulates the response of each magnet of `geometry` at `points`. The
+    /// magnetisations of the magnets are ignored.
+    pub fn new(geometry: &[Rectangle], points: &[Point2]) -> Result<FieldResponse, MagnetError> {
+        let mut matrices = Vec::with_capacity(geometry.len());
+        for magnet in geometry {
+            let unit_x = magnet.with_magnetisation(Point2::i_hat());
+            let unit_y = magnet.with_magnetisation(Point2::j_hat());
+            let magnet_matrices = points
+                .iter()
+                .map(|point| {
+                    let b_x = unit_x.get_field(point)?;
+                    let b_y = unit_y.get_field(point)?;
+                    Ok([[b_x.x, b_y.x], [b_x.y, b_y.y]])
+                })
+                .collect::<Result<Vec<_>, MagnetError>>()?;
+            matrices.push(magnet_matrices);
+        }
+
+        Ok(FieldResponse {
+            points: points.to_vec(),
+            matrices,
+        })
+    }
+
+    /// Returns the total field at the tabulated points for the global
+    /// magnetisation vectors `magnetisations`, one per magnet
+    pub fn field(&self, magnetisations: &[Point2]) -> Result<Vec<Point2>, MagnetError> {
+        if magnetisations.len() != self.matrices.len() {
+            return Err(MagnetError::InvalidParameter(format!(
+                "{} magnetisations given for {} magnets",
+                magnetisations.len(),
+                self.matrices.len()
+            )));
+        }
+
+        let mut field = vec![Point2::zero(); self.points.len()];
+        for (magnet_matrices, j) in self.matrices.iter().zip(magnetisations) {
+            for (b, r) in field.iter_mut().zip(magnet_matrices) {
+                *b += Point2::new(r[0][0] * j.x + r[0][1] * j.y, r[1][0] * j.x + r[1][1] * j.y);
+            }
+        }
+        Ok(field)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::magnets::magnet2d::response::FieldResponse;
+    use crate::magnets::magnet2d::{Magnet2D, Rectangle};
+    use crate::utils::points2::{Point2, Points2};
+
+    #[test]
+    fn cached_response_matches_direct_field() {
+        let geometry = [
+            Rectangle::new(1.0, 0.5, (-1.0, 0.0), 25.0, 1.0, 90.0),
+            Rectangle::new(0.5, 1.0, (1.0, 0.3), -10.0, 1.0, 0.0),
+        ];
+        let points: Vec<Point2> = (0..5).map(|i| Point2::new(-2.0 + i as f64, 1.5)).collect();
+        let response = FieldResponse::new(&geometry, &points).unwrap();
+
+        let magnetisations = [Point2::new(0.4, -1.1), Point2::new(-0.8, 0.2)];
+        let cached = response.field(&magnetisations).unwrap();
+        for (point, b) in points.iter().zip(&cached) {
+            let direct = geometry
+                .iter()
+                .zip(&magnetisations)
+                .map(|(m, j)| m.with_magnetisation(*j).get_field(point).unwrap())
+                .fold(Point2::zero(), |a, b| a + b);
+            assert!((direct - *b).magnitude() < 1e-14);
+        }
+        assert!(response.field(&magnetisations[..1]).is_err());
+    }
+}
@@ -0,0 +1,243 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/.
+Copyright 2021 Peter Dunne */
+
+//! Rectangular magnets with rounded corners
+//!
+//! The field of a sharp rectangle diverges logarithmically at its corners.
+//! Real magnets are rounded or chamfered there, which bounds the field. The
+//! charge on a rounded corner is that of a quarter circle arc, approximated
+//! by `ARC_SEGMENTS` chords.
+
+use crate::errors::MagnetError;
+use crate::magnets::magnet2d::line_field::charged_segment_field;
+use crate::magnets::magnet2d::{Magnet2D, Rectangle};
+use crate::magnets::Magnet;
+use crate::utils::points::Points;
+use crate::utils::points2::{Point2, Points2};
+use crate::PI;
+use std::error::Error;
+use std::fmt;
+
+/// Number of chords approximating each rounded corner
+pub const ARC_SEGMENTS: usize = 32;
+
+/// A rectangle whose corners are rounded with radius `rounded`
+///
+/// The magnet is the rectangle `magnet` with a quarter circle of radius
+/// `rounded` in place of each corner, `0 <= rounded <= min(a, b)`, and zero
+/// giving the sharp rectangle. The field is that of the surface charge
+/// $`\sigma = \mathbf{J} \cdot \mathbf{\hat{n}}`$ on the straight faces and on
+/// the arcs, each arc divided into [`ARC_SEGMENTS`] chords carrying the
+/// charge of their normal. The chords shift the boundary by at most
+/// $`r (1 - \cos(\pi / 4N))`$, about `3e-4 r` for `N = ARC_SEGMENTS`, so the
+/// field is accurate up to that distance from the corners.
+///
+/// # Example
+/// ```rust
+/// use magnet_rs::magnets::magnet2d::rounded::RoundedRectangle;
+/// use magnet_rs::magnets::magnet2d::{Magnet2D, Rectangle};
+/// use magnet_rs::utils::points2::Point2;
+/// let sharp = Rectangle::new(1.0, 1.0, (0.0, 0.0), 0.0, 1.0, 90.0);
+/// let magnet = RoundedRectangle::new(sharp, 0.1).unwrap();
+/// let field = magnet.get_field(&Point2::new(0.5, 0.51)).unwrap();
+/// ```
+#[derive(Copy, Clone)]
+pub struct RoundedRectangle {
+    pub magnet: Rectangle,
+    pub rounded: f64,
+}
+
+impl RoundedRectangle {
+    pub fn new(magnet: Rectangle, rounded: f64) -> Result<RoundedRectangle, MagnetError> {
+        let max_radius = magnet.a.min(magnet.b);
+        if rounded.is_nan() || rounded < 0.0 || rounded > max_radius {
+            return Err(MagnetError::InvalidParameter(format!(
+                "corner radius must be between 0 and {}, got {}",
+                max_radius, rounded
+            )));
+        }
+        Ok(RoundedRectangle { magnet, rounded })
+    }
+
+    /// Returns the vertices, in the local frame and anticlockwise, of the
+    /// polygon approximating the boundary, starting at the bottom of the
+    /// lower right corner
+    fn boundary(&self) -> Vec<Point2> {
+        let (a, b, r) = (self.magnet.a, self.magnet.b, self.rounded);
+        let centres = [
+            Point2::new(a - r, -(b - r)),
+            Point2::new(a - r, b - r),
+            Point2::new(-(a - r), b - r),
+            Point2::new(-(a - r), -(b - r)),
+        ];
+
+        let mut vertices = Vec::with_capacity(4 * (ARC_SEGMENTS + 1));
+        for (q, centre) in centres.iter().enumerate() {
+            let start = (q as f64 - 1.0) * PI / 2.0;
+            for m in 0..=ARC_SEGMENTS {
+                let phi = start + PI / 2.0 * m as f64 / ARC_SEGMENTS as f64;
+                vertices.push(*centre + Point2::new(phi.cos(), phi.sin()).scale(r));
+            }
+        }
+        vertices
+    }
+
+    /// Returns true if `point`, in the local frame, lies in the magnet
+    pub fn contains_local(&self, point: &Point2) -> bool {
+        let (a, b, r) = (self.magnet.a, self.magnet.b, self.rounded);
+        let (x, y) = (point.x.abs(), point.y.abs());
+        if x > a || y > b {
+            return false;
+        }
+        if x > a - r && y > b - r {
+            return (x - (a - r)).hypot(y - (b - r)) <= r;
+        }
+        true
+    }
+}
+
+/// Returns the field of a rounded rectangle at a point in its local frame
+pub fn get_field_rounded_rectangle(
+    magnet: &RoundedRectangle,
+    point: &Point2,
+) -> Result<Point2, MagnetError> {
+    let j = Point2::new(magnet.magnet.jx, magnet.magnet.jy);
+    let vertices = magnet.boundary();
+
+    let mut field = Point2::zero();
+    for (k, start) in vertices.iter().enumerate() {
+        let end = vertices[(k + 1) % vertices.len()];
+        let edge = end - *start;
+        let length = edge.magnitude();
+        if length == 0.0 {
+            // The arcs of a sharp corner collapse to a point
+            continue;
+        }
+        let normal = Point2::new(edge.y, -edge.x).scale(1.0 / length);
+        let sigma = j.dot(&normal);
+        if sigma != 0.0 {
+            field += charged_segment_field(sigma, start, &end, point);
+        }
+    }
+
+    if !(field.x.is_finite() && field.y.is_finite()) {
+        return Err(MagnetError::Calculation(format!(
+            "field is singular at the vertex {}",
+            point
+        )));
+    }
+
+    // The surface charges give mu_0 H, inside the magnet B = mu_0 H + J
+    if magnet.contains_local(point) {
+        field += j;
+    }
+    Ok(field)
+}
+
+/// Implements Display for rounded rectangles.
+impl fmt::Display for RoundedRectangle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[rounded {}\tr: {}]", self.magnet, self.rounded)
+    }
+}
+
+impl Magnet for RoundedRectangle {}
+
+impl Magnet2D<Point2> for RoundedRectangle {
+    fn get_field(&self, point: &Point2) -> Result<Point2, Box<dyn Error>> {
+        let alpha = self.magnet.alpha * PI / 180.;
+        let local_point = (*point - self.magnet.center).rotate(-alpha);
+        let local_field = get_field_rounded_rectangle(self, &local_point)?;
+        Ok(local_field.rotate(alpha))
+    }
+
+    fn get_center(&self) -> Point2 {
+        self.magnet.center
+    }
+
+    fn get_size(&self) -> Point2 {
+        Point2::new(self.magnet.width, self.magnet.height)
+    }
+
+    fn get_j(self) -> Point2 {
+        Point2::new(self.magnet.jx, self.magnet.jy)
+    }
+
+    fn contains(&self, point: &Point2) -> bool {
+        let local_point = (*point - self.magnet.center).rotate(-self.magnet.alpha * PI / 180.);
+        self.contains_local(&local_point)
+    }
+
+    fn validate(&self) -> Result<(), MagnetError> {
+        self.magnet.validate()?;
+        if self.rounded.is_nan()
+            || self.rounded < 0.0
+            || self.rounded > self.magnet.a.min(self.magnet.b)
+        {
+            return Err(MagnetError::InvalidParameter(format!(
+                "corner radius {} does not fit the magnet",
+                self.rounded
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::magnets::magnet2d::rounded::RoundedRectangle;
+    use crate::magnets::magnet2d::{Magnet2D, Rectangle};
+    use crate::utils::points2::{Point2, Points2};
+
+    #[test]
+    fn zero_radius_is_sharp_rectangle() {
+        let sharp = Rectangle::new(1.0, 0.5, (0.2, -0.1), 30.0, 1.2, 60.0);
+        let magnet = RoundedRectangle::new(sharp, 0.0).unwrap();
+        for point in &[
+            Point2::new(1.5, 0.8),
+            Point2::new(0.25, -0.05),
+            Point2::new(-0.4, 0.3),
+        ] {
+            let expected = sharp.get_field(point).unwrap();
+            let field = magnet.get_field(point).unwrap();
+            assert!((field - expected).magnitude() < 1e-10);
+        }
+    }
+
+    #[test]
+    fn rounding_reduces_corner_peak() {
+        // Scanning just above the top face across the corner
+        let sharp = Rectangle::new(1.0, 1.0, (0.0, 0.0), 0.0, 1.0, 90.0);
+        let peak = |magnet: &dyn Magnet2D<Point2>| {
+            (0..=100)
+                .map(|i| {
+                    let point = Point2::new(0.3 + 0.004 * i as f64, 0.505);
+                    magnet.get_field(&point).unwrap().magnitude()
+                })
+                .fold(0.0, f64::max)
+        };
+
+        let sharp_peak = peak(&sharp);
+        let rounded_peak = peak(&RoundedRectangle::new(sharp, 0.1).unwrap());
+        assert!(rounded_peak < 0.95 * sharp_peak);
+
+        // Far away only the small missing volume matters
+        let far = Point2::new(0.0, 10.0);
+        let rounded = RoundedRectangle::new(sharp, 0.1).unwrap();
+        let ratio = rounded.get_field(&far).unwrap().y / sharp.get_field(&far).unwrap().y;
+        let area_ratio = 1.0 - (4.0 - std::f64::consts::PI) * 0.01;
+        assert!((ratio - area_ratio).abs() < 1e-3);
+    }
+
+    #[test]
+    fn corner_radius_must_fit() {
+        let sharp = Rectangle::new(1.0, 0.5, (0.0, 0.0), 0.0, 1.0, 90.0);
+        assert!(RoundedRectangle::new(sharp, 0.3).is_err());
+        assert!(RoundedRectangle::new(sharp, -0.1).is_err());
+        let magnet = RoundedRectangle::new(sharp, 0.25).unwrap();
+        assert!(magnet.contains(&Point2::new(0.0, 0.2)));
+        assert!(!magnet.contains(&Point2::new(0.49, 0.24)));
+    }
+}
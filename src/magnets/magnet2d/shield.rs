@@ -0,0 +1,207 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/.
+Copyright 2021 Peter Dunne */
+
+//! Shielding of 2D magnetic fields
+//!
+//! Simplified models of the field behind conducting and permeable shields.
+
+use crate::errors::MagnetError;
+use crate::magnets::magnet2d::{Magnet2D, Rectangle};
+use crate::utils::linalg::solve;
+use crate::utils::points::Points;
+use crate::utils::points2::{Point2, Points2};
+
+/// Number of cells along the longer side of a shield in
+/// `get_field_with_linear_shield`
+pub const SHIELD_RESOLUTION: usize = 32;
+
+/// Returns the field at `point` of `magnet` behind a conducting shield
+/// filling the half plane beyond `y = shield_plane`, on the far side from the
+/// magnet's center.
+///
+/// This is a simplified quasi-static model of an AC field: on the magnet's
+/// side the field is unchanged, while beyond the plane it is the unshielded
+/// field attenuated as $`e^{-d/\delta}`$, with $`d`$ the depth into the
+/// conductor and $`\delta`$ the `skin_depth` at the frequency of interest.
+/// Reflected fields, the phase lag and the change in field shape are all
+/// neglected, so it only estimates the order of magnitude of the shielding.
+pub fn shielded_field(
+    magnet: &dyn Magnet2D<Point2>,
+    shield_plane: f64,
+    skin_depth: f64,
+    point: Point2,
+) -> Result<Point2, MagnetError> {
+    if !(skin_depth.is_finite() && skin_depth > 0.0) {
+        return Err(MagnetError::InvalidParameter(format!(
+            "skin depth must be positive, got {}",
+            skin_depth
+        )));
+    }
+
+    let field = magnet.get_field(&point)?;
+    let side = (magnet.get_center().y - shield_plane).signum();
+    let depth = side * (shield_plane - point.y);
+    if depth > 0.0 {
+        Ok(field.scale((-depth / skin_depth).exp()))
+    } else {
+        Ok(field)
+    }
+}
+
+/// Returns the field at `point` of `magnet` next to a rectangular shield of
+/// linear material with relative permeability `mu_r`.
+///
+/// The shield is split into cells of uniform induced magnetisation, with
+/// `SHIELD_RESOLUTION` cells along its longer side, and the magnetisations
+/// are solved self-consistently from
+///
+/// ```math
+/// \mathbf{J}_k = \chi \left( \mathbf{B}_{\mathrm{magnet}}(\mathbf{r}_k)
+/// + \sum_l \mu_0 \mathbf{H}_l(\mathbf{r}_k) \right), \quad \chi = \mu_r - 1
+/// ```
+///
+/// at the cell centers $`\mathbf{r}_k`$, where $`\mu_0 \mathbf{H}_l`$ is the
+/// demagnetising field of cell `l`. The magnet is assumed to be unaffected
+/// by the shield, and the shield does not saturate. As `mu_r` grows the
+/// result approaches that of ideal iron. The system is solved afresh on
+/// each call, so each point costs of order `SHIELD_RESOLUTION`⁴ field
+/// evaluations.
+pub fn get_field_with_linear_shield(
+    magnet: &dyn Magnet2D<Point2>,
+    shield: &Rectangle,
+    mu_r: f64,
+    point: Point2,
+) -> Result<Point2, MagnetError> {
+    if !(mu_r.is_finite() && mu_r > 0.0) {
+        return Err(MagnetError::InvalidParameter(format!(
+            "relative permeability must be positive, got {}",
+            mu_r
+        )));
+    }
+
+    let chi = mu_r - 1.0;
+    let source = magnet.get_field(&point)?;
+    if chi == 0.0 {
+        return Ok(source);
+    }
+
+    let cells = shield_cells(shield);
+    let n = cells.len();
+    let centers: Vec<Point2> = cells.iter().map(|cell| cell.center).collect();
+    let units = [Point2::i_hat(), Point2::j_hat()];
+
+    // Dividing through by chi keeps the system well conditioned as
+    // mu_r -> infinity: (I / chi - G) J = B_magnet
+    let mut matrix = vec![vec![0.0; 2 * n]; 2 * n];
+    for (l, cell) in cells.iter().enumerate() {
+        for (c, unit) in units.iter().enumerate() {
+            let unit_cell = cell.with_magnetisation(*unit);
+            for (k, center) in centers.iter().enumerate() {
+                let mut h = unit_cell.get_field(center)?;
+                if k == l {
+                    h = h - *unit;
+                }
+                matrix[2 * k][2 * l + c] = -h.x;
+                matrix[2 * k + 1][2 * l + c] = -h.y;
+            }
+        }
+    }
+    for (i, row) in matrix.iter_mut().enumerate() {
+        row[i] += 1.0 / chi;
+    }
+
+    let mut rhs = Vec::with_capacity(2 * n);
+    for center in &centers {
+        let field = magnet.get_field(center)?;
+        rhs.push(field.x);
+        rhs.push(field.y);
+    }
+
+    let j = solve(&matrix, &rhs).ok_or_else(|| {
+        MagnetError::Calculation("the shield magnetisation system is singular".to_string())
+    })?;
+
+    let mut field = source;
+    for (l, cell) in cells.iter().enumerate() {
+        let induced = cell.with_magnetisation(Point2::new(j[2 * l], j[2 * l + 1]));
+        field += induced.get_field(&point)?;
+    }
+    Ok(field)
+}
+
+/// Splits `shield` into a grid of unmagnetised cells with the shield's
+/// orientation
+fn shield_cells(shield: &Rectangle) -> Vec<Rectangle> {
+    let long = shield.width.max(shield.height);
+    let divisions = |side: f64| ((SHIELD_RESOLUTION as f64 * side / long).round() as usize).max(1);
+    let (nx, ny) = (divisions(shield.width), divisions(shield.height));
+    shield
+        .cells(nx, ny)
+        .iter()
+        .map(|cell| cell.with_magnetisation(Point2::zero()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::magnets::magnet2d::shield::{get_field_with_linear_shield, shielded_field};
+    use crate::magnets::magnet2d::{Magnet2D, Rectangle};
+    use crate::utils::points2::{Point2, Points2};
+
+    #[test]
+    fn thin_skin_depth_attenuates() {
+        let magnet = Rectangle::new(0.01, 0.01, (0.0, 0.0), 0.0, 1.0, 90.0);
+        let behind = Point2::new(0.002, 0.012);
+        let unshielded = magnet.get_field(&behind).unwrap();
+
+        // 2 mm into the shield with a 0.2 mm skin depth gives e^-10
+        let field = shielded_field(&magnet, 0.01, 2e-4, behind).unwrap();
+        assert!(field.magnitude() < 1e-4 * unshielded.magnitude());
+
+        let thick = shielded_field(&magnet, 0.01, 1.0, behind).unwrap();
+        assert!(thick.magnitude() > 0.99 * unshielded.magnitude());
+    }
+
+    #[test]
+    fn magnet_side_is_unchanged() {
+        // The magnet is above the shield, so points above are not shielded
+        let magnet = Rectangle::new(1.0, 1.0, (0.0, 2.0), 0.0, 1.0, 90.0);
+        let point = Point2::new(0.3, 1.0);
+        let field = shielded_field(&magnet, 0.5, 1e-3, point).unwrap();
+        assert_eq!(magnet.get_field(&point).unwrap(), field);
+        assert!(shielded_field(&magnet, 0.5, 0.0, point).is_err());
+    }
+
+    #[test]
+    fn permeable_shield_approaches_ideal_iron() {
+        // A wide slab below y = 0 acts as a half plane of iron, whose image of
+        // the magnet has the reversed tangential magnetisation
+        let magnet = Rectangle::new(0.5, 0.5, (0.0, 0.75), 0.0, 1.0, 60.0);
+        let image = Rectangle::new(0.5, 0.5, (0.0, -0.75), 0.0, 1.0, 120.0);
+        let shield = Rectangle::new(16.0, 2.0, (0.0, -1.0), 0.0, 0.0, 0.0);
+        let point = Point2::new(0.4, 0.9);
+        let ideal = magnet.get_field(&point).unwrap() + image.get_field(&point).unwrap();
+
+        let errors: Vec<f64> = [1.0, 10.0, 1e6]
+            .iter()
+            .map(|mu_r| {
+                let field = get_field_with_linear_shield(&magnet, &shield, *mu_r, point).unwrap();
+                (field - ideal).magnitude()
+            })
+            .collect();
+        assert!(errors[0] > errors[1] && errors[1] > errors[2]);
+        assert!(errors[2] < 0.05 * ideal.magnitude());
+    }
+
+    #[test]
+    fn unit_permeability_has_no_effect() {
+        let magnet = Rectangle::new(1.0, 1.0, (0.0, 2.0), 0.0, 1.0, 90.0);
+        let shield = Rectangle::new(4.0, 1.0, (0.0, 0.0), 0.0, 0.0, 0.0);
+        let point = Point2::new(0.3, 1.0);
+        let field = get_field_with_linear_shield(&magnet, &shield, 1.0, point).unwrap();
+        assert_eq!(magnet.get_field(&point).unwrap(), field);
+        assert!(get_field_with_linear_shield(&magnet, &shield, 0.0, point).is_err());
+    }
+}
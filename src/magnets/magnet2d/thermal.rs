@@ -0,0 +1,184 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/.
+Copyright 2021 Peter Dunne */
+
+//! Magnets in a non-uniform temperature field
+//!
+//! The remanence of a permanent magnet falls reversibly with temperature,
+//! $`J_r(T) = J_r(T_0) (1 + \alpha (T - T_0))`$, with $`\alpha`$ about
+//! -0.0012 /K for NdFeB and -0.0002 /K for ferrite. A magnet in a thermal
+//! gradient is divided into cells, each derated at its own temperature.
+
+use crate::errors::MagnetError;
+use crate::magnets::magnet2d::{CellMagnet2D, Rectangle};
+use crate::magnets::Magnet;
+use crate::utils::points::Points;
+use crate::utils::points2::Point2;
+use std::fmt;
+
+/// Default number of cells along each side of a magnet in a thermal gradient
+pub const THERMAL_RESOLUTION: usize = 16;
+
+/// Temperature, in °C, at which the remanence of the nominal magnet is given
+pub const REFERENCE_TEMPERATURE: f64 = 20.0;
+
+/// Returns the remanence `jr`, given at [`REFERENCE_TEMPERATURE`], derated
+/// to `temperature` with the reversible temperature coefficient
+/// `temp_coefficient` (in 1/K). The linear model is floored at zero.
+pub fn derated_remanence(jr: f64, temp_coefficient: f64, temperature: f64) -> f64 {
+    jr * (1.0 + temp_coefficient * (temperature - REFERENCE_TEMPERATURE)).max(0.0)
+}
+
+/// A rectangular magnet whose remanence is derated by a temperature field
+///
+/// The rectangle is divided into `resolution` x `resolution` cells, each
+/// magnetised along the direction of `magnet` with its remanence derated
+/// with [`derated_remanence`] to the temperature, in °C, at the cell centre.
+/// The temperature closure is evaluated once, on construction, and the
+/// `cells` summed exactly, so a uniform temperature reproduces the field of
+/// the uniformly derated rectangle.
+///
+/// # Example
+/// ```rust
+/// use magnet_rs::magnets::magnet2d::thermal::GradientTempRectangle;
+/// use magnet_rs::magnets::magnet2d::{Magnet2D, Rectangle};
+/// use magnet_rs::utils::points2::Point2;
+/// // NdFeB heated from the right, 20 °C at x = -0.5 to 100 °C at x = 0.5
+/// let nominal = Rectangle::new(1.0, 1.0, (0.0, 0.0), 0.0, 1.3, 90.0);
+/// let magnet = GradientTempRectangle::new(nominal, |p| 60.0 + 80.0 * p.x, -0.0012).unwrap();
+/// let field = magnet.get_field(&Point2::new(0.0, 1.0)).unwrap();
+/// ```
+#[derive(Clone)]
+pub struct GradientTempRectangle {
+    pub magnet: Rectangle,
+    pub temp_coefficient: f64,
+    pub resolution: usize,
+    pub cells: Vec<Rectangle>,
+}
+
+impl GradientTempRectangle {
+    pub fn new<F: Fn(Point2) -> f64>(
+        magnet: Rectangle,
+        temperature: F,
+        temp_coefficient: f64,
+    ) -> Result<GradientTempRectangle, MagnetError> {
+        GradientTempRectangle::with_resolution(
+            magnet,
+            temperature,
+            temp_coefficient,
+            THERMAL_RESOLUTION,
+        )
+    }
+
+    /// Returns a magnet divided into `resolution` x `resolution` cells
+    pub fn with_resolution<F: Fn(Point2) -> f64>(
+        magnet: Rectangle,
+        temperature: F,
+        temp_coefficient: f64,
+        resolution: usize,
+    ) -> Result<GradientTempRectangle, MagnetError> {
+        if !temp_coefficient.is_finite() {
+            return Err(MagnetError::InvalidParameter(format!(
+                "temperature coefficient must be finite, got {}",
+                temp_coefficient
+            )));
+        }
+        if resolution == 0 {
+            return Err(MagnetError::InvalidParameter(
+                "thermal magnet resolution must be at least 1".to_string(),
+            ));
+        }
+
+        let mut cells = magnet.cells(resolution, resolution);
+        for cell in cells.iter_mut() {
+            let t = temperature(cell.center);
+            if t.is_nan() {
+                return Err(MagnetError::InvalidParameter(format!(
+                    "temperature at {} is NaN",
+                    cell.center
+                )));
+            }
+            *cell = cell.with_magnetisation(cell.magnetisation_vector().scale(derated_remanence(
+                1.0,
+                temp_coefficient,
+                t,
+            )));
+        }
+
+        Ok(GradientTempRectangle {
+            magnet,
+            temp_coefficient,
+            resolution,
+            cells,
+        })
+    }
+}
+
+/// Implements Display for magnets in a thermal gradient.
+impl fmt::Display for GradientTempRectangle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[thermal {}\talpha_T: {}\tcells: {}]",
+            self.magnet,
+            self.temp_coefficient,
+            self.cells.len()
+        )
+    }
+}
+
+impl Magnet for GradientTempRectangle {}
+
+impl CellMagnet2D for GradientTempRectangle {
+    fn outline(&self) -> &Rectangle {
+        &self.magnet
+    }
+
+    fn cells(&self) -> &[Rectangle] {
+        &self.cells
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::magnets::magnet2d::thermal::{derated_remanence, GradientTempRectangle};
+    use crate::magnets::magnet2d::{Magnet2D, Rectangle};
+    use crate::utils::points2::{Point2, Points2};
+
+    #[test]
+    fn uniform_temperature_matches_derated_rectangle() {
+        let nominal = Rectangle::new(1.0, 0.5, (0.2, -0.1), 30.0, 1.3, 60.0);
+        let magnet = GradientTempRectangle::new(nominal, |_| 80.0, -0.0012).unwrap();
+        let derated = Rectangle::new(
+            1.0,
+            0.5,
+            (0.2, -0.1),
+            30.0,
+            derated_remanence(1.3, -0.0012, 80.0),
+            60.0,
+        );
+
+        for point in &[Point2::new(1.5, 0.8), Point2::new(-1.0, -1.2)] {
+            let expected = derated.get_field(point).unwrap();
+            let field = magnet.get_field(point).unwrap();
+            assert!((field - expected).magnitude() < 1e-10 * expected.magnitude());
+        }
+        assert!((magnet.get_j() - derated.get_j()).magnitude() < 1e-12);
+    }
+
+    #[test]
+    fn hot_side_is_weaker() {
+        let nominal = Rectangle::new(1.0, 1.0, (0.0, 0.0), 0.0, 1.3, 90.0);
+        let magnet = GradientTempRectangle::new(nominal, |p| 60.0 + 80.0 * p.x, -0.0012).unwrap();
+        let cold = magnet.get_field(&Point2::new(-0.5, 0.8)).unwrap();
+        let hot = magnet.get_field(&Point2::new(0.5, 0.8)).unwrap();
+        assert!(hot.magnitude() < cold.magnitude());
+    }
+
+    #[test]
+    fn derating_is_floored_at_zero() {
+        assert_eq!(0.0, derated_remanence(1.0, -0.01, 500.0));
+        assert!(GradientTempRectangle::new(Rectangle::default(), |_| f64::NAN, -0.001).is_err());
+    }
+}
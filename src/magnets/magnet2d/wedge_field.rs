@@ -0,0 +1,264 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/.
+Copyright 2021 Peter Dunne */
+
+//! 2D Magnetic Field Routines for wedges (annular sectors) and annuli
+//!
+//! The field of a uniformly magnetised wedge is the sum of the fields of its
+//! four charged surfaces: the two radial edges with uniform charge, and the
+//! two arcs with a charge density varying as
+//! $`\sigma = \pm \mathbf{J} \cdot \mathbf{\hat{r}}`$.
+
+use crate::magnets::magnet2d::circle_field::get_field_circle;
+use crate::magnets::magnet2d::line_field::charged_segment_field;
+use crate::magnets::magnet2d::{Annulus, Wedge};
+use crate::utils::complex::Complex;
+use crate::utils::points::Points;
+use crate::utils::points2::{Point2, Points2};
+use crate::PI;
+
+use std::error::Error;
+
+/// Number of wedges used to approximate a radially magnetised annulus
+pub const RADIAL_SEGMENTS: usize = 72;
+
+/// Below this modulus the series expansion is used in `arc_series`
+const SERIES_CUTOFF: f64 = 0.25;
+
+/// Calculates the magnetic field of a wedge whose arcs are centered at the
+/// origin
+pub fn get_field_wedge(magnet: &Wedge, point: &Point2) -> Result<Point2, Box<dyn Error>> {
+    let j = Point2::new(magnet.jx, magnet.jy);
+    let phi_1 = magnet.start_angle * PI / 180.;
+    let phi_2 = magnet.end_angle * PI / 180.;
+
+    // Outward normals of the radial edges are -phi_hat(phi_1), +phi_hat(phi_2)
+    let edge_1 = Point2::new(phi_1.cos(), phi_1.sin());
+    let edge_2 = Point2::new(phi_2.cos(), phi_2.sin());
+    let normal_1 = Point2::new(phi_1.sin(), -phi_1.cos());
+    let normal_2 = Point2::new(-phi_2.sin(), phi_2.cos());
+
+    let mut field = charged_arc_field(&j, magnet.outer_radius, phi_1, phi_2, point)
+        - charged_arc_field(&j, magnet.inner_radius, phi_1, phi_2, point);
+    field += charged_segment_field(
+        j.dot(&normal_1),
+        &edge_1.scale(magnet.inner_radius),
+        &edge_1.scale(magnet.outer_radius),
+        point,
+    );
+    field += charged_segment_field(
+        j.dot(&normal_2),
+        &edge_2.scale(magnet.inner_radius),
+        &edge_2.scale(magnet.outer_radius),
+        point,
+    );
+
+    // The surface charges give mu_0 H, inside the magnet B = mu_0 H + J
+    if magnet.contains_local(point) {
+        field += j;
+    }
+
+    Ok(field)
+}
+
+/// Calculates the magnetic field of an annulus centered at the origin as the
+/// difference of the fields of the outer and inner circles
+///
+/// Radially magnetised annuli are passed to `get_field_radial_annulus`.
+pub fn get_field_annulus(magnet: &Annulus, point: &Point2) -> Result<Point2, Box<dyn Error>> {
+    if magnet.radial {
+        return get_field_radial_annulus(magnet, point);
+    }
+    let outer = get_field_circle(&magnet.outer_circle(), point)?;
+    let inner = get_field_circle(&magnet.inner_circle(), point)?;
+    Ok(outer - inner)
+}
+
+/// Calculates the magnetic field of a radially magnetised annulus centered at
+/// the origin, as the sum of `RADIAL_SEGMENTS` wedges each magnetised along
+/// its bisector.
+///
+/// For an ideal, continuously radial magnetisation the volume charge
+/// $`-\nabla \cdot \mathbf{J} = -J_r / r`$ cancels the surface charges of
+/// the two arcs, and the field vanishes everywhere in 2D. The segmented ring,
+/// like a real assembled radial ring, leaves a stray field from the charges on
+/// the wedge boundaries which decays rapidly away from the ring.
+pub fn get_field_radial_annulus(
+    magnet: &Annulus,
+    point: &Point2,
+) -> Result<Point2, Box<dyn Error>> {
+    let step = 360.0 / RADIAL_SEGMENTS as f64;
+    let mut field = Point2::zero();
+    for k in 0..RADIAL_SEGMENTS {
+        let start = k as f64 * step;
+        let wedge = Wedge::new(
+            (0.0, 0.0),
+            magnet.inner_radius,
+            magnet.outer_radius,
+            start,
+            start + step,
+            magnet.jr,
+            start + 0.5 * step,
+        );
+        field += get_field_wedge(&wedge, point)?;
+    }
+    Ok(field)
+}
+
+/// Returns the field at `point` of an arc of `radius` centered at the origin
+/// from `phi_1` to `phi_2` (radians) with surface charge
+/// $`\sigma = \mathbf{J} \cdot \mathbf{\hat{r}}`$.
+///
+/// Writing $`\sigma = (\bar{J} e^{i\phi} + J e^{-i\phi})/2`$, the field is
+///
+/// ```math
+/// B_x - i B_y = \frac{1}{2 \pi} \int_{\phi_1}^{\phi_2}
+/// \frac{\sigma R \, d\phi}{z - R e^{i\phi}}
+/// ```
+///
+/// which integrates in closed form. The logarithms are expanded about
+/// $`z = 0`$ inside the circle and $`z = \infty`$ outside it, so that the
+/// principal branch is continuous along the arc.
+fn charged_arc_field(j: &Point2, radius: f64, phi_1: f64, phi_2: f64, point: &Point2) -> Point2 {
+    if radius <= 0.0 {
+        return Point2::zero();
+    }
+
+    let j = Complex::new(j.x, j.y);
+    let z = Complex::new(point.x, point.y);
+    let one = Complex::new(1.0, 0.0);
+    let i = Complex::new(0.0, 1.0);
+    let w_1 = Complex::from_polar(1.0, phi_1);
+    let w_2 = Complex::from_polar(1.0, phi_2);
+
+    // Integrals of e^{+i phi} and e^{-i phi} against R dphi / (z - R e^{i phi})
+    let (i_plus, i_minus) = if z.norm() < radius {
+        let q_1 = z / w_1.scale(radius);
+        let q_2 = z / w_2.scale(radius);
+        let i_plus = i * (i.scale(phi_2 - phi_1) + (one - q_2).ln() - (one - q_1).ln());
+        let f_1 = arc_series(q_1) / (w_1 * w_1).scale(radius);
+        let f_2 = arc_series(q_2) / (w_2 * w_2).scale(radius);
+        (i_plus, (f_2 - f_1).scale(radius) / i)
+    } else {
+        let p_1 = w_1.scale(radius) / z;
+        let p_2 = w_2.scale(radius) / z;
+        let i_plus = i * ((one - p_2).ln() - (one - p_1).ln());
+        let r_z2 = Complex::new(radius, 0.0) / (z * z);
+        let f = |w: Complex, p: Complex, phi: f64| {
+            -(one / (z * w)) + r_z2 * (i.scale(phi) - (one - p).ln())
+        };
+        let i_minus = (f(w_2, p_2, phi_2) - f(w_1, p_1, phi_1)).scale(radius) / i;
+        (i_plus, i_minus)
+    };
+
+    let conj_field = (j.conj() * i_plus + j * i_minus).scale(1.0 / (4.0 * PI));
+    Point2::new(conj_field.re, -conj_field.im)
+}
+
+/// Returns $`h(q) = (-\ln(1 - q) - q)/q^2 = \sum_{n \ge 2} q^{n-2}/n`$,
+/// using the series for small $`|q|`$ to avoid cancellation
+fn arc_series(q: Complex) -> Complex {
+    let one = Complex::new(1.0, 0.0);
+    if q.norm() < SERIES_CUTOFF {
+        let mut sum = Complex::new(0.0, 0.0);
+        let mut power = one;
+        for n in 2..40 {
+            sum += power.scale(1.0 / n as f64);
+            power = power * q;
+        }
+        sum
+    } else {
+        (-(one - q).ln() - q) / (q * q)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::magnets::magnet2d::{Annulus, Magnet2D, Rectangle, Wedge};
+    use crate::utils::points::Points;
+    use crate::utils::points2::{Point2, Points2};
+    use crate::PI;
+
+    #[test]
+    fn ring_of_wedges_is_annulus() {
+        let annulus = Annulus::new(0.5, 1.5, (1.0, -1.0), 1.0, 30.0);
+        let wedges: Vec<Wedge> = (0..8)
+            .map(|k| {
+                Wedge::new(
+                    (1.0, -1.0),
+                    0.5,
+                    1.5,
+                    45.0 * k as f64,
+                    45.0 * (k + 1) as f64,
+                    1.0,
+                    30.0,
+                )
+            })
+            .collect();
+
+        // Points in the hole, in the magnet and outside it
+        for point in &[
+            Point2::new(1.1, -0.9),
+            Point2::new(1.0, -1.0),
+            Point2::new(2.0, -0.3),
+            Point2::new(0.3, -1.2),
+            Point2::new(3.0, 2.0),
+        ] {
+            let mut field = Point2::zero();
+            for wedge in &wedges {
+                field += wedge.get_field(point).unwrap();
+            }
+            let expected = annulus.get_field(point).unwrap();
+            assert!((field - expected).magnitude() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn thin_wedge_approaches_rectangle() {
+        // A narrow sector far from its apex is close to a rectangle
+        let wedge = Wedge::new((0.0, -100.0), 100.0, 101.0, 89.5, 90.5, 1.0, 90.0);
+        let width = 2.0 * 100.5 * (0.5_f64).to_radians().sin();
+        let rectangle = Rectangle::new(width, 1.0, (0.0, 0.5), 0.0, 1.0, 90.0);
+        let point = Point2::new(0.3, 1.5);
+        let difference = wedge.get_field(&point).unwrap() - rectangle.get_field(&point).unwrap();
+        assert!(difference.magnitude() < 1e-2 * rectangle.get_field(&point).unwrap().magnitude());
+    }
+
+    #[test]
+    fn radial_ring_field_is_radial_on_symmetry_planes() {
+        let magnet = Annulus::new_radial(0.5, 1.0, (0.0, 0.0), 1.0);
+        let step = 360.0 / super::RADIAL_SEGMENTS as f64;
+
+        // Wedge boundaries and bisectors are both mirror planes. Points on a
+        // boundary inside the magnet lie on the jump in J, so only the
+        // bisectors are checked there.
+        for (angle, radii) in &[
+            (0.0, vec![0.25, 1.01, 1.5]),
+            (7.0 * step, vec![0.25, 1.01, 1.5]),
+            (0.5 * step, vec![0.25, 0.75, 1.01, 1.5]),
+            (12.5 * step, vec![0.25, 0.75, 1.01, 1.5]),
+        ] {
+            let direction = Point2::new(1.0, 0.0).rotate(angle * PI / 180.);
+            for rho in radii {
+                let field = magnet.get_field(&direction.scale(*rho)).unwrap();
+                let tangential = field.x * direction.y - field.y * direction.x;
+                assert!(tangential.abs() < 1e-10);
+            }
+
+            // The segmentation leaves a fringe field just outside the outer
+            // face, which the symmetry makes radial
+            let fringe = magnet.get_field(&direction.scale(1.01)).unwrap();
+            assert!(fringe.dot(&direction).abs() > 1e-3);
+        }
+    }
+
+    #[test]
+    fn radial_ring_stray_field_is_small() {
+        let radial = Annulus::new_radial(0.5, 1.0, (0.0, 0.0), 1.0);
+        let diametric = Annulus::new(0.5, 1.0, (0.0, 0.0), 1.0, 0.0);
+        let point = Point2::new(1.2, 0.3);
+        let stray = radial.get_field(&point).unwrap().magnitude();
+        assert!(stray < 1e-3 * diametric.get_field(&point).unwrap().magnitude());
+    }
+}
@@ -5,5 +5,872 @@ Copyright 2021 Peter Dunne */
 //! # Magnet3D - Field calculations in 3D
 /// This consists of modules for calculating magnetic fields due to
 ///
+pub mod analysis;
 pub mod bulirsch;
+pub mod collection;
+pub mod cone_field;
+pub mod cylinder_field;
+pub mod design;
+pub mod dipole_field;
+pub mod prism_field;
 pub mod solenoid;
+pub mod sphere_field;
+pub mod spheroid_field;
+pub mod torus_field;
+
+use std::error::Error;
+use std::fmt;
+use std::ops::{Add, Mul};
+
+use crate::errors::MagnetError;
+use crate::magnets::magnet3d::cone_field::get_field_cone;
+use crate::magnets::magnet3d::cylinder_field::get_field_cylinder;
+use crate::magnets::magnet3d::prism_field::get_field_prism;
+use crate::magnets::magnet3d::sphere_field::{get_field_sphere, get_field_spherical_shell};
+use crate::magnets::magnet3d::spheroid_field::{get_field_spheroid, spheroid_integrals};
+use crate::magnets::magnet3d::torus_field::get_field_torus;
+use crate::magnets::{check_finite, check_inner_radius, check_magnetisation, check_size, Magnet};
+use crate::utils::points3::{Point3, Points3};
+use crate::PI;
+
+pub trait GetCenter3D {
+    fn get_center(&self) -> Point3;
+}
+
+impl GetCenter3D for Point3 {
+    fn get_center(&self) -> Point3 {
+        *self
+    }
+}
+
+impl<T: Into<f64> + Copy, U: Into<f64> + Copy, V: Into<f64> + Copy> GetCenter3D for (T, U, V) {
+    fn get_center(&self) -> Point3 {
+        Point3 {
+            x: self.0.into(),
+            y: self.1.into(),
+            z: self.2.into(),
+        }
+    }
+}
+
+pub trait Magnet3D<T>: Magnet {
+    fn get_field(&self, point: &T) -> Result<T, Box<dyn Error>>;
+    fn get_center(&self) -> Point3;
+    fn get_size(&self) -> Point3;
+    fn get_j(self) -> Point3;
+
+    /// Returns an error if the magnet is malformed, e.g. with a NaN or
+    /// negative dimension or no magnetisation, so that its field would be
+    /// meaningless
+    fn validate(&self) -> Result<(), MagnetError> {
+        Ok(())
+    }
+}
+
+/// Checks that a magnet built from discrete elements has at least one
+fn check_resolution(resolution: usize) -> Result<(), MagnetError> {
+    if resolution == 0 {
+        return Err(MagnetError::InvalidParameter(
+            "resolution must be at least 1".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Returns the cartesian components of a magnetisation of magnitude `jr`,
+/// with polar angle `theta` from z and azimuthal angle `phi` from x, both in
+/// degrees
+fn magnetisation_components(jr: f64, theta: f64, phi: f64) -> (f64, f64, f64) {
+    let theta = theta * PI / 180.;
+    let phi = phi * PI / 180.;
+    (
+        jr * theta.sin() * phi.cos(),
+        jr * theta.sin() * phi.sin(),
+        jr * theta.cos(),
+    )
+}
+
+/// A uniformly magnetised sphere
+///
+/// ::default method generates a sphere of radius 1, centred at (0, 0, 0),
+/// magnetised in z with a remnant magnetisation of 1 T
+/// # Example
+/// ```rust
+/// use magnet_rs::magnets::magnet3d::Sphere;
+/// let magnet = Sphere::new(0.5, (0.0, 0.0, 1.0), 1.0, 90.0, 0.0);
+/// println!("Sphere:{}", magnet);
+/// ```
+#[derive(Copy, Clone)]
+pub struct Sphere {
+    pub radius: f64,
+    pub center: Point3,
+    pub jr: f64,
+    pub theta: f64,
+    pub phi: f64,
+    pub jx: f64,
+    pub jy: f64,
+    pub jz: f64,
+}
+
+impl Default for Sphere {
+    fn default() -> Self {
+        Sphere {
+            radius: 1.0,
+            center: Point3::new(0.0, 0.0, 0.0),
+            jr: 1.0,
+            theta: 0.0,
+            phi: 0.0,
+            jx: 0.0,
+            jy: 0.0,
+            jz: 1.0,
+        }
+    }
+}
+
+impl Sphere {
+    pub fn new<R, C, J, T, P>(radius: R, center: C, jr: J, theta: T, phi: P) -> Sphere
+    where
+        R: Into<f64> + Mul<Output = R> + Add<Output = R> + Copy,
+        C: GetCenter3D,
+        J: Into<f64> + Mul<Output = J> + Add<Output = J> + Copy,
+        T: Into<f64> + Mul<Output = T> + Add<Output = T> + Copy,
+        P: Into<f64> + Mul<Output = P> + Add<Output = P> + Copy,
+    {
+        let (jx, jy, jz) = magnetisation_components(jr.into(), theta.into(), phi.into());
+        Sphere {
+            radius: radius.into(),
+            center: center.get_center(),
+            jr: jr.into(),
+            theta: theta.into(),
+            phi: phi.into(),
+            jx,
+            jy,
+            jz,
+        }
+    }
+}
+
+/// Implements Display for Sphere magnets.
+impl fmt::Display for Sphere {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[r: {}\tc: {},\tJ ({:.3}, {:.3}, {:.3})]",
+            self.radius, self.center, self.jx, self.jy, self.jz
+        )
+    }
+}
+
+impl Magnet for Sphere {}
+
+impl Magnet3D<Point3> for Sphere {
+    fn get_field(&self, point: &Point3) -> Result<Point3, Box<dyn Error>> {
+        get_field_sphere(self, &(*point - self.center))
+    }
+
+    fn get_center(&self) -> Point3 {
+        self.center
+    }
+
+    fn get_size(&self) -> Point3 {
+        Point3::new(self.radius, self.radius, self.radius)
+    }
+
+    fn get_j(self) -> Point3 {
+        Point3::new(self.jx, self.jy, self.jz)
+    }
+
+    fn validate(&self) -> Result<(), MagnetError> {
+        check_size("radius", self.radius)?;
+        check_finite("center", &[self.center.x, self.center.y, self.center.z])?;
+        check_magnetisation(self.jr, &[self.jx, self.jy, self.jz])
+    }
+}
+
+/// A uniformly magnetised spherical shell (hollow sphere)
+#[derive(Copy, Clone)]
+pub struct SphericalShell {
+    pub inner_radius: f64,
+    pub outer_radius: f64,
+    pub center: Point3,
+    pub jr: f64,
+    pub theta: f64,
+    pub phi: f64,
+    pub jx: f64,
+    pub jy: f64,
+    pub jz: f64,
+}
+
+impl SphericalShell {
+    pub fn new<R, S, C, J, T, P>(
+        inner_radius: R,
+        outer_radius: S,
+        center: C,
+        jr: J,
+        theta: T,
+        phi: P,
+    ) -> SphericalShell
+    where
+        R: Into<f64> + Mul<Output = R> + Add<Output = R> + Copy,
+        S: Into<f64> + Mul<Output = S> + Add<Output = S> + Copy,
+        C: GetCenter3D,
+        J: Into<f64> + Mul<Output = J> + Add<Output = J> + Copy,
+        T: Into<f64> + Mul<Output = T> + Add<Output = T> + Copy,
+        P: Into<f64> + Mul<Output = P> + Add<Output = P> + Copy,
+    {
+        let (jx, jy, jz) = magnetisation_components(jr.into(), theta.into(), phi.into());
+        SphericalShell {
+            inner_radius: inner_radius.into(),
+            outer_radius: outer_radius.into(),
+            center: center.get_center(),
+            jr: jr.into(),
+            theta: theta.into(),
+            phi: phi.into(),
+            jx,
+            jy,
+            jz,
+        }
+    }
+
+    /// Returns the solid sphere bounding the shell, with the shell's
+    /// magnetisation
+    pub fn outer_sphere(&self) -> Sphere {
+        Sphere::new(
+            self.outer_radius,
+            self.center,
+            self.jr,
+            self.theta,
+            self.phi,
+        )
+    }
+
+    /// Returns the sphere filling the hole of the shell, with the shell's
+    /// magnetisation
+    pub fn inner_sphere(&self) -> Sphere {
+        Sphere::new(
+            self.inner_radius,
+            self.center,
+            self.jr,
+            self.theta,
+            self.phi,
+        )
+    }
+}
+
+/// Implements Display for SphericalShell magnets.
+impl fmt::Display for SphericalShell {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[r_in: {}\tr_out: {}\tc: {},\tJ ({:.3}, {:.3}, {:.3})]",
+            self.inner_radius, self.outer_radius, self.center, self.jx, self.jy, self.jz
+        )
+    }
+}
+
+impl Magnet for SphericalShell {}
+
+impl Magnet3D<Point3> for SphericalShell {
+    fn get_field(&self, point: &Point3) -> Result<Point3, Box<dyn Error>> {
+        get_field_spherical_shell(self, &(*point - self.center))
+    }
+
+    fn get_center(&self) -> Point3 {
+        self.center
+    }
+
+    fn get_size(&self) -> Point3 {
+        Point3::new(self.outer_radius, self.outer_radius, self.outer_radius)
+    }
+
+    fn get_j(self) -> Point3 {
+        Point3::new(self.jx, self.jy, self.jz)
+    }
+
+    fn validate(&self) -> Result<(), MagnetError> {
+        check_inner_radius(self.inner_radius)?;
+        check_size("radial thickness", self.outer_radius - self.inner_radius)?;
+        check_finite("center", &[self.center.x, self.center.y, self.center.z])?;
+        check_magnetisation(self.jr, &[self.jx, self.jy, self.jz])
+    }
+}
+
+/// A uniformly magnetised spheroid, with its symmetry axis parallel to z
+///
+/// `polar_radius` is the semi-axis along z and `equatorial_radius` the
+/// semi-axis in the xy plane, so that a larger polar radius gives a prolate
+/// (needle-like) and a smaller one an oblate (disk-like) spheroid. Equal radii
+/// give a sphere.
+///
+/// ::default method generates a sphere of radius 1, centred at (0, 0, 0),
+/// magnetised in z with a remnant magnetisation of 1 T
+/// # Example
+/// ```rust
+/// use magnet_rs::magnets::magnet3d::Spheroid;
+/// let magnet = Spheroid::new(0.5, 2.0, (0.0, 0.0, 0.0), 1.0, 0.0, 0.0);
+/// let (n_equatorial, n_polar) = magnet.demag_factors();
+/// println!("Spheroid:{}, N = ({}, {})", magnet, n_equatorial, n_polar);
+/// ```
+#[derive(Copy, Clone)]
+pub struct Spheroid {
+    pub equatorial_radius: f64,
+    pub polar_radius: f64,
+    pub center: Point3,
+    pub jr: f64,
+    pub theta: f64,
+    pub phi: f64,
+    pub jx: f64,
+    pub jy: f64,
+    pub jz: f64,
+}
+
+impl Default for Spheroid {
+    fn default() -> Self {
+        Spheroid {
+            equatorial_radius: 1.0,
+            polar_radius: 1.0,
+            center: Point3::new(0.0, 0.0, 0.0),
+            jr: 1.0,
+            theta: 0.0,
+            phi: 0.0,
+            jx: 0.0,
+            jy: 0.0,
+            jz: 1.0,
+        }
+    }
+}
+
+impl Spheroid {
+    pub fn new<R, S, C, J, T, P>(
+        equatorial_radius: R,
+        polar_radius: S,
+        center: C,
+        jr: J,
+        theta: T,
+        phi: P,
+    ) -> Spheroid
+    where
+        R: Into<f64> + Mul<Output = R> + Add<Output = R> + Copy,
+        S: Into<f64> + Mul<Output = S> + Add<Output = S> + Copy,
+        C: GetCenter3D,
+        J: Into<f64> + Mul<Output = J> + Add<Output = J> + Copy,
+        T: Into<f64> + Mul<Output = T> + Add<Output = T> + Copy,
+        P: Into<f64> + Mul<Output = P> + Add<Output = P> + Copy,
+    {
+        let (jx, jy, jz) = magnetisation_components(jr.into(), theta.into(), phi.into());
+        Spheroid {
+            equatorial_radius: equatorial_radius.into(),
+            polar_radius: polar_radius.into(),
+            center: center.get_center(),
+            jr: jr.into(),
+            theta: theta.into(),
+            phi: phi.into(),
+            jx,
+            jy,
+            jz,
+        }
+    }
+
+    /// Returns the demagnetising factors `(N_x, N_z)`, perpendicular and
+    /// parallel to the symmetry axis, with $`2 N_x + N_z = 1`$.
+    ///
+    /// These are 1/3 for a sphere, and tend to `(1/2, 0)` for a long needle
+    /// and `(0, 1)` for a thin disk.
+    pub fn demag_factors(&self) -> (f64, f64) {
+        let a = self.equatorial_radius;
+        let c = self.polar_radius;
+        let (i_x, i_z) = spheroid_integrals(a, c, 0.0);
+        let prefactor = a.powi(2) * c / 2.0;
+        (prefactor * i_x, prefactor * i_z)
+    }
+}
+
+/// Implements Display for Spheroid magnets.
+impl fmt::Display for Spheroid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[r_eq: {}\tr_pol: {}\tc: {},\tJ ({:.3}, {:.3}, {:.3})]",
+            self.equatorial_radius, self.polar_radius, self.center, self.jx, self.jy, self.jz
+        )
+    }
+}
+
+impl Magnet for Spheroid {}
+
+impl Magnet3D<Point3> for Spheroid {
+    fn get_field(&self, point: &Point3) -> Result<Point3, Box<dyn Error>> {
+        get_field_spheroid(self, &(*point - self.center))
+    }
+
+    fn get_center(&self) -> Point3 {
+        self.center
+    }
+
+    fn get_size(&self) -> Point3 {
+        Point3::new(
+            self.equatorial_radius,
+            self.equatorial_radius,
+            self.polar_radius,
+        )
+    }
+
+    fn get_j(self) -> Point3 {
+        Point3::new(self.jx, self.jy, self.jz)
+    }
+
+    fn validate(&self) -> Result<(), MagnetError> {
+        check_size("equatorial radius", self.equatorial_radius)?;
+        check_size("polar radius", self.polar_radius)?;
+        check_finite("center", &[self.center.x, self.center.y, self.center.z])?;
+        check_magnetisation(self.jr, &[self.jx, self.jy, self.jz])
+    }
+}
+
+/// A uniformly magnetised cuboid
+///
+/// The prism has dimensions `width`, `depth` and `height` along its local x,
+/// y and z axes, given by `orientation` in the global frame. The
+/// magnetisation components `jx`, `jy`, `jz` are in the global frame.
+///
+/// ::default method generates a cube of size 1 x 1 x 1, centred at
+/// (0, 0, 0), magnetised in z with a remnant magnetisation of 1 T
+/// # Example
+/// ```rust
+/// use magnet_rs::magnets::magnet3d::Prism;
+/// use magnet_rs::utils::points3::{Point3, Points3};
+/// let magnet = Prism::new(1.0, 2.0, 0.5, (0.0, 0.0, 0.0), 1.0, 90.0, 0.0);
+/// let tilted = magnet.rotate(Point3::j_hat(), 30.0);
+/// println!("Prism:{}", tilted);
+/// ```
+#[derive(Copy, Clone)]
+pub struct Prism {
+    pub width: f64,
+    pub depth: f64,
+    pub height: f64,
+    pub center: Point3,
+    pub jr: f64,
+    pub theta: f64,
+    pub phi: f64,
+    pub a: f64,
+    pub b: f64,
+    pub c: f64,
+    pub jx: f64,
+    pub jy: f64,
+    pub jz: f64,
+    pub orientation: [Point3; 3],
+}
+
+impl Default for Prism {
+    fn default() -> Self {
+        Prism {
+            width: 1.0,
+            depth: 1.0,
+            height: 1.0,
+            center: Point3::new(0.0, 0.0, 0.0),
+            jr: 1.0,
+            theta: 0.0,
+            phi: 0.0,
+            a: 0.5,
+            b: 0.5,
+            c: 0.5,
+            jx: 0.0,
+            jy: 0.0,
+            jz: 1.0,
+            orientation: [Point3::i_hat(), Point3::j_hat(), Point3::k_hat()],
+        }
+    }
+}
+
+impl Prism {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new<W, D, H, C, J, T, P>(
+        width: W,
+        depth: D,
+        height: H,
+        center: C,
+        jr: J,
+        theta: T,
+        phi: P,
+    ) -> Prism
+    where
+        W: Into<f64> + Mul<Output = W> + Add<Output = W> + Copy,
+        D: Into<f64> + Mul<Output = D> + Add<Output = D> + Copy,
+        H: Into<f64> + Mul<Output = H> + Add<Output = H> + Copy,
+        C: GetCenter3D,
+        J: Into<f64> + Mul<Output = J> + Add<Output = J> + Copy,
+        T: Into<f64> + Mul<Output = T> + Add<Output = T> + Copy,
+        P: Into<f64> + Mul<Output = P> + Add<Output = P> + Copy,
+    {
+        let (jx, jy, jz) = magnetisation_components(jr.into(), theta.into(), phi.into());
+        Prism {
+            width: width.into(),
+            depth: depth.into(),
+            height: height.into(),
+            center: center.get_center(),
+            jr: jr.into(),
+            theta: theta.into(),
+            phi: phi.into(),
+            a: width.into() / 2.0,
+            b: depth.into() / 2.0,
+            c: height.into() / 2.0,
+            jx,
+            jy,
+            jz,
+            orientation: [Point3::i_hat(), Point3::j_hat(), Point3::k_hat()],
+        }
+    }
+
+    /// Returns a copy of the prism rotated about `axis`, through its center,
+    /// by `angle_deg` degrees. Both the magnetisation and the orientation of
+    /// the prism are rotated.
+    pub fn rotate(&self, axis: Point3, angle_deg: f64) -> Prism {
+        let j = Point3::new(self.jx, self.jy, self.jz).rotate_about_axis(axis, angle_deg);
+        let mut rotated = *self;
+        rotated.jx = j.x;
+        rotated.jy = j.y;
+        rotated.jz = j.z;
+        if self.jr.abs() > 0.0 {
+            rotated.theta = (j.z / self.jr).clamp(-1.0, 1.0).acos() * 180. / PI;
+            rotated.phi = j.y.atan2(j.x) * 180. / PI;
+        }
+        for axis_vector in rotated.orientation.iter_mut() {
+            *axis_vector = axis_vector.rotate_about_axis(axis, angle_deg);
+        }
+        rotated
+    }
+
+    /// Converts a global vector to the prism's local frame
+    pub fn to_local(&self, vector: &Point3) -> Point3 {
+        Point3::new(
+            vector.dot(&self.orientation[0]),
+            vector.dot(&self.orientation[1]),
+            vector.dot(&self.orientation[2]),
+        )
+    }
+
+    /// Converts a vector in the prism's local frame to the global frame
+    pub fn to_global(&self, vector: &Point3) -> Point3 {
+        self.orientation[0].scale(vector.x)
+            + self.orientation[1].scale(vector.y)
+            + self.orientation[2].scale(vector.z)
+    }
+}
+
+/// Implements Display for Prism magnets.
+impl fmt::Display for Prism {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[w: {},\td: {},\th: {},\tc: {},\tJ ({:.3}, {:.3}, {:.3})]",
+            self.width, self.depth, self.height, self.center, self.jx, self.jy, self.jz
+        )
+    }
+}
+
+impl Magnet for Prism {}
+
+impl Magnet3D<Point3> for Prism {
+    fn get_field(&self, point: &Point3) -> Result<Point3, Box<dyn Error>> {
+        let local_point = self.to_local(&(*point - self.center));
+        let local_field = get_field_prism(self, &local_point)?;
+        Ok(self.to_global(&local_field))
+    }
+
+    fn get_center(&self) -> Point3 {
+        self.center
+    }
+
+    fn get_size(&self) -> Point3 {
+        Point3::new(self.width, self.depth, self.height)
+    }
+
+    fn get_j(self) -> Point3 {
+        Point3::new(self.jx, self.jy, self.jz)
+    }
+
+    fn validate(&self) -> Result<(), MagnetError> {
+        check_size("width", self.width)?;
+        check_size("depth", self.depth)?;
+        check_size("height", self.height)?;
+        check_finite("center", &[self.center.x, self.center.y, self.center.z])?;
+        check_magnetisation(self.jr, &[self.jx, self.jy, self.jz])
+    }
+}
+
+/// A cylinder uniformly magnetised along its axis, which is parallel to z
+///
+/// ::default method generates a cylinder of radius 1 and length 1, centred
+/// at (0, 0, 0), with a remnant magnetisation of 1 T
+/// # Example
+/// ```rust
+/// use magnet_rs::magnets::magnet3d::Cylinder;
+/// let magnet = Cylinder::new(0.5, 2.0, (0.0, 0.0, 1.0), 1.0);
+/// println!("Cylinder:{}", magnet);
+/// ```
+#[derive(Copy, Clone)]
+pub struct Cylinder {
+    pub radius: f64,
+    pub length: f64,
+    pub center: Point3,
+    pub jr: f64,
+}
+
+impl Default for Cylinder {
+    fn default() -> Self {
+        Cylinder {
+            radius: 1.0,
+            length: 1.0,
+            center: Point3::new(0.0, 0.0, 0.0),
+            jr: 1.0,
+        }
+    }
+}
+
+impl Cylinder {
+    pub fn new<R, L, C, J>(radius: R, length: L, center: C, jr: J) -> Cylinder
+    where
+        R: Into<f64> + Mul<Output = R> + Add<Output = R> + Copy,
+        L: Into<f64> + Mul<Output = L> + Add<Output = L> + Copy,
+        C: GetCenter3D,
+        J: Into<f64> + Mul<Output = J> + Add<Output = J> + Copy,
+    {
+        Cylinder {
+            radius: radius.into(),
+            length: length.into(),
+            center: center.get_center(),
+            jr: jr.into(),
+        }
+    }
+}
+
+/// Implements Display for Cylinder magnets.
+impl fmt::Display for Cylinder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[r: {},\tl: {},\tc: {},\tJ: {:.3}]",
+            self.radius, self.length, self.center, self.jr
+        )
+    }
+}
+
+impl Magnet for Cylinder {}
+
+impl Magnet3D<Point3> for Cylinder {
+    fn get_field(&self, point: &Point3) -> Result<Point3, Box<dyn Error>> {
+        get_field_cylinder(self, &(*point - self.center))
+    }
+
+    fn get_center(&self) -> Point3 {
+        self.center
+    }
+
+    fn get_size(&self) -> Point3 {
+        Point3::new(2.0 * self.radius, 2.0 * self.radius, self.length)
+    }
+
+    fn get_j(self) -> Point3 {
+        Point3::new(0.0, 0.0, self.jr)
+    }
+
+    fn validate(&self) -> Result<(), MagnetError> {
+        check_size("radius", self.radius)?;
+        check_size("length", self.length)?;
+        check_finite("center", &[self.center.x, self.center.y, self.center.z])?;
+        check_magnetisation(self.jr, &[])
+    }
+}
+
+/// Default number of quadrature nodes per surface used for cones
+pub const CONE_RESOLUTION: usize = 64;
+
+/// A cone uniformly magnetised along its axis, which is parallel to z
+///
+/// The base lies a distance `height` below the `apex`. The field is computed
+/// by numerical integration over the charged surfaces using `resolution`
+/// nodes per surface, `CONE_RESOLUTION` by default; increase it for points
+/// close to the surface.
+/// # Example
+/// ```rust
+/// use magnet_rs::magnets::magnet3d::Cone;
+/// let mut magnet = Cone::new((0.0, 0.0, 1.0), 0.5, 1.0, 1.0);
+/// magnet.resolution = 128;
+/// println!("Cone:{}", magnet);
+/// ```
+#[derive(Copy, Clone)]
+pub struct Cone {
+    pub apex: Point3,
+    pub base_radius: f64,
+    pub height: f64,
+    pub jr: f64,
+    pub resolution: usize,
+}
+
+impl Cone {
+    pub fn new<C, R, H, J>(apex: C, base_radius: R, height: H, jr: J) -> Cone
+    where
+        C: GetCenter3D,
+        R: Into<f64> + Mul<Output = R> + Add<Output = R> + Copy,
+        H: Into<f64> + Mul<Output = H> + Add<Output = H> + Copy,
+        J: Into<f64> + Mul<Output = J> + Add<Output = J> + Copy,
+    {
+        Cone {
+            apex: apex.get_center(),
+            base_radius: base_radius.into(),
+            height: height.into(),
+            jr: jr.into(),
+            resolution: CONE_RESOLUTION,
+        }
+    }
+}
+
+/// Implements Display for Cone magnets.
+impl fmt::Display for Cone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[apex: {},\tr: {},\th: {},\tJ: {:.3}]",
+            self.apex, self.base_radius, self.height, self.jr
+        )
+    }
+}
+
+impl Magnet for Cone {}
+
+impl Magnet3D<Point3> for Cone {
+    fn get_field(&self, point: &Point3) -> Result<Point3, Box<dyn Error>> {
+        get_field_cone(self, &(*point - self.apex))
+    }
+
+    /// Returns the centroid of the cone, a quarter of the height above the
+    /// base
+    fn get_center(&self) -> Point3 {
+        self.apex - Point3::new(0.0, 0.0, 0.75 * self.height)
+    }
+
+    fn get_size(&self) -> Point3 {
+        Point3::new(2.0 * self.base_radius, 2.0 * self.base_radius, self.height)
+    }
+
+    fn get_j(self) -> Point3 {
+        Point3::new(0.0, 0.0, self.jr)
+    }
+
+    fn validate(&self) -> Result<(), MagnetError> {
+        check_size("base radius", self.base_radius)?;
+        check_size("height", self.height)?;
+        check_finite("apex", &[self.apex.x, self.apex.y, self.apex.z])?;
+        check_resolution(self.resolution)?;
+        check_magnetisation(self.jr, &[])
+    }
+}
+
+/// Default number of cells along each coordinate used for tori
+pub const TORUS_RESOLUTION: usize = 16;
+
+/// A uniformly magnetised torus, with its symmetry axis parallel to z
+///
+/// The tube of radius `minor_radius` is centred on a circle of radius
+/// `major_radius` about `center`. The field is a numerical approximation,
+/// summing the dipoles of `resolution`^3 volume cells, `TORUS_RESOLUTION`
+/// by default; it is accurate away from the torus and should not be used
+/// inside or close to it.
+/// # Example
+/// ```rust
+/// use magnet_rs::magnets::magnet3d::Torus;
+/// let mut magnet = Torus::new((0.0, 0.0, 0.0), 1.0, 0.25, 1.0, 90.0, 0.0);
+/// magnet.resolution = 32;
+/// println!("Torus:{}", magnet);
+/// ```
+#[derive(Copy, Clone)]
+pub struct Torus {
+    pub center: Point3,
+    pub major_radius: f64,
+    pub minor_radius: f64,
+    pub jr: f64,
+    pub theta: f64,
+    pub phi: f64,
+    pub jx: f64,
+    pub jy: f64,
+    pub jz: f64,
+    pub resolution: usize,
+}
+
+impl Torus {
+    pub fn new<C, R, S, J, T, P>(
+        center: C,
+        major_radius: R,
+        minor_radius: S,
+        jr: J,
+        theta: T,
+        phi: P,
+    ) -> Torus
+    where
+        C: GetCenter3D,
+        R: Into<f64> + Mul<Output = R> + Add<Output = R> + Copy,
+        S: Into<f64> + Mul<Output = S> + Add<Output = S> + Copy,
+        J: Into<f64> + Mul<Output = J> + Add<Output = J> + Copy,
+        T: Into<f64> + Mul<Output = T> + Add<Output = T> + Copy,
+        P: Into<f64> + Mul<Output = P> + Add<Output = P> + Copy,
+    {
+        let (jx, jy, jz) = magnetisation_components(jr.into(), theta.into(), phi.into());
+        Torus {
+            center: center.get_center(),
+            major_radius: major_radius.into(),
+            minor_radius: minor_radius.into(),
+            jr: jr.into(),
+            theta: theta.into(),
+            phi: phi.into(),
+            jx,
+            jy,
+            jz,
+            resolution: TORUS_RESOLUTION,
+        }
+    }
+
+    /// Returns the volume of the torus, $`2 \pi^2 R r^2`$
+    pub fn volume(&self) -> f64 {
+        2.0 * PI.powi(2) * self.major_radius * self.minor_radius.powi(2)
+    }
+}
+
+/// Implements Display for Torus magnets.
+impl fmt::Display for Torus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[c: {},\tR: {},\tr: {},\tJ ({:.3}, {:.3}, {:.3})]",
+            self.center, self.major_radius, self.minor_radius, self.jx, self.jy, self.jz
+        )
+    }
+}
+
+impl Magnet for Torus {}
+
+impl Magnet3D<Point3> for Torus {
+    fn get_field(&self, point: &Point3) -> Result<Point3, Box<dyn Error>> {
+        get_field_torus(self, &(*point - self.center))
+    }
+
+    fn get_center(&self) -> Point3 {
+        self.center
+    }
+
+    fn get_size(&self) -> Point3 {
+        let outer = 2.0 * (self.major_radius + self.minor_radius);
+        Point3::new(outer, outer, 2.0 * self.minor_radius)
+    }
+
+    fn get_j(self) -> Point3 {
+        Point3::new(self.jx, self.jy, self.jz)
+    }
+
+    fn validate(&self) -> Result<(), MagnetError> {
+        check_size("major radius", self.major_radius)?;
+        check_size("minor radius", self.minor_radius)?;
+        check_finite("center", &[self.center.x, self.center.y, self.center.z])?;
+        check_resolution(self.resolution)?;
+        check_magnetisation(self.jr, &[self.jx, self.jy, self.jz])
+    }
+}
@@ -0,0 +1,114 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/.
+Copyright 2021 Peter Dunne */
+
+//! Analysis of 3D field data
+//!
+//! Routines sampling the field of a collection along paths, e.g. those
+//! followed by a probe in a measurement system.
+
+use crate::errors::MagnetError;
+use crate::magnets::magnet3d::collection::MagnetCollection3D;
+use crate::utils::points3::{Point3, Points3};
+use crate::PI;
+
+/// Samples the field of `collection` at `n` points along a helix and returns
+/// the points with the field at each.
+///
+/// The helix winds anticlockwise about `axis`, a direction through the
+/// origin, at a distance `radius` from it, advancing `pitch` along the axis
+/// per turn. It starts in the plane perpendicular to the axis through the
+/// origin and the `n` samples are evenly spaced in angle over `turns` turns,
+/// including both ends. The starting direction is perpendicular to both the
+/// axis and x, or to y for an axis parallel to x.
+pub fn field_along_helix(
+    collection: &MagnetCollection3D,
+    axis: Point3,
+    radius: f64,
+    pitch: f64,
+    turns: f64,
+    n: usize,
+) -> Result<(Vec<Point3>, Vec<Point3>), MagnetError> {
+    if n < 2 {
+        return Err(MagnetError::InvalidParameter(format!(
+            "at least 2 samples are needed, got {}",
+            n
+        )));
+    }
+    let length = axis.magnitude();
+    if length.is_nan() || length == 0.0 {
+        return Err(MagnetError::InvalidParameter(
+            "the helix axis must be non-zero".to_string(),
+        ));
+    }
+
+    let w = axis.unit();
+    let reference = if w.cross(&Point3::i_hat()).magnitude() > 1e-6 {
+        Point3::i_hat()
+    } else {
+        Point3::j_hat()
+    };
+    let u = w.cross(&reference).unit();
+    let v = w.cross(&u);
+
+    let points: Vec<Point3> = (0..n)
+        .map(|i| {
+            let fraction = turns * i as f64 / (n - 1) as f64;
+            let angle = 2.0 * PI * fraction;
+            u.scale(radius * angle.cos())
+                + v.scale(radius * angle.sin())
+                + w.scale(pitch * fraction)
+        })
+        .collect();
+
+    let field = points
+        .iter()
+        .map(|point| collection.get_field(point))
+        .collect::<Result<Vec<Point3>, MagnetError>>()?;
+
+    Ok((points, field))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::magnets::magnet3d::analysis::field_along_helix;
+    use crate::magnets::magnet3d::collection::MagnetCollection3D;
+    use crate::magnets::magnet3d::Cylinder;
+    use crate::utils::comparison::nearly_equal;
+    use crate::utils::points3::{Point3, Points3};
+
+    fn axisymmetric_source() -> MagnetCollection3D {
+        let mut collection = MagnetCollection3D::new();
+        collection.push(Cylinder::new(0.5, 1.0, (0.0, 0.0, 0.0), 1.0));
+        collection
+    }
+
+    #[test]
+    fn constant_magnitude_at_fixed_height() {
+        // Zero pitch, the probe circles the axis of the cylinder
+        let collection = axisymmetric_source();
+        let (points, field) =
+            field_along_helix(&collection, Point3::k_hat(), 0.8, 0.0, 2.0, 17).unwrap();
+
+        let magnitude = field[0].magnitude();
+        for (point, b) in points.iter().zip(&field) {
+            assert!(nearly_equal(point.x.hypot(point.y), 0.8));
+            assert!((b.magnitude() - magnitude).abs() < 1e-12 * magnitude);
+        }
+    }
+
+    #[test]
+    fn helix_magnitude_depends_only_on_height() {
+        let collection = axisymmetric_source();
+        let (points, field) =
+            field_along_helix(&collection, Point3::k_hat(), 0.8, 0.3, 1.5, 13).unwrap();
+
+        assert!(nearly_equal(points[12].z, 0.45));
+        for (point, b) in points.iter().zip(&field) {
+            let in_plane = Point3::new(0.8, 0.0, point.z);
+            let expected = collection.get_field(&in_plane).unwrap().magnitude();
+            assert!((b.magnitude() - expected).abs() < 1e-12 * expected);
+        }
+    }
+}
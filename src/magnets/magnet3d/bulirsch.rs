@@ -32,10 +32,20 @@ const ERRTOL: f64 = 1e-6;
 /// $` C\left(1, 1, 1, 1 \right)  = \pi/2 `$
 ///
 /// ```rust
-/// use magnet_rs::magnets::bulirsch::cel;
+/// use magnet_rs::magnets::magnet3d::bulirsch::cel;
 /// assert_eq!(cel(1.0, 1.0, 1.0, 1.0), std::f64::consts::FRAC_PI_2);
 /// ```
 pub fn cel(kc: f64, p: f64, c: f64, s: f64) -> f64 {
+    cel_iter(kc, p, c, s, usize::MAX)
+}
+
+/// Bulirsch's complete elliptic integral, performing at most `max_iter`
+/// iterations of the arithmetic-geometric mean after the initial step.
+///
+/// With `max_iter = usize::MAX` this is identical to [`cel`]. Smaller values
+/// return the partially converged estimate, which is useful for studying the
+/// convergence of field routines built on `cel`.
+pub fn cel_iter(kc: f64, p: f64, c: f64, s: f64, max_iter: usize) -> f64 {
     if kc.abs() == 0.0 {
         NAN
     } else {
@@ -72,7 +82,9 @@ pub fn cel(kc: f64, p: f64, c: f64, s: f64) -> f64 {
         g = em;
         em += k;
 
-        while (g - k).abs() > g * ERRTOL {
+        let mut iterations = 0;
+        while (g - k).abs() > g * ERRTOL && iterations < max_iter {
+            iterations += 1;
             k = 2.0 * kk.sqrt();
             kk = k * em;
             f = cc;
@@ -90,10 +102,17 @@ pub fn cel(kc: f64, p: f64, c: f64, s: f64) -> f64 {
 #[cfg(test)]
 mod tests {
     // use crate::utils::nearly_equal;
-    use crate::magnets::magnet3d::bulirsch::cel;
+    use crate::magnets::magnet3d::bulirsch::{cel, cel_iter};
 
     #[test]
     fn cel_all_ones() {
         assert_eq!(cel(1.0, 1.0, 1.0, 1.0), std::f64::consts::FRAC_PI_2);
     }
+
+    #[test]
+    fn cel_iter_converges_to_cel() {
+        let exact = cel(0.3, 1.0, 1.0, 1.0);
+        assert!((cel_iter(0.3, 1.0, 1.0, 1.0, 0) - exact).abs() > 1e-6);
+        assert_eq!(cel_iter(0.3, 1.0, 1.0, 1.0, 100), exact);
+    }
 }
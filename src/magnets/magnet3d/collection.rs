@@ -0,0 +1,120 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/.
+Copyright 2021 Peter Dunne */
+
+//! Collections of 3D magnets
+//!
+//! The field of a collection is the superposition of the fields of its
+//! magnets.
+
+use crate::errors::MagnetError;
+use crate::magnets::magnet3d::Magnet3D;
+use crate::utils::points3::{Point3, Points3};
+
+/// A collection of 3D magnets of any shape
+///
+/// # Example
+/// ```rust
+/// use magnet_rs::magnets::magnet3d::collection::MagnetCollection3D;
+/// use magnet_rs::magnets::magnet3d::{Cylinder, Sphere};
+/// use magnet_rs::utils::points3::Point3;
+/// let mut collection = MagnetCollection3D::new();
+/// collection.push(Cylinder::default());
+/// collection.push(Sphere::new(0.5, (2.0, 0.0, 0.0), 1.0, 90.0, 0.0));
+/// let field = collection.get_field(&Point3::new(1.0, 1.0, 1.0)).unwrap();
+/// ```
+#[derive(Default)]
+pub struct MagnetCollection3D {
+    pub magnets: Vec<Box<dyn Magnet3D<Point3>>>,
+}
+
+impl MagnetCollection3D {
+    pub fn new() -> MagnetCollection3D {
+        MagnetCollection3D {
+            magnets: Vec::new(),
+        }
+    }
+
+    /// Adds a magnet to the collection
+    pub fn push<M: Magnet3D<Point3> + 'static>(&mut self, magnet: M) {
+        self.magnets.push(Box::new(magnet));
+    }
+
+    pub fn len(&self) -> usize {
+        self.magnets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.magnets.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &dyn Magnet3D<Point3>> {
+        self.magnets.iter().map(|magnet| magnet.as_ref())
+    }
+
+    /// Returns the total field of all magnets at `point`
+    pub fn get_field(&self, point: &Point3) -> Result<Point3, MagnetError> {
+        let mut field = Point3::zero();
+        for magnet in self.iter() {
+            field = field + magnet.get_field(point)?;
+        }
+        Ok(field)
+    }
+
+    /// Returns the total field at `point` of the magnets that pass
+    /// [`Magnet3D::validate`], calling `on_invalid` with the index and error
+    /// of each magnet that is skipped instead
+    pub fn total_field<F: FnMut(usize, &MagnetError)>(
+        &self,
+        point: &Point3,
+        mut on_invalid: F,
+    ) -> Result<Point3, MagnetError> {
+        let mut field = Point3::zero();
+        for (k, magnet) in self.iter().enumerate() {
+            match magnet.validate() {
+                Ok(()) => field = field + magnet.get_field(point)?,
+                Err(error) => on_invalid(k, &error),
+            }
+        }
+        Ok(field)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::magnets::magnet3d::collection::MagnetCollection3D;
+    use crate::magnets::magnet3d::{Cylinder, Magnet3D, Prism};
+    use crate::utils::points3::{Point3, Points3};
+
+    #[test]
+    fn collection_field_is_superposition() {
+        let first = Cylinder::new(0.5, 1.0, (0.0, 0.0, -1.0), 1.0);
+        let second = Prism::new(1.0, 1.0, 1.0, (1.0, 0.0, 1.0), 1.0, 90.0, 0.0);
+        let mut collection = MagnetCollection3D::new();
+        collection.push(first);
+        collection.push(second);
+
+        let point = Point3::new(0.3, 1.2, 0.4);
+        let expected = first.get_field(&point).unwrap() + second.get_field(&point).unwrap();
+        let field = collection.get_field(&point).unwrap();
+        assert_eq!(2, collection.len());
+        assert!((field - expected).magnitude() < 1e-15);
+    }
+
+    #[test]
+    fn total_field_skips_invalid_magnets() {
+        let valid = Cylinder::new(0.5, 1.0, (0.0, 0.0, -1.0), 1.0);
+        let mut collection = MagnetCollection3D::new();
+        collection.push(Prism::new(1.0, -1.0, 1.0, (1.0, 0.0, 1.0), 1.0, 90.0, 0.0));
+        collection.push(valid);
+
+        let point = Point3::new(0.3, 0.2, 1.5);
+        let mut skipped = Vec::new();
+        let field = collection
+            .total_field(&point, |k, _| skipped.push(k))
+            .unwrap();
+        assert_eq!(skipped, vec![0]);
+        assert!((field - valid.get_field(&point).unwrap()).magnitude() < 1e-15);
+    }
+}
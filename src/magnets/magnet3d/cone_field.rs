@@ -0,0 +1,123 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/.
+Copyright 2021 Peter Dunne */
+
+//! 3D Magnetic Field Routines for axially magnetised cones
+//!
+//! The surface charge of the cone is split into rings, on the lateral surface
+//! and on the base, and the closed form field of each ring is integrated
+//! numerically with Gauss-Legendre quadrature.
+
+use crate::magnets::magnet3d::bulirsch::cel;
+use crate::magnets::magnet3d::Cone;
+use crate::utils::points3::Point3;
+use crate::utils::quadrature::gauss_legendre;
+use crate::{FP_CUTOFF, I_4PI, PI};
+use std::error::Error;
+
+/// Calculates the magnetic field of an axially magnetised cone, at a point
+/// relative to its apex.
+///
+/// The lateral surface carries a charge $`\sigma = J_r n_z`$ and the base
+/// $`\sigma = -J_r`$. Each is integrated using `magnet.resolution`
+/// Gauss-Legendre nodes, so the accuracy degrades close to the surfaces,
+/// where the resolution should be increased.
+pub fn get_field_cone(magnet: &Cone, point: &Point3) -> Result<Point3, Box<dyn Error>> {
+    let (nodes, weights) = gauss_legendre(magnet.resolution);
+    let rho = (point.x.powi(2) + point.y.powi(2)).sqrt();
+    let (radius, height) = (magnet.base_radius, magnet.height);
+    let slope = radius / height;
+
+    let mut b_rho = 0.0;
+    let mut b_z = 0.0;
+    for (x, w) in nodes.iter().zip(&weights) {
+        // Lateral ring at depth d below the apex, charge J 2 pi r (R/h) dd
+        let depth = height * (x + 1.0) / 2.0;
+        let ring_radius = slope * depth;
+        let charge = magnet.jr * 2.0 * PI * ring_radius * slope * w * height / 2.0;
+        let (r, z) = charged_ring_field(charge, ring_radius, rho, point.z + depth);
+        b_rho += r;
+        b_z += z;
+
+        // Base ring, charge -J 2 pi r dr
+        let ring_radius = radius * (x + 1.0) / 2.0;
+        let charge = -magnet.jr * 2.0 * PI * ring_radius * w * radius / 2.0;
+        let (r, z) = charged_ring_field(charge, ring_radius, rho, point.z + height);
+        b_rho += r;
+        b_z += z;
+    }
+
+    let mut field = if rho > FP_CUTOFF {
+        Point3::new(b_rho * point.x / rho, b_rho * point.y / rho, b_z)
+    } else {
+        Point3::new(0.0, 0.0, b_z)
+    };
+
+    // The surface charges give mu_0 H, inside the magnet B = mu_0 H + J
+    if point.z < 0.0 && point.z > -height && rho < -slope * point.z {
+        field.z += magnet.jr;
+    }
+
+    Ok(field)
+}
+
+/// Returns the field `(B_rho, B_z)` of a ring of total charge `charge` (in
+/// T m²) and radius `ring_radius`, at a radial distance `rho` and a height
+/// `z` above the plane of the ring
+fn charged_ring_field(charge: f64, ring_radius: f64, rho: f64, z: f64) -> (f64, f64) {
+    let sum_sq = (ring_radius + rho).powi(2) + z.powi(2);
+    let diff_sq = (ring_radius - rho).powi(2) + z.powi(2);
+    let kc = (diff_sq / sum_sq).sqrt();
+    let prefac = charge * I_4PI / sum_sq.sqrt();
+
+    let e_k = cel(kc, 1.0, 1.0, kc.powi(2));
+    let b_z = prefac * 2.0 * z * e_k / (PI * diff_sq);
+    let b_rho = if rho > FP_CUTOFF {
+        let k_k = cel(kc, 1.0, 1.0, 1.0);
+        prefac * (k_k - (ring_radius.powi(2) - rho.powi(2) + z.powi(2)) * e_k / diff_sq)
+            / (PI * rho)
+    } else {
+        0.0
+    };
+
+    (b_rho, b_z)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::magnets::magnet3d::dipole_field::get_field_dipole;
+    use crate::magnets::magnet3d::{Cone, Magnet3D};
+    use crate::utils::points3::{Point3, Points3};
+    use crate::PI;
+
+    #[test]
+    fn on_axis_field_matches_reference() {
+        // Reference values from adaptive quadrature of the on-axis ring fields
+        let magnet = Cone::new((0.0, 0.0, 1.0), 0.5, 1.0, 1.0);
+        for (z, expected) in &[
+            (1.5, 0.0236024342255),
+            (-0.5, 0.0755705817704),
+            (0.4, 0.758402406845),
+            (3.0, 0.00201971737781),
+        ] {
+            let field = magnet.get_field(&Point3::new(0.0, 0.0, *z)).unwrap();
+            assert!((field.z - expected).abs() < 1e-8);
+            assert!(field.x == 0.0 && field.y == 0.0);
+        }
+    }
+
+    #[test]
+    fn far_field_is_dipole() {
+        let magnet = Cone::new((0.0, 0.0, 0.0), 0.5, 1.0, 1.0);
+        let point = Point3::new(8.0, 5.0, -12.0);
+        let field = magnet.get_field(&point).unwrap();
+
+        let volume = PI * 0.25 / 3.0;
+        let dipole = get_field_dipole(
+            &Point3::new(0.0, 0.0, volume),
+            &(point - magnet.get_center()),
+        );
+        assert!((field - dipole).magnitude() < 1e-3 * dipole.magnitude());
+    }
+}
@@ -0,0 +1,207 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/.
+Copyright 2021 Peter Dunne */
+
+//! 3D Magnetic Field Routines for axially magnetised cylinders
+//!
+//! An axially magnetised cylinder is equivalent to a solenoid of the same
+//! dimensions, so the field is evaluated with [`solenoid_field_iter`], which
+//! relies on Bulirsch's iterative complete elliptic integral.
+
+use crate::errors::MagnetError;
+use crate::magnets::magnet3d::solenoid::solenoid_field_iter;
+use crate::magnets::magnet3d::Cylinder;
+use crate::utils::points3::Point3;
+use std::error::Error;
+
+/// Calculates the magnetic field of an axially magnetised cylinder centered
+/// at the origin
+pub fn get_field_cylinder(magnet: &Cylinder, point: &Point3) -> Result<Point3, Box<dyn Error>> {
+    Ok(cylinder_field_iter(magnet, point, usize::MAX))
+}
+
+/// Returns the field of a cylinder centered at the origin, capping the
+/// iterations of each elliptic integral at `max_iter`
+fn cylinder_field_iter(magnet: &Cylinder, point: &Point3, max_iter: usize) -> Point3 {
+    let rho = (point.x.powi(2) + point.y.powi(2)).sqrt();
+    let phi = point.y.atan2(point.x);
+    let field = solenoid_field_iter(
+        magnet.radius,
+        magnet.length / 2.0,
+        rho,
+        point.z,
+        magnet.jr,
+        max_iter,
+    );
+
+    // solenoid_field_iter returns (Bz, Br)
+    Point3::new(field.y * phi.cos(), field.y * phi.sin(), field.x)
+}
+
+/// Returns the field of `magnet` at `point` after each number of elliptic
+/// integral iterations from 0 to `max_iter`, as `(iterations, field)` pairs.
+///
+/// The integrals stop early once converged, so later estimates repeat the
+/// converged value. This can be used to choose an iteration count that
+/// balances speed and accuracy.
+pub fn cylinder_convergence_study(
+    magnet: &Cylinder,
+    point: Point3,
+    max_iter: usize,
+) -> Vec<(usize, Point3)> {
+    let local_point = point - magnet.center;
+    (0..=max_iter)
+        .map(|n| (n, cylinder_field_iter(magnet, &local_point, n)))
+        .collect()
+}
+
+/// Returns the field of a thin disk of `radius` and `thickness`, axially
+/// magnetised with `magnetisation` (in T) and centered at `center`, without
+/// evaluating elliptic integrals.
+///
+/// On the axis the field is exact,
+///
+/// ```math
+/// B_z(z) = \frac{J}{2} \left( g(z + t/2) - g(z - t/2) \right), \quad
+/// g(u) = \frac{u}{\sqrt{R^2 + u^2}}
+/// ```
+///
+/// and off the axis it is expanded in the radial distance $`\rho`$,
+///
+/// ```math
+/// B_z = B_z(z) - \frac{\rho^2}{4} B_z''(z), \quad
+/// B_\rho = -\frac{\rho}{2} B_z'(z) + \frac{\rho^3}{16} B_z'''(z),
+/// ```
+///
+/// which follows from $`\mathbf{B}`$ being divergence and curl free away
+/// from the rim of the disk. The relative error is of order
+/// $`(\rho/d)^4`$, where $`d`$ is the distance from the axial point to the
+/// rim, so the expansion holds near the axis, $`\rho \ll R`$, including
+/// inside the disk and close to its faces. For a thin disk, $`t \ll R`$, the
+/// rims of both faces coincide and the result is that of the general
+/// cylinder over the whole paraxial region; use [`get_field_cylinder`] off
+/// axis towards or beyond the rim.
+pub fn get_field_disk(
+    radius: f64,
+    thickness: f64,
+    magnetisation: f64,
+    center: Point3,
+    point: Point3,
+) -> Result<Point3, MagnetError> {
+    if radius.is_nan() || radius <= 0.0 || thickness.is_nan() || thickness <= 0.0 {
+        return Err(MagnetError::InvalidParameter(format!(
+            "disk radius and thickness must be positive, got {} and {}",
+            radius, thickness
+        )));
+    }
+
+    let local = point - center;
+    let rho_sq = local.x.powi(2) + local.y.powi(2);
+    let a_sq = radius.powi(2);
+
+    // Derivatives of g(u) = u / sqrt(R^2 + u^2), with s = R^2 + u^2
+    let derivatives = |u: f64| {
+        let s = a_sq + u.powi(2);
+        let g_0 = u / s.sqrt();
+        let g_1 = a_sq / s.powf(1.5);
+        let g_2 = -3.0 * a_sq * u / s.powf(2.5);
+        let g_3 = -3.0 * a_sq * (a_sq - 4.0 * u.powi(2)) / s.powf(3.5);
+        [g_0, g_1, g_2, g_3]
+    };
+    let upper = derivatives(local.z + thickness / 2.0);
+    let lower = derivatives(local.z - thickness / 2.0);
+    let b: Vec<f64> = upper
+        .iter()
+        .zip(lower.iter())
+        .map(|(u, l)| magnetisation / 2.0 * (u - l))
+        .collect();
+
+    let b_z = b[0] - rho_sq / 4.0 * b[2];
+    // B_rho / rho, so that the field stays finite on the axis
+    let b_rho = -b[1] / 2.0 + rho_sq / 16.0 * b[3];
+
+    Ok(Point3::new(b_rho * local.x, b_rho * local.y, b_z))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::magnets::magnet3d::cylinder_field::{cylinder_convergence_study, get_field_disk};
+    use crate::magnets::magnet3d::{Cylinder, Magnet3D};
+    use crate::utils::comparison::nearly_equal;
+    use crate::utils::points3::{Point3, Points3};
+
+    fn on_axis_field(magnet: &Cylinder, z: f64) -> f64 {
+        let zp = z - magnet.center.z + magnet.length / 2.0;
+        let zn = z - magnet.center.z - magnet.length / 2.0;
+        let a = magnet.radius;
+        magnet.jr / 2.0
+            * (zp / (zp.powi(2) + a.powi(2)).sqrt() - zn / (zn.powi(2) + a.powi(2)).sqrt())
+    }
+
+    #[test]
+    fn on_axis_field_matches_analytic() {
+        let magnet = Cylinder::new(0.5, 2.0, (0.0, 0.0, 1.0), 1.2);
+        for z in &[-1.0, 0.5, 1.0, 3.0] {
+            let field = magnet.get_field(&Point3::new(0.0, 0.0, *z)).unwrap();
+            assert!((field.z - on_axis_field(&magnet, *z)).abs() < 1e-10);
+            assert!(nearly_equal(field.x, 0.0) && nearly_equal(field.y, 0.0));
+        }
+    }
+
+    #[test]
+    fn on_axis_residual_is_monotonic() {
+        let magnet = Cylinder::new(1.0, 0.5, (0.0, 0.0, 0.0), 1.0);
+        let z = 0.8;
+        let exact = on_axis_field(&magnet, z);
+        let study = cylinder_convergence_study(&magnet, Point3::new(0.0, 0.0, z), 8);
+
+        assert_eq!(9, study.len());
+        let residuals: Vec<f64> = study.iter().map(|(_, b)| (b.z - exact).abs()).collect();
+        for pair in residuals.windows(2) {
+            assert!(pair[1] <= pair[0] + 1e-15);
+        }
+        assert!(residuals[8] < 1e-10);
+    }
+
+    #[test]
+    fn off_axis_study_converges_to_field() {
+        let magnet = Cylinder::new(1.0, 0.5, (0.0, 0.0, 0.0), 1.0);
+        let point = Point3::new(0.6, 0.3, 0.4);
+        let field = magnet.get_field(&point).unwrap();
+        let study = cylinder_convergence_study(&magnet, point, 10);
+
+        let residuals: Vec<f64> = study
+            .iter()
+            .map(|(_, b)| (*b - field).magnitude())
+            .collect();
+        assert!(residuals[0] > residuals[2]);
+        assert_eq!(0.0, residuals[10]);
+    }
+
+    #[test]
+    fn thin_disk_matches_cylinder() {
+        let center = Point3::new(0.5, -0.5, 1.0);
+        let magnet = Cylinder::new(1.0, 0.02, center, 1.2);
+        let offsets = [
+            Point3::new(0.0, 0.0, 0.3),
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(0.1, -0.05, 0.5),
+            Point3::new(0.05, 0.1, -0.2),
+            Point3::new(0.08, 0.0, 0.005),
+        ];
+        for offset in &offsets {
+            let point = center + *offset;
+            let disk = get_field_disk(1.0, 0.02, 1.2, center, point).unwrap();
+            let cylinder = magnet.get_field(&point).unwrap();
+            assert!((disk - cylinder).magnitude() < 1e-4 * cylinder.magnitude());
+        }
+    }
+
+    #[test]
+    fn invalid_disk() {
+        let origin = Point3::new(0.0, 0.0, 0.0);
+        assert!(get_field_disk(0.0, 0.1, 1.0, origin, origin).is_err());
+        assert!(get_field_disk(1.0, -0.1, 1.0, origin, origin).is_err());
+    }
+}
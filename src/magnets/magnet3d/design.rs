@@ -0,0 +1,152 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/.
+Copyright 2021 Peter Dunne */
+
+//! Design of 3D magnet assemblies
+//!
+//! Routines building magnet assemblies from segments, e.g. the Halbach
+//! cylinders of permanent magnet NMR and MRI systems.
+
+use crate::errors::MagnetError;
+use crate::magnets::magnet3d::collection::MagnetCollection3D;
+use crate::magnets::magnet3d::Prism;
+use crate::utils::points3::{Point3, Points3};
+use crate::PI;
+
+/// Geometry and magnetisation of a segmented dipolar Halbach cylinder, whose
+/// axis is z.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct HalbachSpec {
+    pub inner_radius: f64,
+    pub outer_radius: f64,
+    /// Length along z
+    pub length: f64,
+    pub remanence: f64,
+    /// Number of segments around the circumference
+    pub n_segments: usize,
+}
+
+/// Returns the Halbach cylinder `spec`, centred on the origin and rotated
+/// about z by `rotation_deg`, approximated by `spec.n_segments` prisms.
+///
+/// The segment at an angle $`\psi`$ around the cylinder is magnetised at
+/// $`2\psi`$ from x, less the rotation, so the unrotated cylinder has its
+/// bore field along x, of $`B_r \ln(r_o/r_i)`$ for an infinitely long ideal
+/// cylinder. Each segment spans the wall radially and is as wide as fits at
+/// the inner radius, so the segments do not overlap but leave gaps towards
+/// the outer radius, lowering the field roughly in proportion to the unfilled
+/// fraction of the wall.
+pub fn halbach_cylinder(spec: &HalbachSpec, rotation_deg: f64) -> MagnetCollection3D {
+    let mut cylinder = MagnetCollection3D::new();
+    let n = spec.n_segments as f64;
+    let thickness = spec.outer_radius - spec.inner_radius;
+    let width = 2.0 * spec.inner_radius * (PI / n).tan();
+    let centre_radius = (spec.inner_radius + spec.outer_radius) / 2.0;
+
+    for k in 0..spec.n_segments {
+        let psi = 360.0 * k as f64 / n + rotation_deg;
+        let segment = Prism::new(
+            thickness,
+            width,
+            spec.length,
+            (0.0, 0.0, 0.0),
+            spec.remanence,
+            90.0,
+            psi - rotation_deg,
+        );
+        let mut segment = segment.rotate(Point3::k_hat(), psi);
+        let psi_rad = psi * PI / 180.;
+        segment.center = Point3::new(psi_rad.cos(), psi_rad.sin(), 0.0).scale(centre_radius);
+        cylinder.push(segment);
+    }
+    cylinder
+}
+
+/// Returns the two concentric Halbach cylinders `inner` and `outer`, with the
+/// outer one rotated about z by `relative_angle_deg`, as used to tune the
+/// bore field of a permanent magnet NMR system.
+///
+/// The bore fields of the two add as vectors, so the magnitude varies from
+/// the sum of the two at 0 to their difference at 180; cylinders with equal
+/// bore fields, e.g. with the same radius ratio, cancel there. The outer
+/// cylinder's inner radius should be at least the inner cylinder's outer
+/// radius.
+pub fn nested_halbach(
+    inner: HalbachSpec,
+    outer: HalbachSpec,
+    relative_angle_deg: f64,
+) -> MagnetCollection3D {
+    let mut nested = halbach_cylinder(&inner, 0.0);
+    nested
+        .magnets
+        .extend(halbach_cylinder(&outer, relative_angle_deg).magnets);
+    nested
+}
+
+/// Returns the field at the centre of the bore of [`nested_halbach`] for each
+/// of `relative_angles_deg`.
+pub fn nested_halbach_bore_field(
+    inner: HalbachSpec,
+    outer: HalbachSpec,
+    relative_angles_deg: &[f64],
+) -> Result<Vec<Point3>, MagnetError> {
+    relative_angles_deg
+        .iter()
+        .map(|angle| nested_halbach(inner, outer, *angle).get_field(&Point3::zero()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::magnets::magnet3d::design::{
+        halbach_cylinder, nested_halbach, nested_halbach_bore_field, HalbachSpec,
+    };
+    use crate::utils::points3::{Point3, Points3};
+
+    fn spec(inner_radius: f64, outer_radius: f64) -> HalbachSpec {
+        HalbachSpec {
+            inner_radius,
+            outer_radius,
+            length: 100.0,
+            remanence: 1.2,
+            n_segments: 16,
+        }
+    }
+
+    #[test]
+    fn long_halbach_bore_field() {
+        let spec = spec(1.0, 2.0);
+        let field = halbach_cylinder(&spec, 0.0)
+            .get_field(&Point3::zero())
+            .unwrap();
+        let ideal = spec.remanence * (2.0_f64).ln();
+        // The square segments fill about two thirds of the wall
+        assert!(field.x > 0.6 * ideal && field.x < 0.8 * ideal);
+        assert!(field.y.abs() < 1e-12 && field.z.abs() < 1e-12);
+
+        let rotated = halbach_cylinder(&spec, 30.0)
+            .get_field(&Point3::zero())
+            .unwrap();
+        let expected = field.rotate_about_axis(Point3::k_hat(), 30.0);
+        assert!((rotated - expected).magnitude() < 1e-12);
+    }
+
+    #[test]
+    fn rotating_outer_cylinder_tunes_bore_field() {
+        // Equal radius ratios give equal bore fields for long cylinders
+        let (inner, outer) = (spec(1.0, 2.0), spec(2.2, 4.4));
+        let angles: Vec<f64> = (0..=12).map(|k| 15.0 * k as f64).collect();
+        let magnitudes: Vec<f64> = nested_halbach_bore_field(inner, outer, &angles)
+            .unwrap()
+            .iter()
+            .map(|b| b.magnitude())
+            .collect();
+
+        for pair in magnitudes.windows(2) {
+            assert!(pair[1] < pair[0]);
+        }
+        assert!(magnitudes[12] < 0.02 * magnitudes[0]);
+        assert_eq!(32, nested_halbach(inner, outer, 0.0).len());
+    }
+}
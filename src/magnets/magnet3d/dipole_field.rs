@@ -0,0 +1,21 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/.
+Copyright 2021 Peter Dunne */
+
+use crate::utils::points3::{Point3, Points3};
+use crate::I_4PI;
+
+/// Returns the field of a point dipole at the origin
+///
+/// The moment is given as magnetisation times volume, $`\mathbf{m} = \mathbf{J} V`$
+/// in T m^3, so that
+/// ```math
+/// \mathbf{B} = \frac{1}{4\pi} \frac{3 (\mathbf{m} \cdot \hat{\mathbf{r}}) \hat{\mathbf{r}} - \mathbf{m}}{r^3}
+/// ```
+pub fn get_field_dipole(moment: &Point3, point: &Point3) -> Point3 {
+    let r = point.magnitude();
+    let r_hat = point.scale(1.0 / r);
+    let m_dot_r = moment.dot(&r_hat);
+    (r_hat.scale(3.0 * m_dot_r) - *moment).scale(I_4PI / r.powi(3))
+}
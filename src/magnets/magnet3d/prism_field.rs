@@ -0,0 +1,150 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/.
+Copyright 2021 Peter Dunne */
+
+//! 3D Magnetic Field Routines for cuboids
+//!
+//! The field of a uniformly magnetised cuboid is the sum of the fields of the
+//! uniformly charged faces perpendicular to each magnetisation component.
+
+use crate::magnets::magnet3d::Prism;
+use crate::utils::points3::Point3;
+use crate::I_4PI;
+use std::error::Error;
+
+/// Returns the field vector due to a prism of size `2a x 2b x 2c` centered at
+/// the origin, at a point given in the prism's local frame
+///
+/// with an arbitrary magnetisation
+/// $`\mathbf{J} = J_x \mathbf{\hat{x}} + J_y \mathbf{\hat{y}} + J_z \mathbf{\hat{z}}`$
+/// in the prism's local frame.
+pub fn get_field_prism(magnet: &Prism, point: &Point3) -> Result<Point3, Box<dyn Error>> {
+    let j = magnet.to_local(&Point3::new(magnet.jx, magnet.jy, magnet.jz));
+    let (a, b, c) = (magnet.a, magnet.b, magnet.c);
+    let (x, y, z) = (point.x, point.y, point.z);
+
+    let mut field = Point3::new(0.0, 0.0, 0.0);
+
+    if j.z != 0.0 {
+        // Faces at z = +/- c, in-plane coordinates (x, y)
+        for (sign, z0) in &[(1.0, c), (-1.0, -c)] {
+            let (bx, by, bz) = charged_face_field(sign * j.z, (-a, a), (-b, b), x, y, z - z0);
+            field = field + Point3::new(bx, by, bz);
+        }
+    }
+
+    if j.x != 0.0 {
+        // Faces at x = +/- a, in-plane coordinates (y, z)
+        for (sign, x0) in &[(1.0, a), (-1.0, -a)] {
+            let (by, bz, bx) = charged_face_field(sign * j.x, (-b, b), (-c, c), y, z, x - x0);
+            field = field + Point3::new(bx, by, bz);
+        }
+    }
+
+    if j.y != 0.0 {
+        // Faces at y = +/- b, in-plane coordinates (z, x)
+        for (sign, y0) in &[(1.0, b), (-1.0, -b)] {
+            let (bz, bx, by) = charged_face_field(sign * j.y, (-c, c), (-a, a), z, x, y - y0);
+            field = field + Point3::new(bx, by, bz);
+        }
+    }
+
+    // The surface charges give mu_0 H, inside the magnet B = mu_0 H + J
+    if x.abs() < a && y.abs() < b && z.abs() < c {
+        field = field + j;
+    }
+
+    Ok(field)
+}
+
+/// Returns the field `(B_u, B_v, B_w)` of a rectangular sheet with uniform
+/// surface charge `sigma` (in T) spanning `u_range` x `v_range` in a plane of
+/// constant w, at a point `(u, v)` in plane and a height `w` above it.
+fn charged_face_field(
+    sigma: f64,
+    u_range: (f64, f64),
+    v_range: (f64, f64),
+    u: f64,
+    v: f64,
+    w: f64,
+) -> (f64, f64, f64) {
+    let mut b_u = 0.0;
+    let mut b_v = 0.0;
+    let mut b_w = 0.0;
+
+    for (sign_u, u_k) in &[(-1.0, u_range.0), (1.0, u_range.1)] {
+        for (sign_v, v_k) in &[(-1.0, v_range.0), (1.0, v_range.1)] {
+            let du = u - u_k;
+            let dv = v - v_k;
+            let r = (du.powi(2) + dv.powi(2) + w.powi(2)).sqrt();
+            let sign = sign_u * sign_v;
+
+            b_u -= sign * (dv / (du.powi(2) + w.powi(2)).sqrt()).asinh();
+            b_v -= sign * (du / (dv.powi(2) + w.powi(2)).sqrt()).asinh();
+            b_w += sign * (du * dv / (w * r)).atan();
+        }
+    }
+
+    (
+        sigma * I_4PI * b_u,
+        sigma * I_4PI * b_v,
+        sigma * I_4PI * b_w,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::magnets::magnet3d::dipole_field::get_field_dipole;
+    use crate::magnets::magnet3d::prism_field::get_field_prism;
+    use crate::magnets::magnet3d::{Magnet3D, Prism};
+    use crate::utils::comparison::nearly_equal;
+    use crate::utils::points3::{Point3, Points3};
+
+    #[test]
+    fn cube_center_field() {
+        // N = 1/3 for a cube, so B = J (1 - N)
+        for (theta, phi) in &[(0.0, 0.0), (90.0, 0.0), (90.0, 90.0)] {
+            let magnet = Prism::new(1.0, 1.0, 1.0, (0.0, 0.0, 0.0), 1.0, *theta, *phi);
+            let field = get_field_prism(&magnet, &Point3::new(0.0, 0.0, 0.0)).unwrap();
+            let expected = Point3::new(magnet.jx, magnet.jy, magnet.jz).scale(2.0 / 3.0);
+            assert!((field - expected).magnitude() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn far_field_is_dipole() {
+        let magnet = Prism::new(1.0, 0.5, 2.0, (0.0, 0.0, 0.0), 1.0, 40.0, 20.0);
+        let point = Point3::new(30.0, -20.0, 40.0);
+        let field = magnet.get_field(&point).unwrap();
+        let volume = magnet.width * magnet.depth * magnet.height;
+        let moment = Point3::new(magnet.jx, magnet.jy, magnet.jz).scale(volume);
+        let dipole = get_field_dipole(&moment, &point);
+        assert!((field - dipole).magnitude() < 1e-3 * dipole.magnitude());
+    }
+
+    #[test]
+    fn rotated_prism_rotates_field() {
+        let magnet = Prism::new(1.0, 0.5, 2.0, (0.5, 0.0, -0.5), 1.0, 90.0, 0.0);
+        let axis = Point3::new(1.0, 2.0, -0.5);
+        let rotated = magnet.rotate(axis, 35.0);
+
+        let point = Point3::new(1.2, -0.7, 2.1);
+        let relative = point - magnet.center;
+        let rotated_point = magnet.center + relative.rotate_about_axis(axis, 35.0);
+
+        let field = magnet
+            .get_field(&point)
+            .unwrap()
+            .rotate_about_axis(axis, 35.0);
+        let rotated_field = rotated.get_field(&rotated_point).unwrap();
+        assert!((field - rotated_field).magnitude() < 1e-12);
+    }
+
+    #[test]
+    fn rotate_updates_magnetisation_angles() {
+        let magnet = Prism::default().rotate(Point3::j_hat(), 90.0);
+        assert!(nearly_equal(magnet.jx, 1.0) && nearly_equal(magnet.jz, 0.0));
+        assert!(nearly_equal(magnet.theta, 90.0) && nearly_equal(magnet.phi, 0.0));
+    }
+}
@@ -1,4 +1,4 @@
-use crate::magnets::magnet3d::bulirsch::cel;
+use crate::magnets::magnet3d::bulirsch::cel_iter;
 use crate::utils::points2::Point2;
 use crate::PI;
 // use crate::magnets::
@@ -6,6 +6,13 @@ use crate::PI;
 /// Calculate the magnetic field due to a solenoid at any point
 /// returns Bz,Br as a Point2 structure
 pub fn solenoid_field(a: f64, b: f64, rho: f64, z: f64, b_0: f64) -> Point2 {
+    solenoid_field_iter(a, b, rho, z, b_0, usize::MAX)
+}
+
+/// Calculate the magnetic field due to a solenoid, capping the number of
+/// iterations of each complete elliptic integral at `max_iter`
+/// returns Bz,Br as a Point2 structure
+pub fn solenoid_field_iter(a: f64, b: f64, rho: f64, z: f64, b_0: f64, max_iter: usize) -> Point2 {
     let zp = z + b;
     let zn = z - b;
 
@@ -18,17 +25,46 @@ pub fn solenoid_field(a: f64, b: f64, rho: f64, z: f64, b_0: f64) -> Point2 {
     let beta_p = zp / (zp.powi(2) + (rho_a).powi(2)).sqrt();
     let beta_n = zn / (zn.powi(2) + (rho_a).powi(2)).sqrt();
 
-    let gamma = (rho_na) / (rho_a);
+    let gamma = (a - rho) / (rho_a);
 
     let kp = ((zp.powi(2) + (rho_na).powi(2)) / (zp.powi(2) + (rho_a).powi(2))).sqrt();
 
     let kn = ((zn.powi(2) + (rho_na).powi(2)) / (zn.powi(2) + (rho_a).powi(2))).sqrt();
 
-    let b_r = (b_0 / PI) * (alpha_p * cel(kp, 1.0, 1.0, -1.0) - alpha_n * cel(kn, 1.0, 1.0, -1.0));
+    let b_r = (b_0 / PI)
+        * (alpha_p * cel_iter(kp, 1.0, 1.0, -1.0, max_iter)
+            - alpha_n * cel_iter(kn, 1.0, 1.0, -1.0, max_iter));
 
     let b_z = ((b_0 / PI) * a / (rho_a))
-        * (beta_p * cel(kp, gamma.powi(2), 1.0, gamma)
-            - beta_n * cel(kn, gamma.powi(2), 1.0, gamma));
+        * (beta_p * cel_iter(kp, gamma.powi(2), 1.0, gamma, max_iter)
+            - beta_n * cel_iter(kn, gamma.powi(2), 1.0, gamma, max_iter));
 
     Point2::new(b_z, b_r)
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::magnets::magnet3d::solenoid::solenoid_field;
+    use crate::utils::comparison::nearly_equal;
+
+    #[test]
+    fn on_axis_field() {
+        // On the axis Bz = (B0 / 2) [(z + b) / sqrt((z + b)^2 + a^2) - (z - b) / sqrt((z - b)^2 + a^2)]
+        let (a, b, b_0) = (1.0_f64, 2.0_f64, 1.0_f64);
+        for &z in &[0.0_f64, 1.5, 4.0, -3.0] {
+            let expected = (b_0 / 2.0)
+                * ((z + b) / ((z + b).powi(2) + a.powi(2)).sqrt()
+                    - (z - b) / ((z - b).powi(2) + a.powi(2)).sqrt());
+            let field = solenoid_field(a, b, 0.0, z, b_0);
+            assert!(nearly_equal(field.x, expected));
+            assert!(field.y.abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn center_of_short_solenoid() {
+        // For a = b the center field is B0 / sqrt(2)
+        let field = solenoid_field(1.0, 1.0, 0.0, 0.0, 1.0);
+        assert!(nearly_equal(field.x, 1.0 / 2.0_f64.sqrt()));
+    }
+}
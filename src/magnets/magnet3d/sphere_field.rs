@@ -0,0 +1,87 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/.
+Copyright 2021 Peter Dunne */
+/// Contains magnet field routines for calculating the magnetic field due to
+/// uniformly magnetised spheres and spherical shells
+use crate::magnets::magnet3d::dipole_field::get_field_dipole;
+use crate::magnets::magnet3d::{Sphere, SphericalShell};
+use crate::utils::points3::{Point3, Points3};
+use crate::PI;
+
+use std::error::Error;
+
+/// Calculates the magnetic field of a uniformly magnetised sphere centered at
+/// the origin.
+///
+/// Inside the sphere the field is uniform, $`\mathbf{B} = 2\mathbf{J}/3`$, and
+/// outside it is that of a point dipole with moment $`\mathbf{J} V`$.
+pub fn get_field_sphere(magnet: &Sphere, point: &Point3) -> Result<Point3, Box<dyn Error>> {
+    let j = Point3::new(magnet.jx, magnet.jy, magnet.jz);
+
+    if point.magnitude() < magnet.radius {
+        Ok(j.scale(2.0 / 3.0))
+    } else {
+        let volume = 4.0 * PI * magnet.radius.powi(3) / 3.0;
+        Ok(get_field_dipole(&j.scale(volume), point))
+    }
+}
+
+/// Calculates the magnetic field of a uniformly magnetised spherical shell
+/// centered at the origin, as the difference of the fields of the outer and
+/// inner spheres.
+pub fn get_field_spherical_shell(
+    magnet: &SphericalShell,
+    point: &Point3,
+) -> Result<Point3, Box<dyn Error>> {
+    let outer = get_field_sphere(&magnet.outer_sphere(), point)?;
+    let inner = get_field_sphere(&magnet.inner_sphere(), point)?;
+    Ok(outer - inner)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::magnets::magnet3d::dipole_field::get_field_dipole;
+    use crate::magnets::magnet3d::sphere_field::{get_field_sphere, get_field_spherical_shell};
+    use crate::magnets::magnet3d::{Magnet3D, Sphere, SphericalShell};
+    use crate::utils::comparison::nearly_equal;
+    use crate::utils::points3::{Point3, Points3};
+    use crate::PI;
+
+    #[test]
+    fn sphere_interior_field() {
+        let magnet = Sphere::default();
+        let field = get_field_sphere(&magnet, &Point3::new(0.1, 0.2, 0.3)).unwrap();
+        assert_eq!(Point3::new(0.0, 0.0, 2.0 / 3.0), field);
+    }
+
+    #[test]
+    fn sphere_pole_field_continuous() {
+        // B normal to the surface is continuous, so just outside the pole B = 2J/3
+        let magnet = Sphere::default();
+        let field = get_field_sphere(&magnet, &Point3::new(0.0, 0.0, 1.0)).unwrap();
+        assert!(nearly_equal(field.z, 2.0 / 3.0) && nearly_equal(field.x, 0.0));
+    }
+
+    #[test]
+    fn thin_shell_far_field_is_dipole() {
+        let magnet = SphericalShell::new(0.99, 1.0, (0.0, 0.0, 0.0), 1.0, 30.0, 45.0);
+        let point = Point3::new(3.0, -2.0, 5.0);
+        let field = magnet.get_field(&point).unwrap();
+
+        let volume = 4.0 * PI * (1.0_f64.powi(3) - 0.99_f64.powi(3)) / 3.0;
+        let moment = Point3::new(magnet.jx, magnet.jy, magnet.jz).scale(volume);
+        let dipole = get_field_dipole(&moment, &point);
+        assert!((field - dipole).magnitude() < 1e-12 * dipole.magnitude().max(1.0));
+    }
+
+    #[test]
+    fn shell_interior_field_is_zero() {
+        let magnet = SphericalShell::new(0.5, 1.0, (1.0, 1.0, 1.0), 1.0, 0.0, 0.0);
+        let field = magnet.get_field(&Point3::new(1.1, 0.9, 1.2)).unwrap();
+        assert!(field.magnitude() < 1e-15);
+
+        let field = get_field_spherical_shell(&magnet, &Point3::new(0.0, 0.0, 0.25)).unwrap();
+        assert!(field.magnitude() < 1e-15);
+    }
+}
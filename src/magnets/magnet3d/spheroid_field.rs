@@ -0,0 +1,196 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/.
+Copyright 2021 Peter Dunne */
+
+//! 3D Magnetic Field Routines for spheroids
+//!
+//! A uniformly magnetised ellipsoid has a uniform interior field, set by its
+//! demagnetising factors, and an exterior field given in closed form in terms
+//! of the confocal ellipsoidal coordinate $`\lambda`$ of the point, the
+//! largest root of
+//! ```math
+//! \frac{x^2 + y^2}{a^2 + \lambda} + \frac{z^2}{c^2 + \lambda} = 1
+//! ```
+//! for a spheroid with equatorial semi-axis `a` and polar semi-axis `c`.
+
+use crate::magnets::magnet3d::Spheroid;
+use crate::utils::points3::Point3;
+use std::error::Error;
+
+/// Below this value of $`|\eta|`$ the spheroid integrals are evaluated from
+/// their power series, avoiding the cancellation of the closed forms close to
+/// a sphere
+const SERIES_CUTOFF: f64 = 0.1;
+
+/// Number of terms of the power series, enough for double precision at the
+/// cutoff
+const SERIES_TERMS: usize = 20;
+
+/// Calculates the magnetic field of a uniformly magnetised spheroid centered
+/// at the origin.
+///
+/// Inside, $`\mathbf{B} = \mathbf{J} - \mathsf{N}\mathbf{J}`$ with the
+/// diagonal demagnetising tensor $`\mathsf{N} = (N_x, N_x, N_z)`$. Outside,
+/// the field is that of the scalar potential
+/// ```math
+/// \psi = \frac{a^2 c}{2 \mu_0} \sum_i J_i x_i I_i(\lambda), \qquad
+/// I_i(\lambda) = \int_\lambda^\infty \frac{ds}{(a_i^2 + s)\sqrt{(a^2 + s)^2(c^2 + s)}}
+/// ```
+/// which reduces to the interior field at $`\lambda = 0`$ and to a point
+/// dipole far away.
+pub fn get_field_spheroid(magnet: &Spheroid, point: &Point3) -> Result<Point3, Box<dyn Error>> {
+    let a = magnet.equatorial_radius;
+    let c = magnet.polar_radius;
+    if !(a > 0.0 && c > 0.0) {
+        return Err(format!("spheroid radii must be positive, got {} and {}", a, c).into());
+    }
+
+    let j = Point3::new(magnet.jx, magnet.jy, magnet.jz);
+    let (x, y, z) = (point.x, point.y, point.z);
+    let rho_sq = x.powi(2) + y.powi(2);
+    let prefactor = a.powi(2) * c / 2.0;
+
+    if rho_sq / a.powi(2) + z.powi(2) / c.powi(2) < 1.0 {
+        let (n_x, n_z) = magnet.demag_factors();
+        return Ok(Point3::new(
+            j.x * (1.0 - n_x),
+            j.y * (1.0 - n_x),
+            j.z * (1.0 - n_z),
+        ));
+    }
+
+    let lambda = ellipsoidal_coordinate(a, c, rho_sq, z.powi(2));
+    let (i_x, i_z) = spheroid_integrals(a, c, lambda);
+
+    let q_a = a.powi(2) + lambda;
+    let q_c = c.powi(2) + lambda;
+    let delta = q_a * q_c.sqrt();
+
+    // Derivative of psi through lambda, grad(lambda) = 2 x_i / (a_i^2 + lambda) / T
+    let s = (j.x * x + j.y * y) / q_a + j.z * z / q_c;
+    let t = rho_sq / q_a.powi(2) + z.powi(2) / q_c.powi(2);
+    let k = 2.0 * s / (delta * t);
+
+    Ok(Point3::new(
+        -prefactor * (j.x * i_x - k * x / q_a),
+        -prefactor * (j.y * i_x - k * y / q_a),
+        -prefactor * (j.z * i_z - k * z / q_c),
+    ))
+}
+
+/// Returns the largest root $`\lambda \geq 0`$ of
+/// $`\rho^2/(a^2 + \lambda) + z^2/(c^2 + \lambda) = 1`$ for a point on or
+/// outside the spheroid
+fn ellipsoidal_coordinate(a: f64, c: f64, rho_sq: f64, z_sq: f64) -> f64 {
+    let (a_sq, c_sq) = (a.powi(2), c.powi(2));
+    let p = a_sq + c_sq - rho_sq - z_sq;
+    let q = a_sq * c_sq - rho_sq * c_sq - z_sq * a_sq;
+    let root = (p.powi(2) - 4.0 * q).max(0.0).sqrt();
+
+    let lambda = if p > 0.0 {
+        -2.0 * q / (p + root)
+    } else {
+        (root - p) / 2.0
+    };
+    lambda.max(0.0)
+}
+
+/// Returns the integrals `(I_x, I_z)` of [`get_field_spheroid`] for a
+/// spheroid with semi-axes `a` (equatorial) and `c` (polar).
+///
+/// With $`C = c^2 + \lambda`$ and $`\eta = (a^2 - c^2)/C`$,
+/// ```math
+/// I_x = \frac{2}{C^{3/2}} \frac{g(\eta) - 1/(1 + \eta)}{2\eta}, \qquad
+/// I_z = \frac{2}{C^{3/2}} \frac{1 - g(\eta)}{\eta}
+/// ```
+/// where $`g(\eta) = \tan^{-1}(\sqrt{\eta})/\sqrt{\eta}`$ for oblate and
+/// $`\tanh^{-1}(\sqrt{-\eta})/\sqrt{-\eta}`$ for prolate spheroids.
+pub fn spheroid_integrals(a: f64, c: f64, lambda: f64) -> (f64, f64) {
+    let q_c = c.powi(2) + lambda;
+    let eta = (a.powi(2) - c.powi(2)) / q_c;
+    let scale = 2.0 / q_c.powf(1.5);
+
+    let (f_x, f_z) = if eta.abs() < SERIES_CUTOFF {
+        // g = sum (-eta)^n / (2n + 1)
+        let mut f_x = 0.0;
+        let mut f_z = 0.0;
+        let mut power = 1.0;
+        for n in 0..SERIES_TERMS {
+            let n = n as f64;
+            f_x += (n + 1.0) * power / (2.0 * n + 3.0);
+            f_z += power / (2.0 * n + 3.0);
+            power *= -eta;
+        }
+        (f_x, f_z)
+    } else {
+        let g = if eta > 0.0 {
+            eta.sqrt().atan() / eta.sqrt()
+        } else {
+            (-eta).sqrt().atanh() / (-eta).sqrt()
+        };
+        ((g - 1.0 / (1.0 + eta)) / (2.0 * eta), (1.0 - g) / eta)
+    };
+
+    (scale * f_x, scale * f_z)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::magnets::magnet3d::dipole_field::get_field_dipole;
+    use crate::magnets::magnet3d::{Magnet3D, Sphere, Spheroid};
+    use crate::utils::comparison::nearly_equal;
+    use crate::utils::points3::{Point3, Points3};
+    use crate::PI;
+
+    #[test]
+    fn equal_radii_match_sphere() {
+        let sphere = Sphere::new(0.7, (0.1, -0.2, 0.3), 1.2, 35.0, 20.0);
+        let spheroid = Spheroid::new(0.7, 0.7, (0.1, -0.2, 0.3), 1.2, 35.0, 20.0);
+
+        for point in &[
+            Point3::new(0.2, 0.0, 0.4),
+            Point3::new(1.5, -0.3, 0.8),
+            Point3::new(0.1, -0.2, 1.0),
+            Point3::new(-2.0, 3.0, -1.0),
+        ] {
+            let expected = sphere.get_field(point).unwrap();
+            let field = spheroid.get_field(point).unwrap();
+            assert!((field - expected).magnitude() < 1e-14);
+        }
+    }
+
+    #[test]
+    fn demag_factors_sum_to_one() {
+        for (a, c) in &[(1.0, 1.0), (1.0, 1.05), (1.0, 5.0), (3.0, 0.2)] {
+            let (n_x, n_z) = Spheroid::new(*a, *c, (0.0, 0.0, 0.0), 1.0, 0.0, 0.0).demag_factors();
+            assert!(nearly_equal(2.0 * n_x + n_z, 1.0));
+        }
+
+        // Prolate spheroid with c = 2a, N_z = (2 ln(2 + sqrt 3)/sqrt 3 - 1) / 3
+        let (_, n_z) = Spheroid::new(1.0, 2.0, (0.0, 0.0, 0.0), 1.0, 0.0, 0.0).demag_factors();
+        let expected = (2.0 * (2.0 + 3.0_f64.sqrt()).ln() / 3.0_f64.sqrt() - 1.0) / 3.0;
+        assert!(nearly_equal(n_z, expected));
+    }
+
+    #[test]
+    fn normal_field_continuous_at_pole() {
+        let magnet = Spheroid::new(0.5, 1.5, (0.0, 0.0, 0.0), 1.0, 0.0, 0.0);
+        let (_, n_z) = magnet.demag_factors();
+        let field = magnet
+            .get_field(&Point3::new(0.0, 0.0, 1.5 + 1e-12))
+            .unwrap();
+        assert!((field.z - (1.0 - n_z)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn far_field_is_dipole() {
+        let magnet = Spheroid::new(2.0, 0.5, (0.0, 0.0, 0.0), 1.0, 60.0, 10.0);
+        let point = Point3::new(40.0, -30.0, 50.0);
+        let volume = 4.0 * PI * 2.0_f64.powi(2) * 0.5 / 3.0;
+        let moment = Point3::new(magnet.jx, magnet.jy, magnet.jz).scale(volume);
+        let dipole = get_field_dipole(&moment, &point);
+        let field = magnet.get_field(&point).unwrap();
+        assert!((field - dipole).magnitude() < 1e-3 * dipole.magnitude());
+    }
+}
@@ -0,0 +1,85 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/.
+Copyright 2021 Peter Dunne */
+
+//! 3D Magnetic Field Routines for uniformly magnetised tori
+//!
+//! The torus volume is split into cells in its toroidal coordinates, and the
+//! field approximated by the sum of the point dipoles of the cells.
+
+use crate::magnets::magnet3d::dipole_field::get_field_dipole;
+use crate::magnets::magnet3d::Torus;
+use crate::utils::points3::{Point3, Points3};
+use crate::PI;
+use std::error::Error;
+
+/// Calculates the magnetic field of a uniformly magnetised torus, at a point
+/// relative to its center.
+///
+/// The torus is divided into `magnet.resolution` cells in each of the
+/// toroidal angle, the poloidal angle and the distance from the tube centre,
+/// with a dipole $`\mathbf{J}\,dV`$ at the midpoint of each. The cell volumes
+/// add up to the exact volume, so the far field is that of the net moment
+/// $`\mathbf{J} V`$, but the approximation breaks down within a few cell
+/// sizes of the torus, and inside it.
+pub fn get_field_torus(magnet: &Torus, point: &Point3) -> Result<Point3, Box<dyn Error>> {
+    let n = magnet.resolution;
+    if n == 0 {
+        return Err("torus resolution must be at least 1".into());
+    }
+
+    let j = Point3::new(magnet.jx, magnet.jy, magnet.jz);
+    let (major, minor) = (magnet.major_radius, magnet.minor_radius);
+    let d_angle = 2.0 * PI / n as f64;
+    let d_s = minor / n as f64;
+
+    let mut field = Point3::zero();
+    for k in 0..n {
+        let toroidal = (k as f64 + 0.5) * d_angle;
+        let (sin_t, cos_t) = toroidal.sin_cos();
+        for l in 0..n {
+            let poloidal = (l as f64 + 0.5) * d_angle;
+            let (sin_p, cos_p) = poloidal.sin_cos();
+            for m in 0..n {
+                let s = (m as f64 + 0.5) * d_s;
+                let rho = major + s * cos_p;
+                let volume = rho * s * d_s * d_angle * d_angle;
+                let source = Point3::new(rho * cos_t, rho * sin_t, s * sin_p);
+                field = field + get_field_dipole(&j.scale(volume), &(*point - source));
+            }
+        }
+    }
+
+    Ok(field)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::magnets::magnet3d::dipole_field::get_field_dipole;
+    use crate::magnets::magnet3d::{Magnet3D, Torus};
+    use crate::utils::points3::{Point3, Points3};
+
+    #[test]
+    fn far_field_is_net_dipole() {
+        let magnet = Torus::new((0.5, -0.5, 1.0), 1.0, 0.3, 1.0, 60.0, 30.0);
+        let offset = Point3::new(30.0, -20.0, 40.0);
+        let moment = Point3::new(magnet.jx, magnet.jy, magnet.jz).scale(magnet.volume());
+        let dipole = get_field_dipole(&moment, &offset);
+        let field = magnet.get_field(&(magnet.center + offset)).unwrap();
+        assert!((field - dipole).magnitude() < 1e-3 * dipole.magnitude());
+    }
+
+    #[test]
+    fn axial_field_at_center_opposes_axial_magnetisation() {
+        // At the centre of the hole the return flux of an axially magnetised
+        // torus points against J, and converges with resolution
+        let mut magnet = Torus::new((0.0, 0.0, 0.0), 1.0, 0.3, 1.0, 0.0, 0.0);
+        let coarse = magnet.get_field(&Point3::zero()).unwrap();
+        magnet.resolution = 24;
+        let fine = magnet.get_field(&Point3::zero()).unwrap();
+
+        assert!(fine.z < 0.0 && fine.x.abs() < 1e-12 && fine.y.abs() < 1e-12);
+        assert!((fine - coarse).magnitude() < 1e-3 * fine.magnitude());
+    }
+}
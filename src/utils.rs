@@ -4,12 +4,24 @@ file, You can obtain one at https://mozilla.org/MPL/2.0/.
 Copyright 2021 Peter Dunne */
 //!
 //! The utils module contains utilities to generate points structs,
-//! convert between different coordinates, and quaternion routines for rotation
+//! convert between different coordinates, complex numbers and Fourier
+//! transforms, quaternion routines for rotation, dense linear algebra, numerical
+//! quadrature and minimisation, seedable random sampling, lookup table
+//! approximations of transcendental functions, and interval arithmetic
 //!
 
 pub mod comparison;
+pub mod complex;
 pub mod conversions;
+#[cfg(feature = "interval")]
+pub mod interval;
+pub mod linalg;
+#[cfg(feature = "lut")]
+pub mod lut;
+pub mod optimize;
 pub mod points;
 pub mod points2;
 pub mod points3;
+pub mod quadrature;
 pub mod quaternion;
+pub mod random;
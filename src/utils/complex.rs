@@ -0,0 +1,195 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/.
+Copyright 2021 Peter Dunne */
+//! Complex numbers and discrete Fourier transforms
+//!
+
+use std::ops::{Add, AddAssign, Div, Mul, Neg, Sub};
+
+use crate::PI;
+
+/// A complex number `re + i im`
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Complex {
+    pub re: f64,
+    pub im: f64,
+}
+
+impl Complex {
+    pub fn new(re: f64, im: f64) -> Complex {
+        Complex { re, im }
+    }
+
+    /// Returns $`r e^{i \phi}`$
+    pub fn from_polar(r: f64, phi: f64) -> Complex {
+        Complex {
+            re: r * phi.cos(),
+            im: r * phi.sin(),
+        }
+    }
+
+    pub fn conj(&self) -> Complex {
+        Complex {
+            re: self.re,
+            im: -self.im,
+        }
+    }
+
+    pub fn norm(&self) -> f64 {
+        self.re.hypot(self.im)
+    }
+
+    pub fn norm_squared(&self) -> f64 {
+        self.re.powi(2) + self.im.powi(2)
+    }
+
+    pub fn arg(&self) -> f64 {
+        self.im.atan2(self.re)
+    }
+
+    /// Returns the principal value of the natural logarithm, with the
+    /// imaginary part in $`(-\pi, \pi]`$
+    pub fn ln(&self) -> Complex {
+        Complex {
+            re: self.norm().ln(),
+            im: self.arg(),
+        }
+    }
+
+    pub fn scale(&self, s: f64) -> Complex {
+        Complex {
+            re: self.re * s,
+            im: self.im * s,
+        }
+    }
+}
+
+impl Add for Complex {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self::Output {
+        Complex::new(self.re + other.re, self.im + other.im)
+    }
+}
+
+impl AddAssign for Complex {
+    fn add_assign(&mut self, other: Self) {
+        *self = *self + other;
+    }
+}
+
+impl Sub for Complex {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self::Output {
+        Complex::new(self.re - other.re, self.im - other.im)
+    }
+}
+
+impl Mul for Complex {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self::Output {
+        Complex::new(
+            self.re * other.re - self.im * other.im,
+            self.re * other.im + self.im * other.re,
+        )
+    }
+}
+
+impl Div for Complex {
+    type Output = Self;
+
+    fn div(self, other: Self) -> Self::Output {
+        let denominator = other.norm_squared();
+        Complex::new(
+            (self.re * other.re + self.im * other.im) / denominator,
+            (self.im * other.re - self.re * other.im) / denominator,
+        )
+    }
+}
+
+impl Neg for Complex {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        Complex::new(-self.re, -self.im)
+    }
+}
+
+/// Returns the discrete Fourier transform
+/// $`X_k = \sum_n x_n e^{-2 \pi i k n / N}`$ of `signal`.
+///
+/// Uses a radix-2 fast Fourier transform when the length is a power of two,
+/// and the direct $`O(N^2)`$ sum otherwise.
+pub fn dft(signal: &[Complex]) -> Vec<Complex> {
+    let n = signal.len();
+    if n <= 1 {
+        return signal.to_vec();
+    }
+
+    if n.is_power_of_two() {
+        let even: Vec<Complex> = signal.iter().step_by(2).copied().collect();
+        let odd: Vec<Complex> = signal.iter().skip(1).step_by(2).copied().collect();
+        let even = dft(&even);
+        let odd = dft(&odd);
+
+        let mut spectrum = vec![Complex::new(0.0, 0.0); n];
+        for k in 0..n / 2 {
+            let twiddle = Complex::from_polar(1.0, -2.0 * PI * k as f64 / n as f64) * odd[k];
+            spectrum[k] = even[k] + twiddle;
+            spectrum[k + n / 2] = even[k] - twiddle;
+        }
+        spectrum
+    } else {
+        (0..n)
+            .map(|k| {
+                let mut sum = Complex::new(0.0, 0.0);
+                for (j, x) in signal.iter().enumerate() {
+                    sum += *x * Complex::from_polar(1.0, -2.0 * PI * (k * j) as f64 / n as f64);
+                }
+                sum
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::complex::{dft, Complex};
+    use crate::PI;
+
+    #[test]
+    fn complex_division() {
+        let z = Complex::new(1.0, 2.0) * Complex::new(-0.5, 3.0);
+        let w = z / Complex::new(-0.5, 3.0);
+        assert!((w - Complex::new(1.0, 2.0)).norm() < 1e-15);
+    }
+
+    #[test]
+    fn fft_matches_direct_dft() {
+        let signal: Vec<Complex> = (0..16)
+            .map(|i| Complex::new((0.3 * i as f64).sin(), (i as f64).sqrt()))
+            .collect();
+        let spectrum = dft(&signal);
+        for (k, x_k) in spectrum.iter().enumerate() {
+            let mut direct = Complex::new(0.0, 0.0);
+            for (j, x) in signal.iter().enumerate() {
+                direct += *x * Complex::from_polar(1.0, -2.0 * PI * (k * j) as f64 / 16.0);
+            }
+            assert!((*x_k - direct).norm() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn dft_of_cosine() {
+        let n = 12;
+        let signal: Vec<Complex> = (0..n)
+            .map(|i| Complex::new((2.0 * PI * 2.0 * i as f64 / n as f64).cos(), 0.0))
+            .collect();
+        let spectrum = dft(&signal);
+        assert!((spectrum[2].norm() - 6.0).abs() < 1e-12);
+        assert!(spectrum[1].norm() < 1e-12);
+    }
+}
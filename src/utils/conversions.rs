@@ -11,7 +11,7 @@ pub fn cart2pol(point: Point2) -> PolarPoint {
 pub fn pol2cart(point: PolarPoint) -> Point2 {
     let x = point.rho * point.phi.cos();
     let y = point.rho * point.phi.sin();
-    Point2 { x: x, y: y }
+    Point2 { x, y }
 }
 
 /// Converts polar vectors to cartesian vectors
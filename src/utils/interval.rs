@@ -0,0 +1,293 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/.
+Copyright 2021 Peter Dunne */
+//! Interval arithmetic
+//!
+//! Closed intervals of reals with outward rounding, so the result of each
+//! operation contains the exact result for every choice of operands in the
+//! operand intervals. The basic operations are correctly rounded in IEEE 754
+//! and widened by one ulp. `ln` and `atan2` come from the platform's maths
+//! library, which is not required to round correctly, and are widened by
+//! [`LIBM_ULPS`] instead.
+
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+use crate::PI;
+
+/// Number of ulps by which the results of `ln` and `atan2` are widened,
+/// comfortably above the error of common maths libraries, which is below one
+/// ulp
+pub const LIBM_ULPS: usize = 4;
+
+/// A closed interval `[lo, hi]`
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Interval {
+    pub lo: f64,
+    pub hi: f64,
+}
+
+/// Returns `[lo, hi]` widened outwards by `ulps` ulps
+fn widen(lo: f64, hi: f64, ulps: usize) -> Interval {
+    let mut interval = Interval { lo, hi };
+    for _ in 0..ulps {
+        interval.lo = next_down(interval.lo);
+        interval.hi = next_up(interval.hi);
+    }
+    interval
+}
+
+/// Returns the least `f64` greater than `x`, as the recent `f64::next_up`
+fn next_up(x: f64) -> f64 {
+    if x.is_nan() || x == f64::INFINITY {
+        return x;
+    }
+    if x == 0.0 {
+        // The smallest positive subnormal, from either sign of zero
+        return f64::from_bits(1);
+    }
+    let bits = x.to_bits();
+    f64::from_bits(if x > 0.0 { bits + 1 } else { bits - 1 })
+}
+
+/// Returns the greatest `f64` less than `x`, as the recent `f64::next_down`
+fn next_down(x: f64) -> f64 {
+    -next_up(-x)
+}
+
+impl Interval {
+    pub fn new(lo: f64, hi: f64) -> Interval {
+        Interval { lo, hi }
+    }
+
+    /// Returns the degenerate interval `[x, x]`, exact for a value that is
+    /// representable
+    pub fn point(x: f64) -> Interval {
+        Interval { lo: x, hi: x }
+    }
+
+    /// Returns the smallest interval containing the real number nearest to
+    /// `x`, for constants such as $`1/2\pi`$ that were rounded to `x`
+    pub fn rounded(x: f64) -> Interval {
+        widen(x, x, 1)
+    }
+
+    pub fn width(&self) -> f64 {
+        self.hi - self.lo
+    }
+
+    pub fn contains(&self, x: f64) -> bool {
+        self.lo <= x && x <= self.hi
+    }
+
+    pub fn contains_zero(&self) -> bool {
+        self.contains(0.0)
+    }
+
+    pub fn is_finite(&self) -> bool {
+        self.lo.is_finite() && self.hi.is_finite()
+    }
+
+    /// Returns the interval of squares, which is non-negative even when the
+    /// interval contains 0, unlike `self * self`
+    pub fn sqr(&self) -> Interval {
+        let (small, large) = (
+            self.lo.abs().min(self.hi.abs()),
+            self.lo.abs().max(self.hi.abs()),
+        );
+        let small = if self.contains_zero() { 0.0 } else { small };
+        widen(small * small, large * large, 1)
+    }
+
+    /// Returns the interval of natural logarithms, unbounded below if the
+    /// interval reaches 0
+    pub fn ln(&self) -> Interval {
+        let lo = if self.lo > 0.0 {
+            self.lo.ln()
+        } else {
+            f64::NEG_INFINITY
+        };
+        widen(lo, self.hi.ln(), LIBM_ULPS)
+    }
+
+    /// Returns the interval of `y.atan2(x)` for `y` in `self` and `x` in `x`.
+    ///
+    /// Where the box of arguments does not touch the origin or the branch
+    /// cut along the negative x axis, the angle is continuous over it and is
+    /// extreme at its corners. Otherwise the result is the whole range
+    /// $`[-\pi, \pi]`$.
+    pub fn atan2(&self, x: Interval) -> Interval {
+        if x.lo <= 0.0 && self.contains_zero() {
+            return widen(-PI, PI, 1);
+        }
+
+        let corners = [
+            self.lo.atan2(x.lo),
+            self.lo.atan2(x.hi),
+            self.hi.atan2(x.lo),
+            self.hi.atan2(x.hi),
+        ];
+        let lo = corners.iter().fold(f64::INFINITY, |m, c| m.min(*c));
+        let hi = corners.iter().fold(f64::NEG_INFINITY, |m, c| m.max(*c));
+        widen(lo, hi, LIBM_ULPS)
+    }
+
+    /// Returns the intervals of `sin` and `cos` over the interval, in
+    /// radians.
+    ///
+    /// Both are evaluated at the midpoint and widened by the half-width, as
+    /// neither changes faster than its argument, so this is tight for the
+    /// narrow intervals of a rounded angle. A degenerate interval at 0 is
+    /// exact.
+    pub fn sin_cos(&self) -> (Interval, Interval) {
+        if self.lo == 0.0 && self.hi == 0.0 {
+            return (Interval::point(0.0), Interval::point(1.0));
+        }
+
+        let mid = self.lo + (self.hi - self.lo) / 2.0;
+        let radius = next_up((mid - self.lo).max(self.hi - mid));
+        let (sin, cos) = mid.sin_cos();
+        let bound = |x: f64| {
+            let interval = widen(x - radius, x + radius, LIBM_ULPS);
+            Interval::new(interval.lo.max(-1.0), interval.hi.min(1.0))
+        };
+        (bound(sin), bound(cos))
+    }
+}
+
+impl Add for Interval {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        widen(self.lo + other.lo, self.hi + other.hi, 1)
+    }
+}
+
+impl Sub for Interval {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        widen(self.lo - other.hi, self.hi - other.lo, 1)
+    }
+}
+
+impl Mul for Interval {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        let products = [
+            self.lo * other.lo,
+            self.lo * other.hi,
+            self.hi * other.lo,
+            self.hi * other.hi,
+        ];
+        let lo = products.iter().fold(f64::INFINITY, |m, p| m.min(*p));
+        let hi = products.iter().fold(f64::NEG_INFINITY, |m, p| m.max(*p));
+        widen(lo, hi, 1)
+    }
+}
+
+impl Div for Interval {
+    type Output = Self;
+
+    /// Returns the whole real line if the divisor contains 0
+    fn div(self, other: Self) -> Self {
+        if other.contains_zero() {
+            return Interval::new(f64::NEG_INFINITY, f64::INFINITY);
+        }
+        let quotients = [
+            self.lo / other.lo,
+            self.lo / other.hi,
+            self.hi / other.lo,
+            self.hi / other.hi,
+        ];
+        let lo = quotients.iter().fold(f64::INFINITY, |m, q| m.min(*q));
+        let hi = quotients.iter().fold(f64::NEG_INFINITY, |m, q| m.max(*q));
+        widen(lo, hi, 1)
+    }
+}
+
+impl Neg for Interval {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Interval {
+            lo: -self.hi,
+            hi: -self.lo,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::interval::{next_down, next_up, Interval};
+    use crate::PI;
+
+    #[test]
+    fn neighbouring_floats() {
+        assert_eq!(1.0 + f64::EPSILON, next_up(1.0));
+        assert_eq!(1.0 - f64::EPSILON / 2.0, next_down(1.0));
+        assert_eq!(-1.0 - f64::EPSILON, next_down(-1.0));
+        assert_eq!(f64::from_bits(1), next_up(0.0));
+        assert_eq!(f64::from_bits(1), next_up(-0.0));
+        assert_eq!(-f64::from_bits(1), next_down(0.0));
+        assert_eq!(0.0, next_up(-f64::from_bits(1)));
+        assert_eq!(f64::INFINITY, next_up(f64::MAX));
+        assert_eq!(f64::MAX, next_down(f64::INFINITY));
+        assert_eq!(f64::INFINITY, next_up(f64::INFINITY));
+        assert!(next_up(f64::NAN).is_nan());
+    }
+
+    #[test]
+    fn sin_cos_encloses_angles() {
+        let (sin, cos) = Interval::point(0.0).sin_cos();
+        assert_eq!((Interval::point(0.0), Interval::point(1.0)), (sin, cos));
+
+        // 30 degrees, with the rounding of pi
+        let angle = Interval::point(30.0) * Interval::rounded(PI) / Interval::point(180.0);
+        let (sin, cos) = angle.sin_cos();
+        assert!(sin.contains(0.5) && cos.contains(0.75_f64.sqrt()));
+        assert!(sin.width() < 1e-14 && cos.width() < 1e-14);
+
+        let (sin, cos) = Interval::new(-0.1, 0.1).sin_cos();
+        assert!(sin.contains(0.1_f64.sin()) && sin.contains(-(0.1_f64.sin())));
+        assert!(cos.contains(1.0) && cos.contains(0.1_f64.cos()) && cos.hi <= 1.0);
+    }
+
+    #[test]
+    fn arithmetic_encloses_exact_results() {
+        // 0.1 is not representable, so 0.1 + 0.2 rounds away from 0.3
+        let sum = Interval::rounded(0.1) + Interval::rounded(0.2);
+        assert!(sum.contains(0.3) && sum.contains(0.1 + 0.2));
+        assert!(sum.width() < 1e-15);
+
+        let x = Interval::new(-1.0, 2.0);
+        let y = Interval::new(3.0, 4.0);
+        let product = x * y;
+        assert!(product.contains(-4.0) && product.contains(8.0));
+        assert!(!product.contains(-4.01) && !product.contains(8.01));
+
+        assert!(x.sqr().contains(0.0) && x.sqr().contains(4.0) && x.sqr().lo <= 0.0);
+        assert!((y / x).lo.is_infinite());
+        assert_eq!(Interval::new(-4.0, -3.0), -y);
+    }
+
+    #[test]
+    fn ln_and_atan2_enclose_samples() {
+        let x = Interval::new(0.5, 2.0);
+        let logs = x.ln();
+        for t in &[0.5, 0.7, 1.0, 1.9, 2.0] {
+            assert!(logs.contains(f64::ln(*t)));
+        }
+        assert!(Interval::new(0.0, 1.0).ln().lo.is_infinite());
+
+        // A box in the second quadrant, and one straddling the branch cut
+        let angles = Interval::new(1.0, 2.0).atan2(Interval::new(-3.0, -1.0));
+        for (y, x) in &[(1.0_f64, -3.0), (2.0, -1.0), (1.5, -2.0)] {
+            assert!(angles.contains(y.atan2(*x)));
+        }
+        assert!(angles.width() < PI / 2.0);
+        let cut = Interval::new(-1.0, 1.0).atan2(Interval::new(-2.0, -1.0));
+        assert!(cut.contains(-PI) && cut.contains(PI));
+    }
+}
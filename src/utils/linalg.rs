@@ -0,0 +1,156 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/.
+Copyright 2021 Peter Dunne */
+//! Dense linear algebra
+//!
+//! Small direct solvers for the linear systems of fitting and of magnets in
+//! linear media. Matrices are stored as a vector of rows.
+
+/// Relative size of a pivot below which a matrix is treated as singular
+const SINGULAR_TOLERANCE: f64 = 1e-13;
+
+/// Solves the square system $`A\mathbf{x} = \mathbf{b}`$ by Gaussian
+/// elimination with partial pivoting.
+///
+/// Returns `None` if the matrix is not square, does not match `rhs`, or is
+/// numerically singular.
+pub fn solve(matrix: &[Vec<f64>], rhs: &[f64]) -> Option<Vec<f64>> {
+    let n = rhs.len();
+    if matrix.len() != n || matrix.iter().any(|row| row.len() != n) {
+        return None;
+    }
+
+    let mut a = matrix.to_vec();
+    let mut b = rhs.to_vec();
+    let scale = a
+        .iter()
+        .flatten()
+        .fold(0.0_f64, |max, value| max.max(value.abs()));
+
+    for k in 0..n {
+        let pivot = (k..n).max_by(|&i, &j| a[i][k].abs().total_cmp(&a[j][k].abs()))?;
+        if a[pivot][k].is_nan() || a[pivot][k].abs() <= SINGULAR_TOLERANCE * scale {
+            return None;
+        }
+        a.swap(k, pivot);
+        b.swap(k, pivot);
+
+        let (upper, lower) = a.split_at_mut(k + 1);
+        let pivot_row = &upper[k];
+        let b_k = b[k];
+        for (row, b_i) in lower.iter_mut().zip(b[k + 1..].iter_mut()) {
+            let factor = row[k] / pivot_row[k];
+            for (a_ij, a_kj) in row[k..].iter_mut().zip(&pivot_row[k..]) {
+                *a_ij -= factor * a_kj;
+            }
+            *b_i -= factor * b_k;
+        }
+    }
+
+    let mut x = vec![0.0; n];
+    for k in (0..n).rev() {
+        let sum: f64 = (k + 1..n).map(|j| a[k][j] * x[j]).sum();
+        x[k] = (b[k] - sum) / a[k][k];
+    }
+    Some(x)
+}
+
+/// Returns the least squares solution of the overdetermined system
+/// $`A\mathbf{x} \approx \mathbf{b}`$, minimising
+/// $`|A\mathbf{x} - \mathbf{b}|^2`$, by Householder QR decomposition.
+///
+/// Unlike the normal equations, QR does not square the condition number of
+/// `matrix`. Returns `None` if there are fewer rows than columns, the rows do
+/// not match `rhs`, or the columns are numerically linearly dependent.
+pub fn least_squares(matrix: &[Vec<f64>], rhs: &[f64]) -> Option<Vec<f64>> {
+    let rows = rhs.len();
+    let cols = matrix.first().map_or(0, |row| row.len());
+    if matrix.len() != rows || rows < cols || matrix.iter().any(|row| row.len() != cols) {
+        return None;
+    }
+
+    let mut a = matrix.to_vec();
+    let mut b = rhs.to_vec();
+    let scale = a
+        .iter()
+        .flatten()
+        .fold(0.0_f64, |max, value| max.max(value.abs()));
+
+    for k in 0..cols {
+        let norm = (k..rows).map(|i| a[i][k].powi(2)).sum::<f64>().sqrt();
+        if norm.is_nan() || norm <= SINGULAR_TOLERANCE * scale {
+            return None;
+        }
+
+        // Reflect column k onto -sign(a_kk) |a_k| e_k, with the reflection
+        // I - 2 v v^T / |v|^2 applied to the remaining columns and to b
+        let alpha = if a[k][k] > 0.0 { -norm } else { norm };
+        let mut v: Vec<f64> = (k..rows).map(|i| a[i][k]).collect();
+        v[0] -= alpha;
+        let v_norm_sq: f64 = v.iter().map(|v_i| v_i.powi(2)).sum();
+
+        let mut factors = vec![0.0; cols - k];
+        let mut b_factor = 0.0;
+        for ((row, b_i), v_i) in a[k..].iter().zip(&b[k..]).zip(&v) {
+            for (factor, a_ij) in factors.iter_mut().zip(&row[k..]) {
+                *factor += v_i * a_ij;
+            }
+            b_factor += v_i * b_i;
+        }
+        for factor in factors.iter_mut() {
+            *factor *= 2.0 / v_norm_sq;
+        }
+        b_factor *= 2.0 / v_norm_sq;
+
+        for ((row, b_i), v_i) in a[k..].iter_mut().zip(b[k..].iter_mut()).zip(&v) {
+            for (a_ij, factor) in row[k..].iter_mut().zip(&factors) {
+                *a_ij -= factor * v_i;
+            }
+            *b_i -= b_factor * v_i;
+        }
+    }
+
+    let mut x = vec![0.0; cols];
+    for k in (0..cols).rev() {
+        let sum: f64 = (k + 1..cols).map(|j| a[k][j] * x[j]).sum();
+        x[k] = (b[k] - sum) / a[k][k];
+    }
+    Some(x)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::linalg::{least_squares, solve};
+
+    #[test]
+    fn solve_needs_pivoting() {
+        let matrix = vec![
+            vec![0.0, 2.0, 1.0],
+            vec![1.0, 1.0, 0.0],
+            vec![3.0, 0.0, 1.0],
+        ];
+        let x = solve(&matrix, &[7.0, 3.0, 6.0]).unwrap();
+        for (x_i, expected) in x.iter().zip(&[1.0, 2.0, 3.0]) {
+            assert!((x_i - expected).abs() < 1e-14);
+        }
+        assert!(solve(&[vec![1.0, 2.0], vec![2.0, 4.0]], &[1.0, 2.0]).is_none());
+    }
+
+    #[test]
+    fn least_squares_line_fit() {
+        // y = 2 + 0.5 x with symmetric noise, which cancels in the fit
+        let xs = [0.0, 1.0, 2.0, 3.0];
+        let noise = [0.1, -0.1, -0.1, 0.1];
+        let matrix: Vec<Vec<f64>> = xs.iter().map(|x| vec![1.0, *x]).collect();
+        let rhs: Vec<f64> = xs
+            .iter()
+            .zip(&noise)
+            .map(|(x, e)| 2.0 + 0.5 * x + e)
+            .collect();
+
+        let fit = least_squares(&matrix, &rhs).unwrap();
+        assert!((fit[0] - 2.0).abs() < 1e-14 && (fit[1] - 0.5).abs() < 1e-14);
+        assert!(least_squares(&matrix[..1], &rhs[..1]).is_none());
+    }
+}
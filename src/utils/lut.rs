@@ -0,0 +1,114 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/.
+Copyright 2021 Peter Dunne */
+//! Lookup table approximations of transcendental functions
+//!
+//! For targets where `atan2` and `ln` are expensive, e.g. microcontrollers
+//! without a floating point library in hardware. Each function reduces its
+//! argument to a fixed interval and linearly interpolates a table of
+//! `TABLE_SIZE` entries, built on first use.
+
+use crate::PI;
+use std::f64::consts::LN_2;
+use std::sync::OnceLock;
+
+/// Number of entries in each lookup table
+pub const TABLE_SIZE: usize = 1025;
+
+/// Largest absolute error, in rad, of [`fast_atan2`]
+pub const ATAN2_MAX_ERROR: f64 = 1e-7;
+
+/// Largest absolute error of [`fast_ln`]
+pub const LN_MAX_ERROR: f64 = 2e-7;
+
+static ATAN_TABLE: OnceLock<Vec<f64>> = OnceLock::new();
+static LN_TABLE: OnceLock<Vec<f64>> = OnceLock::new();
+
+/// Samples `f` at `TABLE_SIZE` points evenly spanning `[0, 1]`
+fn build_table(f: fn(f64) -> f64) -> Vec<f64> {
+    (0..TABLE_SIZE)
+        .map(|i| f(i as f64 / (TABLE_SIZE - 1) as f64))
+        .collect()
+}
+
+/// Linearly interpolates `table` at `t` in `[0, 1]`
+fn lookup(table: &[f64], t: f64) -> f64 {
+    let position = t * (TABLE_SIZE - 1) as f64;
+    let i = (position as usize).min(TABLE_SIZE - 2);
+    let fraction = position - i as f64;
+    table[i] + fraction * (table[i + 1] - table[i])
+}
+
+/// Returns an approximation of `y.atan2(x)` to within [`ATAN2_MAX_ERROR`]
+///
+/// The argument is reduced by symmetry to $`\tan^{-1} t`$ with
+/// $`0 \le t \le 1`$. Signed zeros and infinities are handled like `atan2`.
+pub fn fast_atan2(y: f64, x: f64) -> f64 {
+    let (x_abs, y_abs) = (x.abs(), y.abs());
+    let t = x_abs.min(y_abs) / x_abs.max(y_abs);
+    if t.is_nan() {
+        // 0 / 0, inf / inf and NaN inputs
+        return y.atan2(x);
+    }
+
+    let table = ATAN_TABLE.get_or_init(|| build_table(f64::atan));
+    let mut angle = lookup(table, t);
+    if y_abs > x_abs {
+        angle = PI / 2.0 - angle;
+    }
+    if x.is_sign_negative() {
+        angle = PI - angle;
+    }
+    if y.is_sign_negative() {
+        angle = -angle;
+    }
+    angle
+}
+
+/// Returns an approximation of `x.ln()` to within [`LN_MAX_ERROR`]
+///
+/// Writing $`x = m \, 2^e`$ with $`1 \le m < 2`$ from the bits of `x`, the
+/// result is $`e \ln 2 + \ln m`$. Zero, negative, subnormal and non-finite
+/// arguments fall back to `ln`.
+pub fn fast_ln(x: f64) -> f64 {
+    let bits = x.to_bits();
+    let exponent = ((bits >> 52) & 0x7ff) as i64;
+    if x <= 0.0 || exponent == 0 || exponent == 0x7ff {
+        return x.ln();
+    }
+
+    let mantissa = f64::from_bits((bits & 0x000f_ffff_ffff_ffff) | (1023 << 52));
+    let table = LN_TABLE.get_or_init(|| build_table(f64::ln_1p));
+    (exponent - 1023) as f64 * LN_2 + lookup(table, mantissa - 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::lut::{fast_atan2, fast_ln, ATAN2_MAX_ERROR, LN_MAX_ERROR};
+
+    #[test]
+    fn atan2_within_tolerance() {
+        for i in 0..720 {
+            let phi = (i as f64 + 0.3) * 0.5_f64.to_radians();
+            for r in &[1e-8, 0.7, 3e5] {
+                let (y, x) = (r * phi.sin(), r * phi.cos());
+                assert!((fast_atan2(y, x) - y.atan2(x)).abs() < ATAN2_MAX_ERROR);
+            }
+        }
+        for (y, x) in &[(0.0_f64, 0.0), (0.0, -0.0), (-0.0, -1.0), (1.0, 0.0)] {
+            assert_eq!(y.atan2(*x), fast_atan2(*y, *x));
+        }
+    }
+
+    #[test]
+    fn ln_within_tolerance() {
+        for i in 0..1000 {
+            let x = 1.0327_f64.powi(i - 500);
+            assert!((fast_ln(x) - x.ln()).abs() < LN_MAX_ERROR);
+        }
+        assert_eq!(0.0, fast_ln(1.0));
+        assert_eq!(f64::NEG_INFINITY, fast_ln(0.0));
+        assert!(fast_ln(-1.0).is_nan());
+    }
+}
@@ -0,0 +1,104 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/.
+Copyright 2021 Peter Dunne */
+//! Numerical minimisation
+//!
+
+use crate::errors::MagnetError;
+
+/// Inverse of the golden ratio, $`(\sqrt{5} - 1)/2`$
+const INV_GOLDEN_RATIO: f64 = 0.618_033_988_749_895;
+
+/// Returns the minimiser of `f` on $`[a, b]`$ by golden section search,
+/// stopping once the bracket is narrower than `tolerance`, or can no longer
+/// be narrowed in floating point.
+///
+/// The search converges to the minimum for a unimodal `f`, and to a local
+/// minimum otherwise. `NAN` values of `f` are treated as larger than any
+/// number. As `f` is flat at its minimum, the minimiser is only resolved to
+/// around the square root of the machine precision, so smaller tolerances do
+/// not improve it. Returns an error if the bracket is not finite or the
+/// tolerance is not positive.
+pub fn golden_section<F: Fn(f64) -> f64>(
+    f: F,
+    a: f64,
+    b: f64,
+    tolerance: f64,
+) -> Result<f64, MagnetError> {
+    if !(a.is_finite() && b.is_finite()) {
+        return Err(MagnetError::InvalidParameter(format!(
+            "bracket [{}, {}] must be finite",
+            a, b
+        )));
+    }
+    if tolerance.is_nan() || tolerance <= 0.0 {
+        return Err(MagnetError::InvalidParameter(format!(
+            "tolerance must be positive, got {}",
+            tolerance
+        )));
+    }
+
+    let value = |x: f64| {
+        let fx = f(x);
+        if fx.is_nan() {
+            f64::INFINITY
+        } else {
+            fx
+        }
+    };
+
+    let (mut a, mut b) = if a < b { (a, b) } else { (b, a) };
+    let mut x_1 = b - INV_GOLDEN_RATIO * (b - a);
+    let mut x_2 = a + INV_GOLDEN_RATIO * (b - a);
+    let mut f_1 = value(x_1);
+    let mut f_2 = value(x_2);
+
+    while b - a > tolerance {
+        // The probes have rounded onto the ends of the bracket
+        if !(a < x_1 && x_2 < b) {
+            break;
+        }
+        if f_1 < f_2 {
+            b = x_2;
+            x_2 = x_1;
+            f_2 = f_1;
+            x_1 = b - INV_GOLDEN_RATIO * (b - a);
+            f_1 = value(x_1);
+        } else {
+            a = x_1;
+            x_1 = x_2;
+            f_1 = f_2;
+            x_2 = a + INV_GOLDEN_RATIO * (b - a);
+            f_2 = value(x_2);
+        }
+    }
+
+    Ok((a + b) / 2.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::optimize::golden_section;
+
+    #[test]
+    fn minimum_of_parabola() {
+        let x = golden_section(|x| (x - 1.3).powi(2) + 2.0, -4.0, 5.0, 1e-10).unwrap();
+        assert!((x - 1.3).abs() < 1e-7);
+    }
+
+    #[test]
+    fn reversed_bracket() {
+        let x = golden_section(|x| x.cos(), 6.0, 2.0, 1e-10).unwrap();
+        assert!((x - std::f64::consts::PI).abs() < 1e-7);
+    }
+
+    #[test]
+    fn tolerance_below_float_resolution_terminates() {
+        let x = golden_section(|x| (x - 1e6).powi(2), 0.0, 2e6, 1e-300).unwrap();
+        assert!((x - 1e6).abs() < 1e-1);
+        assert!(golden_section(|x| x * x, -1.0, 1.0, 0.0).is_err());
+        assert!(golden_section(|x| x * x, -1.0, 1.0, f64::NAN).is_err());
+        assert!(golden_section(|x| x * x, -1.0, f64::INFINITY, 1e-6).is_err());
+    }
+}
@@ -62,8 +62,8 @@ impl fmt::Display for PolarPoint {
     }
 }
 
-/// # Points Traits
-/// Overloading of +-*/, as well as helper functions
+// # Points Traits
+// Overloading of +-*/, as well as helper functions
 
 /// Traits specific to Points2
 pub trait Points2 {
@@ -83,6 +83,8 @@ pub trait Points2 {
 
     fn dot(&self, other: &Self) -> f64;
     fn unit(&self) -> Self::Output;
+    /// Rotates anticlockwise about the origin by `angle` radians
+    fn rotate(&self, angle: f64) -> Self::Output;
     fn zero() -> Self::Output;
     fn identity() -> Self::Output;
     fn i_hat() -> Self::Output;
@@ -210,6 +212,14 @@ impl Points2 for Point2 {
         self.scale(1.0 / self.magnitude())
     }
 
+    fn rotate(&self, angle: f64) -> Point2 {
+        let (sin, cos) = angle.sin_cos();
+        Point2 {
+            x: self.x * cos - self.y * sin,
+            y: self.x * sin + self.y * cos,
+        }
+    }
+
     fn zero() -> Point2 {
         Point2 {
             x: 0.0_f64,
@@ -533,6 +543,13 @@ mod tests {
         assert_eq!(11.0, result);
     }
 
+    #[test]
+    fn rotate_point() {
+        let p1 = Point2 { x: 3.0, y: 4.0 };
+        let result = p1.rotate(crate::PI_2);
+        assert!(nearly_equal(result.x, -4.0) && nearly_equal(result.y, 3.0));
+    }
+
     #[test]
     fn unit_vector() {
         let p1 = Point2 { x: 3.0, y: 4.0 };
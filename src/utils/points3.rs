@@ -1,4 +1,5 @@
 // use crate::utils::points::Points;
+use crate::PI;
 use std::fmt;
 use std::ops::{Add, Div, Mul, Neg, Sub};
 
@@ -50,7 +51,11 @@ pub trait Points3 {
     fn distance_from_point(&self, other: &Self) -> f64;
 
     fn dot(&self, other: &Self) -> f64;
+    fn cross(&self, other: &Self) -> Self::Output;
     fn unit(&self) -> Self::Output;
+    /// Rotates about `axis` through the origin by `angle_deg` degrees,
+    /// anticlockwise when looking down the axis towards the origin
+    fn rotate_about_axis(&self, axis: Self, angle_deg: f64) -> Self::Output;
     fn zero() -> Self::Output;
     fn identity() -> Self::Output;
     fn i_hat() -> Self::Output;
@@ -174,10 +179,27 @@ impl Points3 for Point3 {
         self.x * other.x + self.y * other.y + self.z * other.z
     }
 
+    fn cross(&self, other: &Self) -> Point3 {
+        Point3 {
+            x: self.y * other.z - self.z * other.y,
+            y: self.z * other.x - self.x * other.z,
+            z: self.x * other.y - self.y * other.x,
+        }
+    }
+
     fn unit(&self) -> Point3 {
         self.scale(1.0 / self.magnitude())
     }
 
+    /// Uses Rodrigues' rotation formula,
+    /// $`\mathbf{v}_{rot} = \mathbf{v} \cos\theta + (\mathbf{k} \times \mathbf{v}) \sin\theta
+    /// + \mathbf{k} (\mathbf{k} \cdot \mathbf{v}) (1 - \cos\theta)`$
+    fn rotate_about_axis(&self, axis: Point3, angle_deg: f64) -> Point3 {
+        let k = axis.unit();
+        let (sin, cos) = (angle_deg * PI / 180.).sin_cos();
+        self.scale(cos) + k.cross(self).scale(sin) + k.scale(k.dot(self) * (1.0 - cos))
+    }
+
     fn zero() -> Point3 {
         Point3 {
             x: 0.0_f64,
@@ -460,6 +482,38 @@ mod tests {
         assert_eq!(26.0, result);
     }
 
+    #[test]
+    fn cross_product() {
+        let result = Point3::i_hat().cross(&Point3::j_hat());
+        assert_eq!(Point3::k_hat(), result);
+    }
+
+    #[test]
+    fn rotate_about_z_matches_2d_rotation() {
+        use crate::utils::points2::{Point2, Points2};
+
+        let p1 = Point3::new(3.0, 4.0, 5.0);
+        let result = p1.rotate_about_axis(Point3::k_hat().scale(2.0), 90.0);
+        let planar = Point2::new(3.0, 4.0).rotate(crate::PI_2);
+
+        assert!(
+            nearly_equal(result.x, planar.x)
+                && nearly_equal(result.y, planar.y)
+                && nearly_equal(result.z, 5.0)
+        );
+    }
+
+    #[test]
+    fn rotate_about_diagonal() {
+        // A third of a turn about (1, 1, 1) cycles the axes
+        let result = Point3::i_hat().rotate_about_axis(Point3::identity(), 120.0);
+        assert!(
+            nearly_equal(result.x, 0.0)
+                && nearly_equal(result.y, 1.0)
+                && nearly_equal(result.z, 0.0)
+        );
+    }
+
     #[test]
     fn unit_vector() {
         let p1 = Point3 {
@@ -0,0 +1,95 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/.
+Copyright 2021 Peter Dunne */
+//! Numerical quadrature
+//!
+
+use crate::PI;
+
+/// Returns the `n` nodes and weights of Gauss-Legendre quadrature on
+/// $`[-1, 1]`$, which integrates polynomials of degree up to `2n - 1`
+/// exactly.
+///
+/// The nodes are the roots of the Legendre polynomial $`P_n`$, found by
+/// Newton's method.
+pub fn gauss_legendre(n: usize) -> (Vec<f64>, Vec<f64>) {
+    let mut nodes = vec![0.0; n];
+    let mut weights = vec![0.0; n];
+
+    for i in 0..n.div_ceil(2) {
+        // Initial guess from the asymptotic form of the roots
+        let mut x = (PI * (i as f64 + 0.75) / (n as f64 + 0.5)).cos();
+        let mut derivative = 0.0;
+
+        for _ in 0..100 {
+            let (p_n, p_n_minus_1) = legendre(n, x);
+            derivative = n as f64 * (x * p_n - p_n_minus_1) / (x * x - 1.0);
+            let step = p_n / derivative;
+            x -= step;
+            if step.abs() < 1e-15 {
+                break;
+            }
+        }
+
+        let weight = 2.0 / ((1.0 - x * x) * derivative * derivative);
+        nodes[i] = -x;
+        nodes[n - 1 - i] = x;
+        weights[i] = weight;
+        weights[n - 1 - i] = weight;
+    }
+
+    (nodes, weights)
+}
+
+/// Returns $`(P_n(x), P_{n-1}(x))`$ from the three term recurrence
+fn legendre(n: usize, x: f64) -> (f64, f64) {
+    let mut p_n = 1.0;
+    let mut p_n_minus_1 = 0.0;
+    for k in 1..=n {
+        let p_n_minus_2 = p_n_minus_1;
+        p_n_minus_1 = p_n;
+        p_n = ((2 * k - 1) as f64 * x * p_n_minus_1 - (k - 1) as f64 * p_n_minus_2) / k as f64;
+    }
+    (p_n, p_n_minus_1)
+}
+
+/// Integrates `f` from `a` to `b` with `n`-point Gauss-Legendre quadrature
+pub fn integrate<F: Fn(f64) -> f64>(f: F, a: f64, b: f64, n: usize) -> f64 {
+    let (nodes, weights) = gauss_legendre(n);
+    let half_width = (b - a) / 2.0;
+    let mid = (a + b) / 2.0;
+    nodes
+        .iter()
+        .zip(&weights)
+        .map(|(x, w)| w * f(mid + half_width * x))
+        .sum::<f64>()
+        * half_width
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::quadrature::{gauss_legendre, integrate};
+
+    #[test]
+    fn weights_sum_to_two() {
+        for n in &[1, 2, 5, 16, 31] {
+            let (nodes, weights) = gauss_legendre(*n);
+            assert_eq!(*n, nodes.len());
+            assert!((weights.iter().sum::<f64>() - 2.0).abs() < 1e-13);
+        }
+    }
+
+    #[test]
+    fn exact_for_polynomials() {
+        // 4 points integrate up to degree 7 exactly
+        let result = integrate(|x| x.powi(7) - 3.0 * x.powi(2), 0.0, 2.0, 4);
+        assert!((result - (32.0 - 8.0)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn smooth_integrand() {
+        let result = integrate(|x| x.cos(), 0.0, std::f64::consts::FRAC_PI_2, 12);
+        assert!((result - 1.0).abs() < 1e-14);
+    }
+}
@@ -0,0 +1,171 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+License, v. 2.0. If a copy of the MPL was not distributed with this
+file, You can obtain one at https://mozilla.org/MPL/2.0/.
+Copyright 2021 Peter Dunne */
+//! Pseudo-random numbers
+//!
+//! A small, seedable generator for reproducible sampling, e.g. of the easy
+//! axes of bonded magnets. It is not suitable for cryptography.
+
+use crate::PI;
+
+/// A SplitMix64 pseudo-random number generator
+///
+/// The same seed always gives the same sequence.
+#[derive(Copy, Clone, Debug)]
+pub struct Rng {
+    pub state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng { state: seed }
+    }
+
+    /// Returns the next 64 random bits
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a uniform sample in $`[0, 1)`$
+    pub fn uniform(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Returns a sample of the normal distribution with mean `mu` and
+    /// standard deviation `sigma`, by the Box-Muller transform
+    pub fn gaussian(&mut self, mu: f64, sigma: f64) -> f64 {
+        // Strictly positive, so the logarithm is finite
+        let u = 1.0 - self.uniform();
+        let v = self.uniform();
+        mu + sigma * (-2.0 * u.ln()).sqrt() * (2.0 * PI * v).cos()
+    }
+
+    /// Returns an angle, in radians, from the von Mises distribution with
+    /// mean `mu` and concentration `kappa`,
+    ///
+    /// ```math
+    /// p(\theta) = \frac{e^{\kappa \cos(\theta - \mu)}}{2 \pi I_0(\kappa)}
+    /// ```
+    ///
+    /// the circular analogue of the normal distribution, with a spread of
+    /// about $`1/\sqrt{\kappa}`$ for large `kappa` and uniform for
+    /// `kappa = 0`. Uses the rejection method of Best and Fisher (1979).
+    pub fn von_mises(&mut self, mu: f64, kappa: f64) -> f64 {
+        if kappa < 1e-8 {
+            return mu + PI * (2.0 * self.uniform() - 1.0);
+        }
+
+        let tau = 1.0 + (1.0 + 4.0 * kappa.powi(2)).sqrt();
+        let rho = (tau - (2.0 * tau).sqrt()) / (2.0 * kappa);
+        let r = (1.0 + rho.powi(2)) / (2.0 * rho);
+
+        loop {
+            let z = (PI * self.uniform()).cos();
+            let f = (1.0 + r * z) / (r + z);
+            let c = kappa * (r - f);
+            // Strictly positive, so the logarithm below is finite
+            let u = 1.0 - self.uniform();
+            if c * (2.0 - c) > u || (c / u).ln() + 1.0 - c >= 0.0 {
+                let sign = if self.uniform() < 0.5 { -1.0 } else { 1.0 };
+                return mu + sign * f.clamp(-1.0, 1.0).acos();
+            }
+        }
+    }
+}
+
+/// Returns the mean resultant length $`I_1(\kappa) / I_0(\kappa)`$ of the
+/// von Mises distribution with concentration `kappa`, the mean of
+/// $`\cos(\theta - \mu)`$, rising from 0 for `kappa = 0` towards 1.
+///
+/// The Bessel functions are summed from their power series for small
+/// `kappa`, and from their asymptotic expansions for large `kappa`, which
+/// would otherwise overflow.
+pub fn von_mises_resultant_length(kappa: f64) -> f64 {
+    if kappa < 30.0 {
+        let t = (kappa / 2.0).powi(2);
+        let (mut i0, mut i1) = (0.0, 0.0);
+        let mut term = 1.0;
+        for k in 0..100 {
+            let k = k as f64;
+            i0 += term;
+            i1 += term / (k + 1.0);
+            term *= t / (k + 1.0).powi(2);
+        }
+        kappa / 2.0 * i1 / i0
+    } else {
+        // I_nu(kappa) = e^kappa / sqrt(2 pi kappa) sum_k (-1)^k a_k(nu) / kappa^k
+        let (mut i0, mut i1) = (0.0, 0.0);
+        let (mut a0, mut a1) = (1.0, 1.0);
+        for k in 1..=12 {
+            i0 += a0;
+            i1 += a1;
+            let odd = (2 * k - 1) as f64;
+            let step = -1.0 / (8.0 * k as f64 * kappa);
+            a0 *= step * (0.0 - odd.powi(2));
+            a1 *= step * (4.0 - odd.powi(2));
+        }
+        i1 / i0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::random::{von_mises_resultant_length, Rng};
+
+    #[test]
+    fn uniform_is_reproducible_and_in_range() {
+        let mut first = Rng::new(7);
+        let mut second = Rng::new(7);
+        for _ in 0..1000 {
+            let u = first.uniform();
+            assert_eq!(u, second.uniform());
+            assert!((0.0..1.0).contains(&u));
+        }
+    }
+
+    #[test]
+    fn von_mises_mean_resultant_length() {
+        // E[cos(theta - mu)] = I_1(kappa) / I_0(kappa), 0.697775 for kappa = 2
+        let mut rng = Rng::new(42);
+        let n = 20000;
+        let (mut c, mut s) = (0.0, 0.0);
+        for _ in 0..n {
+            let theta = rng.von_mises(0.5, 2.0) - 0.5;
+            c += theta.cos();
+            s += theta.sin();
+        }
+        assert!((c / n as f64 - 0.697775).abs() < 0.01);
+        assert!((s / n as f64).abs() < 0.01);
+    }
+
+    #[test]
+    fn resultant_length_series_and_asymptotics() {
+        assert_eq!(0.0, von_mises_resultant_length(0.0));
+        assert!((von_mises_resultant_length(2.0) - 0.697_774_658).abs() < 1e-9);
+        // The series and asymptotic expansion agree where they meet
+        let (below, above) = (
+            von_mises_resultant_length(30.0 - 1e-9),
+            von_mises_resultant_length(30.0),
+        );
+        assert!((below - above).abs() < 1e-12);
+        let kappa = 1e5;
+        let expected = 1.0 - 1.0 / (2.0 * kappa) - 1.0 / (8.0 * kappa * kappa);
+        assert!((von_mises_resultant_length(kappa) - expected).abs() < 1e-14);
+    }
+
+    #[test]
+    fn gaussian_moments() {
+        let mut rng = Rng::new(3);
+        let n = 20000;
+        let samples: Vec<f64> = (0..n).map(|_| rng.gaussian(1.5, 0.5)).collect();
+        let mean = samples.iter().sum::<f64>() / n as f64;
+        let variance = samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / n as f64;
+        assert!((mean - 1.5).abs() < 0.01);
+        assert!((variance.sqrt() - 0.5).abs() < 0.01);
+    }
+}